@@ -1,16 +1,28 @@
-use anyhow::Result;
-use clap::{Parser, Subcommand};
-use serde::Serialize;
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use ledger_core::{pow::count_leading_zero_bits, Hash, Transaction};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use tracing_subscriber::{fmt, EnvFilter};
 
 #[derive(Parser, Debug)]
 #[command(name = "ledger-cli")]
 #[command(about = "CLI client for the minimal ledger node")]
 struct Cli {
+    /// Output format: `table` for humans, `json` for one `{"status","body"}` object per command
+    #[arg(long, global = true, value_enum, default_value_t = Format::Table)]
+    format: Format,
     #[command(subcommand)]
     cmd: Command,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Table,
+    Json,
+}
+
 #[derive(Subcommand, Debug)]
 enum Command {
     /// Submit a transaction
@@ -28,6 +40,60 @@ enum Command {
         #[arg(long)]
         amount: u64,
     },
+    /// Fetch a block with all its transactions and pretty-print it
+    DumpBlock {
+        /// Node base URL (e.g. http://127.0.0.1:8080)
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        node: String,
+        /// Block index
+        #[arg(long)]
+        index: u64,
+        /// Print the raw JSON returned by the node
+        #[arg(long)]
+        json: bool,
+    },
+    /// Ask the node to mine a block from its mempool
+    Mine {
+        /// Node base URL (e.g. http://127.0.0.1:8080)
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        node: String,
+        /// Required leading zero bits in the block hash
+        #[arg(long, default_value_t = 20)]
+        target: u32,
+        /// Optional data payload to seal into the block
+        #[arg(long)]
+        data: Option<String>,
+    },
+    /// Show the chain height
+    Head {
+        /// Node base URL (e.g. http://127.0.0.1:8080)
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        node: String,
+    },
+    /// Show the chain height and tip hash
+    Tip {
+        /// Node base URL (e.g. http://127.0.0.1:8080)
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        node: String,
+    },
+    /// Show a block as JSON
+    Block {
+        /// Node base URL (e.g. http://127.0.0.1:8080)
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        node: String,
+        /// Block index
+        #[arg(long)]
+        index: u64,
+    },
+    /// Show an account's balance
+    Balance {
+        /// Node base URL (e.g. http://127.0.0.1:8080)
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        node: String,
+        /// Account name
+        #[arg(long)]
+        account: String,
+    },
 }
 
 #[derive(Serialize)]
@@ -37,15 +103,70 @@ struct Tx {
     amount: u64,
 }
 
+/// Mirror of the node's `/chain/block/{index}` response.
+#[derive(Debug, Deserialize)]
+struct BlockDetail {
+    index: u64,
+    ts: u64,
+    nonce: u64,
+    hash: String,
+    previous_hash: String,
+    merkle_root: String,
+    #[serde(default)]
+    data_hash: Option<String>,
+    #[serde(default)]
+    data: Option<String>,
+    confirmations: u64,
+    txs: Vec<Transaction>,
+}
+
+/// Mirror of the node's `/mine` response.
+#[derive(Debug, Deserialize)]
+struct MineResult {
+    mined: bool,
+    #[serde(default)]
+    height: Option<u64>,
+    #[serde(default)]
+    nonce: Option<u64>,
+    #[serde(default)]
+    hash: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     fmt()
         .with_env_filter(EnvFilter::from_default_env())
         .pretty()
+        .with_writer(std::io::stderr)
         .init();
 
     let cli = Cli::parse();
-    match cli.cmd {
+    print!("{}", run(cli.cmd, cli.format).await?);
+    Ok(())
+}
+
+/// How long a request may take before the node counts as unreachable.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `/mine` searches before it answers; the node gives up on its own after
+/// `--max-mine-secs` (300 by default), so allow a little longer.
+const MINE_TIMEOUT: Duration = Duration::from_secs(330);
+
+/// Client shared by every command, so a hung node fails the command
+/// instead of blocking it forever.
+fn http_client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .pool_max_idle_per_host(4)
+        .build()
+        .context("failed to build HTTP client")
+}
+
+/// Execute `cmd` and return what it should print in `format`.
+async fn run(cmd: Command, format: Format) -> Result<String> {
+    let out = match cmd {
         Command::Submit {
             node,
             from,
@@ -53,13 +174,439 @@ async fn main() -> Result<()> {
             amount,
         } => {
             let tx = Tx { from, to, amount };
-            let client = reqwest::Client::new();
+            let client = http_client()?;
             let res = client.post(format!("{node}/tx")).json(&tx).send().await?;
             let status = res.status();
             let body = res.text().await?;
-            println!("status: {}", status);
-            println!("{body}");
+            match format {
+                Format::Json => envelope(status, &body)?,
+                Format::Table => format!("status: {status}\n{body}\n"),
+            }
+        }
+        Command::DumpBlock { node, index, json } => {
+            let client = http_client()?;
+            let res = client
+                .get(format!("{node}/chain/block/{index}"))
+                .send()
+                .await
+                .with_context(|| format!("failed to reach node at {node}"))?;
+            let status = res.status();
+            let body = res.text().await?;
+            // JSON mode passes failures through as they came, error and all
+            if format == Format::Json {
+                envelope(status, &body)?
+            } else if status == reqwest::StatusCode::NOT_FOUND {
+                bail!("block {index} not found on {node}");
+            } else if !status.is_success() {
+                bail!("node returned {status}: {body}");
+            } else if json {
+                format!("{body}\n")
+            } else {
+                let block: BlockDetail = serde_json::from_str(&body)
+                    .context("failed to decode block returned by node")?;
+                format_block(&block)?
+            }
         }
+        Command::Mine { node, target, data } => {
+            let mut params = vec![("target", target.to_string())];
+            params.extend(data.map(|d| ("data", d)));
+            let res = http_client()?
+                .get(format!("{node}/mine"))
+                .query(&params)
+                .timeout(MINE_TIMEOUT)
+                .send()
+                .await
+                .with_context(|| format!("failed to reach node at {node}"))?;
+            let status = res.status();
+            let body = res.text().await?;
+            // JSON mode passes failures through as they came, error and all
+            if format == Format::Json {
+                envelope(status, &body)?
+            } else if !status.is_success() {
+                bail!("node returned {status}: {body}");
+            } else {
+                let result: MineResult = serde_json::from_str(&body)
+                    .context("failed to decode mine result returned by node")?;
+                format_mine(&result)?
+            }
+        }
+        Command::Head { node } => query(&node, "/chain/head", format).await?,
+        Command::Tip { node } => query(&node, "/chain/tip", format).await?,
+        Command::Block { node, index } => {
+            query(&node, &format!("/chain/block/{index}"), format).await?
+        }
+        Command::Balance { node, account } => {
+            let account = utf8_percent_encode(&account, PATH_SEGMENT);
+            query(&node, &format!("/balance/{account}"), format).await?
+        }
+    };
+    Ok(out)
+}
+
+/// Bytes escaped when a value is spliced into one URL path segment: all but
+/// the RFC 3986 unreserved characters, so `/`, `?` and `#` cannot reshape the path.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// GET `path` from the node and pretty-print the JSON it returns. Any
+/// non-success status is an error, so the process exits non-zero; JSON mode
+/// instead wraps it in the [`envelope`] like a success.
+async fn query(node: &str, path: &str, format: Format) -> Result<String> {
+    let res = http_client()?
+        .get(format!("{node}{path}"))
+        .send()
+        .await
+        .with_context(|| format!("failed to reach node at {node}"))?;
+    let status = res.status();
+    if format == Format::Json {
+        return envelope(status, &res.text().await?);
+    }
+    if !status.is_success() {
+        bail!("node returned {status} for {path}: {}", res.text().await?);
+    }
+    let body: serde_json::Value = res
+        .json()
+        .await
+        .with_context(|| format!("node returned invalid JSON for {path}"))?;
+    Ok(format!("{}\n", serde_json::to_string_pretty(&body)?))
+}
+
+/// One-line `{"status":..,"body":..}` for `--format json`. A body that is not
+/// JSON (e.g. a plain-text error from the node) is kept as a string.
+fn envelope(status: reqwest::StatusCode, body: &str) -> Result<String> {
+    let body =
+        serde_json::from_str(body).unwrap_or_else(|_| serde_json::Value::String(body.to_string()));
+    Ok(format!(
+        "{}\n",
+        serde_json::to_string(&serde_json::json!({ "status": status.as_u16(), "body": body }))?
+    ))
+}
+
+/// One line per field for a mined block; a failed attempt is an error.
+fn format_mine(r: &MineResult) -> Result<String> {
+    if !r.mined {
+        bail!(
+            "block not mined: {}",
+            r.error.as_deref().unwrap_or("unknown error")
+        );
+    }
+    let field = |v: Option<String>| v.unwrap_or_else(|| "-".to_string());
+    Ok(format!(
+        "height : {}\nnonce  : {}\nhash   : {}\n",
+        field(r.height.map(|h| h.to_string())),
+        field(r.nonce.map(|n| n.to_string())),
+        field(r.hash.clone()),
+    ))
+}
+
+/// Render a block and its transactions for humans, recomputing the hash's leading zero bits.
+fn format_block(b: &BlockDetail) -> Result<String> {
+    let mut hash: Hash = Default::default();
+    hex::decode_to_slice(&b.hash, &mut hash).context("block hash is not 32 bytes of hex")?;
+
+    let mut out = String::new();
+    out.push_str(&format!("Block #{}\n", b.index));
+    out.push_str(&format!("  hash          : {}\n", b.hash));
+    out.push_str(&format!("  previous_hash : {}\n", b.previous_hash));
+    out.push_str(&format!("  merkle_root   : {}\n", b.merkle_root));
+    out.push_str(&format!(
+        "  data_hash     : {}\n",
+        b.data_hash.as_deref().unwrap_or("-")
+    ));
+    out.push_str(&format!("  timestamp     : {}\n", b.ts));
+    out.push_str(&format!("  nonce         : {}\n", b.nonce));
+    out.push_str(&format!(
+        "  leading zeros : {}\n",
+        count_leading_zero_bits(&hash)
+    ));
+    out.push_str(&format!("  confirmations : {}\n", b.confirmations));
+    out.push_str(&format!(
+        "  data          : {}\n",
+        b.data.as_deref().unwrap_or("-")
+    ));
+    out.push_str(&format!("  transactions  : {}\n", b.txs.len()));
+    for (i, tx) in b.txs.iter().enumerate() {
+        out.push_str(&format!(
+            "    [{i}] {} -> {} amount={} ts={}\n",
+            tx.from, tx.to, tx.amount, tx.timestamp
+        ));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    async fn node_returning(route: &str, status: u16, body: serde_json::Value) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(route))
+            .respond_with(ResponseTemplate::new(status).set_body_json(body))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn run_args(args: &[&str]) -> Result<String> {
+        let cli = Cli::try_parse_from([&["ledger-cli"], args].concat()).unwrap();
+        run(cli.cmd, cli.format).await
+    }
+
+    #[tokio::test]
+    async fn head_and_tip() {
+        let server = node_returning("/chain/head", 200, serde_json::json!({ "height": 7 })).await;
+        let out = run_args(&["head", "--node", &server.uri()]).await.unwrap();
+        assert!(out.contains("\"height\": 7"), "{out}");
+
+        let server = node_returning(
+            "/chain/tip",
+            200,
+            serde_json::json!({ "height": 7, "hash": "00ab" }),
+        )
+        .await;
+        let out = run_args(&["tip", "--node", &server.uri()]).await.unwrap();
+        assert!(out.contains("\"hash\": \"00ab\""), "{out}");
+    }
+
+    #[tokio::test]
+    async fn block_by_index() {
+        let server = node_returning(
+            "/chain/block/3",
+            200,
+            serde_json::json!({ "index": 3, "confirmations": 1, "txs": [] }),
+        )
+        .await;
+        let out = run_args(&["block", "--node", &server.uri(), "--index", "3"])
+            .await
+            .unwrap();
+        assert!(out.contains("\"index\": 3"), "{out}");
+        assert!(out.contains("\"confirmations\": 1"), "{out}");
+    }
+
+    #[tokio::test]
+    async fn balance_of_account() {
+        let server = node_returning(
+            "/balance/alice",
+            200,
+            serde_json::json!({ "account": "alice", "balance": 42 }),
+        )
+        .await;
+        let out = run_args(&["balance", "--node", &server.uri(), "--account", "alice"])
+            .await
+            .unwrap();
+        assert!(out.contains("\"balance\": 42"), "{out}");
+    }
+
+    #[tokio::test]
+    async fn http_error_is_an_error() {
+        let server = node_returning(
+            "/chain/block/9",
+            404,
+            serde_json::json!({ "error": "block 9 not found" }),
+        )
+        .await;
+        let err = run_args(&["block", "--node", &server.uri(), "--index", "9"])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("404"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn mine_success() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/mine"))
+            .and(query_param("target", "20"))
+            .and(query_param("data", "hello"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "mined": true,
+                "height": 4,
+                "nonce": 1234,
+                "hash": "00000abc",
+                "tx_count": 2,
+            })))
+            .mount(&server)
+            .await;
+        let out = run_args(&["mine", "--node", &server.uri(), "--data", "hello"])
+            .await
+            .unwrap();
+        assert!(out.contains("height : 4"), "{out}");
+        assert!(out.contains("nonce  : 1234"), "{out}");
+        assert!(out.contains("hash   : 00000abc"), "{out}");
+    }
+
+    #[tokio::test]
+    async fn mine_failure() {
+        let server = node_returning(
+            "/mine",
+            200,
+            serde_json::json!({ "mined": false, "error": "timeout" }),
+        )
+        .await;
+        let err = run_args(&["mine", "--node", &server.uri(), "--target", "8"])
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "block not mined: timeout");
+    }
+
+    #[tokio::test]
+    async fn mine_failure_json_format() {
+        let failed = serde_json::json!({ "mined": false, "error": "timeout" });
+        let server = node_returning("/mine", 200, failed.clone()).await;
+        let out = run_args(&["--format", "json", "mine", "--node", &server.uri()])
+            .await
+            .unwrap();
+        let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(v, serde_json::json!({ "status": 200, "body": failed }));
+
+        let server = node_returning("/mine", 500, serde_json::json!({ "error": "disk" })).await;
+        let out = run_args(&["--format", "json", "mine", "--node", &server.uri()])
+            .await
+            .unwrap();
+        let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(v["status"], 500);
+        assert_eq!(v["body"]["error"], "disk");
+    }
+
+    #[tokio::test]
+    async fn submit_json_format() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/tx"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({ "ok": true })),
+            )
+            .mount(&server)
+            .await;
+        let out = run_args(&[
+            "--format",
+            "json",
+            "submit",
+            "--node",
+            &server.uri(),
+            "--from",
+            "alice",
+            "--to",
+            "bob",
+            "--amount",
+            "5",
+        ])
+        .await
+        .unwrap();
+        assert_eq!(out.lines().count(), 1, "{out}");
+        let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(
+            v,
+            serde_json::json!({ "status": 200, "body": { "ok": true } })
+        );
+    }
+
+    #[tokio::test]
+    async fn query_json_format() {
+        let server = node_returning("/chain/head", 200, serde_json::json!({ "height": 7 })).await;
+        // the flag is global, so it may follow the subcommand too
+        let out = run_args(&["head", "--node", &server.uri(), "--format", "json"])
+            .await
+            .unwrap();
+        let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(v["status"], 200);
+        assert_eq!(v["body"]["height"], 7);
+    }
+
+    #[tokio::test]
+    async fn query_json_format_http_errors() {
+        let missing = serde_json::json!({ "error": "block 9 not found" });
+        let server = node_returning("/chain/block/9", 404, missing.clone()).await;
+        let out = run_args(&[
+            "--format",
+            "json",
+            "block",
+            "--node",
+            &server.uri(),
+            "--index",
+            "9",
+        ])
+        .await
+        .unwrap();
+        let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(v, serde_json::json!({ "status": 404, "body": missing }));
+
+        let server =
+            node_returning("/chain/head", 500, serde_json::json!({ "error": "disk" })).await;
+        let out = run_args(&["--format", "json", "head", "--node", &server.uri()])
+            .await
+            .unwrap();
+        let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(v["status"], 500);
+        assert_eq!(v["body"]["error"], "disk");
+    }
+
+    #[tokio::test]
+    async fn dump_block_json_format_http_errors() {
+        let missing = serde_json::json!({ "error": "block 9 not found" });
+        let server = node_returning("/chain/block/9", 404, missing.clone()).await;
+        let args = [
+            "--format",
+            "json",
+            "dump-block",
+            "--node",
+            &server.uri(),
+            "--index",
+            "9",
+        ];
+        let out = run_args(&args).await.unwrap();
+        let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(v, serde_json::json!({ "status": 404, "body": missing }));
+
+        let server = node_returning(
+            "/chain/block/9",
+            500,
+            serde_json::json!({ "error": "disk" }),
+        )
+        .await;
+        let args = [
+            "--format",
+            "json",
+            "dump-block",
+            "--node",
+            &server.uri(),
+            "--index",
+            "9",
+        ];
+        let out = run_args(&args).await.unwrap();
+        let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(v["status"], 500);
+        assert_eq!(v["body"]["error"], "disk");
+
+        // table mode still fails
+        let args = ["dump-block", "--node", &server.uri(), "--index", "9"];
+        assert!(run_args(&args)
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("500"));
+    }
+
+    #[tokio::test]
+    async fn balance_escapes_the_account() {
+        let body = serde_json::json!({ "balance": 3 });
+        let server = node_returning("/balance/a%2Fb%3Fc%20d", 200, body).await;
+        let out = run_args(&["balance", "--node", &server.uri(), "--account", "a/b?c d"])
+            .await
+            .unwrap();
+        assert!(out.contains("\"balance\": 3"), "{out}");
+    }
+
+    #[test]
+    fn index_must_be_a_number() {
+        assert!(Cli::try_parse_from(["ledger-cli", "block", "--index", "tip"]).is_err());
     }
-    Ok(())
 }
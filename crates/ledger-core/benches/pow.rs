@@ -0,0 +1,65 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use ledger_core::{
+    block_data_hash, mine::mine_block_parallel_at, pow::mine_block, Block, BlockHeader, Transaction,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Every transaction and header is dated this, so runs hash the same bytes.
+const TIMESTAMP: u64 = 1_600_000_000;
+
+fn seeded_block() -> Block {
+    let mut rng = StdRng::seed_from_u64(42);
+    let txs: Vec<Transaction> = (0..10)
+        .map(|i| Transaction {
+            from: format!("alice-{i}"),
+            to: "bob".into(),
+            amount: rng.gen_range(1..10),
+            timestamp: TIMESTAMP,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        })
+        .collect();
+
+    let header = BlockHeader::new_at(
+        0,
+        [0u8; 32],
+        block_data_hash(&None),
+        ledger_core::merkle_root(&txs),
+        0,
+        TIMESTAMP,
+    );
+    Block {
+        header,
+        data: None,
+        txs,
+    }
+}
+
+fn bench_pow(c: &mut Criterion) {
+    c.bench_function("mine_block_target_20", |b| {
+        let block = seeded_block();
+        b.iter(|| {
+            let _mined = mine_block_parallel_at(
+                block.header.index,
+                block.header.previous_hash,
+                block.txs.clone(),
+                block.data.clone(),
+                20,
+                block.header.timestamp,
+            )
+            .unwrap();
+        });
+    });
+
+    // single-threaded and nonce-ordered, so every iteration does the same work
+    c.bench_function("mine_block_single_thread_target_16", |b| {
+        let block = seeded_block();
+        b.iter(|| {
+            let _mined = mine_block(block.clone(), 16);
+        });
+    });
+}
+
+criterion_group!(benches, bench_pow);
+criterion_main!(benches);
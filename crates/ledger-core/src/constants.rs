@@ -1,6 +1,19 @@
 pub const BYTE: usize = 8;
 pub const HASH_SIZE: usize = 32;
 pub const HASH_HEX_SIZE: usize = HASH_SIZE * 2;
+/// Rows `/chain/blocks` returns when no `limit` is given. This is the only
+/// definition; the node and its clients import it from here.
 pub const BLOCKS_PER_BATCH: u32 = 99;
+/// Largest `limit` `/chain/blocks` honours.
 pub const MAX_BLOCKS_PER_REQUEST: u32 = BLOCKS_PER_BATCH * 10;
+/// Longest `from`/`to` account name `Transaction::validate` accepts, in characters.
+pub const MAX_ACCOUNT_NAME_LEN: usize = 64;
+/// Most transactions, coinbase included, a mined block may hold.
+pub const MAX_TXS_PER_BLOCK: usize = 1_000;
 pub const POW_TARGET_DIFFICULTY: u32 = 20;
+/// Block interval `Chain::mine_next_block` retargets towards.
+pub const TARGET_BLOCK_SECS: u64 = 10;
+/// Number of recent headers retargeting looks at.
+pub const DIFFICULTY_WINDOW: usize = 10;
+/// How far ahead of the validator's clock a block timestamp may be, in seconds.
+pub const MAX_FUTURE_DRIFT_SECS: u64 = 7200;
@@ -0,0 +1,153 @@
+//! Difficulty retargeting.
+//!
+//! Headers do not record the target they were mined at, so the current target
+//! is taken as the fewest leading zero bits among the headers in the window:
+//! every one of them met the target, so that is its tightest known bound.
+
+use crate::{
+    constants::POW_TARGET_DIFFICULTY, digest::HashAlgo, pow::count_leading_zero_bits, BlockHeader,
+};
+
+/// Easiest target retargeting will go down to.
+pub const MIN_TARGET: u32 = 1;
+/// Hardest target retargeting will go up to.
+pub const MAX_TARGET: u32 = 28;
+
+/// Target for the block after `headers`, which must be in ascending order.
+///
+/// Looks at the last `window` headers and compares the time they spanned to
+/// the expected `desired_secs_per_block` per interval: blocks arriving faster
+/// than desired raise the target by one bit, slower lowers it by one. With
+/// fewer than two headers there is nothing to measure and
+/// [`POW_TARGET_DIFFICULTY`] is returned. Headers are hashed with SHA-256;
+/// see [`next_target_with`] for other algorithms.
+pub fn next_target(headers: &[BlockHeader], desired_secs_per_block: u64, window: usize) -> u32 {
+    next_target_with(HashAlgo::Sha256, headers, desired_secs_per_block, window)
+}
+
+/// [`next_target`] for a chain whose headers are hashed with `algo`.
+pub fn next_target_with(
+    algo: HashAlgo,
+    headers: &[BlockHeader],
+    desired_secs_per_block: u64,
+    window: usize,
+) -> u32 {
+    let recent = &headers[headers.len().saturating_sub(window)..];
+    let (Some(first), Some(last)) = (recent.first(), recent.last()) else {
+        return POW_TARGET_DIFFICULTY;
+    };
+    if recent.len() < 2 {
+        return POW_TARGET_DIFFICULTY;
+    }
+
+    let current = recent
+        .iter()
+        .map(|h| count_leading_zero_bits(&algo.header_hash(*h)))
+        .min()
+        .unwrap_or(POW_TARGET_DIFFICULTY);
+    let intervals = (recent.len() - 1) as u64;
+    let expected = intervals.saturating_mul(desired_secs_per_block);
+    let actual = last.timestamp.saturating_sub(first.timestamp);
+
+    let next = match actual.cmp(&expected) {
+        std::cmp::Ordering::Less => current.saturating_add(1),
+        std::cmp::Ordering::Greater => current.saturating_sub(1),
+        std::cmp::Ordering::Equal => current,
+    };
+    next.clamp(MIN_TARGET, MAX_TARGET)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block_header_hash, constants::HASH_SIZE, pow::mine_genesis_block, Block};
+
+    /// Headers mined at `target` with `spacing` seconds between them.
+    fn headers(n: u64, target: u32, spacing: u64) -> Vec<BlockHeader> {
+        let mut out: Vec<BlockHeader> = Vec::new();
+        for index in 0..n {
+            let previous_hash = out
+                .last()
+                .map(|h| block_header_hash(*h))
+                .unwrap_or([0u8; HASH_SIZE]);
+            let header = BlockHeader::new_at(
+                index,
+                previous_hash,
+                [0u8; HASH_SIZE],
+                [0u8; HASH_SIZE],
+                0,
+                1_600_000_000 + index * spacing,
+            );
+            let block = Block {
+                header,
+                data: None,
+                txs: vec![],
+            };
+            out.push(mine_genesis_block(block, target).header);
+        }
+        out
+    }
+
+    fn current(headers: &[BlockHeader]) -> u32 {
+        headers
+            .iter()
+            .map(|h| count_leading_zero_bits(&block_header_hash(*h)))
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    fn fast_blocks_raise_target() {
+        let hs = headers(6, 4, 1);
+        assert_eq!(next_target(&hs, 10, 5), current(&hs[1..]) + 1);
+    }
+
+    #[test]
+    fn slow_blocks_lower_target() {
+        let hs = headers(6, 4, 60);
+        assert_eq!(next_target(&hs, 10, 5), current(&hs[1..]) - 1);
+    }
+
+    #[test]
+    fn clamped_and_defaulted() {
+        assert_eq!(next_target(&[], 10, 5), POW_TARGET_DIFFICULTY);
+        assert_eq!(next_target(&headers(1, 1, 1), 10, 5), POW_TARGET_DIFFICULTY);
+        let hs = headers(4, 0, 600);
+        assert!(next_target(&hs, 10, 4) >= MIN_TARGET);
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn blake3_headers_measured_with_blake3() {
+        use crate::pow::mine_block_with_algo;
+        let algo = HashAlgo::Blake3;
+        let mut hs: Vec<BlockHeader> = Vec::new();
+        for index in 0..6u64 {
+            let previous_hash = hs
+                .last()
+                .map(|h| algo.header_hash(*h))
+                .unwrap_or([0u8; HASH_SIZE]);
+            let header = BlockHeader::new_at(
+                index,
+                previous_hash,
+                [0u8; HASH_SIZE],
+                [0u8; HASH_SIZE],
+                0,
+                1_600_000_000 + index,
+            );
+            let block = Block {
+                header,
+                data: None,
+                txs: vec![],
+            };
+            hs.push(mine_block_with_algo(block, 8, algo).header);
+        }
+        let current = hs[1..]
+            .iter()
+            .map(|h| count_leading_zero_bits(&algo.header_hash(*h)))
+            .min()
+            .unwrap();
+        assert!(current >= 8);
+        assert_eq!(next_target_with(algo, &hs, 10, 5), current + 1);
+    }
+}
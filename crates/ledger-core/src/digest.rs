@@ -0,0 +1,228 @@
+//! The 32-byte digest used for block, data and merkle hashes.
+//!
+//! Hashing goes through [`Digest32`] so the merkle and header code can be
+//! reused with another function; [`Sha256Hasher`] is what the chain uses
+//! unless it was created with another [`HashAlgo`].
+
+use crate::{
+    block_data_hash_with, block_header_hash_with, merkle_root_with, Block, BlockHeader, Hash,
+    Transaction,
+};
+use sha2::{Digest, Sha256};
+use std::{fmt, str::FromStr};
+
+/// A hash function with a 32-byte output, plugged into the generic
+/// header, data and merkle hashing.
+pub trait Digest32 {
+    /// Hash `data` in one shot.
+    fn digest(data: &[u8]) -> Hash;
+}
+
+/// SHA-256, the digest every stored block is hashed with.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256Hasher;
+
+impl Digest32 for Sha256Hasher {
+    fn digest(data: &[u8]) -> Hash {
+        Sha256::digest(data).into()
+    }
+}
+
+/// BLAKE3, several times faster than SHA-256 (`blake3` feature).
+#[cfg(feature = "blake3")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Blake3Hasher;
+
+#[cfg(feature = "blake3")]
+impl Digest32 for Blake3Hasher {
+    fn digest(data: &[u8]) -> Hash {
+        blake3::hash(data).into()
+    }
+}
+
+/// The [`Digest32`] a chain hashes headers, data and merkle trees with,
+/// chosen at runtime. A chain is created with one and never mixes them: a
+/// non-SHA-256 algorithm is recorded in the genesis block's data. The
+/// default is [`HashAlgo::Blake3`] when the `blake3` feature is on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HashAlgo {
+    #[cfg_attr(not(feature = "blake3"), default)]
+    Sha256,
+    #[cfg(feature = "blake3")]
+    #[default]
+    Blake3,
+}
+
+impl HashAlgo {
+    /// Lowercase name, as accepted by `--hash-algo` and recorded in genesis.
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgo::Sha256 => "sha256",
+            #[cfg(feature = "blake3")]
+            HashAlgo::Blake3 => "blake3",
+        }
+    }
+
+    /// Hash of `header`: what proof of work is measured on.
+    pub fn header_hash(self, header: BlockHeader) -> Hash {
+        match self {
+            HashAlgo::Sha256 => block_header_hash_with::<Sha256Hasher>(header),
+            #[cfg(feature = "blake3")]
+            HashAlgo::Blake3 => block_header_hash_with::<Blake3Hasher>(header),
+        }
+    }
+
+    /// Merkle root of `txs`, as committed to in the header.
+    pub fn merkle_root(self, txs: &[Transaction]) -> Hash {
+        match self {
+            HashAlgo::Sha256 => merkle_root_with::<Sha256Hasher>(txs),
+            #[cfg(feature = "blake3")]
+            HashAlgo::Blake3 => merkle_root_with::<Blake3Hasher>(txs),
+        }
+    }
+
+    /// Hash of a block's optional `data`, as committed to in the header.
+    pub fn data_hash(self, data: &Option<String>) -> Hash {
+        match self {
+            HashAlgo::Sha256 => block_data_hash_with::<Sha256Hasher>(data),
+            #[cfg(feature = "blake3")]
+            HashAlgo::Blake3 => block_data_hash_with::<Blake3Hasher>(data),
+        }
+    }
+
+    /// `data` with this algorithm recorded in it, for a new genesis block.
+    /// SHA-256 leaves it untouched, so existing chains read as SHA-256.
+    pub fn tag_genesis_data(self, data: String) -> String {
+        match self {
+            HashAlgo::Sha256 => data,
+            #[allow(unreachable_patterns)]
+            other => format!("{data} {GENESIS_TAG}{}]", other.name()),
+        }
+    }
+
+    /// The algorithm recorded in `genesis` by [`HashAlgo::tag_genesis_data`].
+    pub fn of_genesis(genesis: &Block) -> Result<Self, String> {
+        let data = genesis.data.as_deref().unwrap_or_default();
+        match data
+            .rsplit_once(GENESIS_TAG)
+            .and_then(|(_, rest)| rest.strip_suffix(']'))
+        {
+            Some(name) => name.parse(),
+            None => Ok(HashAlgo::Sha256),
+        }
+    }
+}
+
+const GENESIS_TAG: &str = "[hash-algo=";
+
+impl fmt::Display for HashAlgo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for HashAlgo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(HashAlgo::Sha256),
+            #[cfg(feature = "blake3")]
+            "blake3" => Ok(HashAlgo::Blake3),
+            #[cfg(not(feature = "blake3"))]
+            "blake3" => {
+                Err("blake3 support was not compiled in (enable the `blake3` feature)".into())
+            }
+            other => Err(format!("unknown hash algorithm {other:?}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block_data_hash, block_data_hash_with, merkle_root, merkle_root_with, Transaction,
+    };
+
+    /// Not a real hash: the input length in the first byte, enough to tell digests apart.
+    struct LengthDigest;
+
+    impl Digest32 for LengthDigest {
+        fn digest(data: &[u8]) -> Hash {
+            let mut out = [0u8; 32];
+            out[0] = data.len() as u8;
+            out
+        }
+    }
+
+    fn txs() -> Vec<Transaction> {
+        (0..3)
+            .map(|i| Transaction {
+                from: format!("a{i}"),
+                to: format!("b{i}"),
+                amount: i,
+                timestamp: 1_600_000_000,
+                fee: 0,
+                signature: None,
+                pubkey: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn wrappers_use_sha256() {
+        assert_eq!(
+            merkle_root(&txs()),
+            merkle_root_with::<Sha256Hasher>(&txs())
+        );
+        let data = Some("hello".to_string());
+        assert_eq!(block_data_hash(&data), Sha256Hasher::digest(b"hello"));
+        assert_eq!(block_data_hash(&None), Sha256Hasher::digest(b""));
+    }
+
+    #[test]
+    fn digest_is_pluggable() {
+        assert_ne!(
+            merkle_root_with::<LengthDigest>(&txs()),
+            merkle_root(&txs())
+        );
+        assert_eq!(
+            block_data_hash_with::<LengthDigest>(&Some("hello".into()))[0],
+            5
+        );
+    }
+
+    #[test]
+    fn hash_algo_parses_and_tags_genesis() {
+        assert_eq!("sha256".parse(), Ok(HashAlgo::Sha256));
+        assert!("md5".parse::<HashAlgo>().is_err());
+        let genesis = crate::chain::genesis_block();
+        assert_eq!(HashAlgo::of_genesis(&genesis), Ok(HashAlgo::Sha256));
+        assert_eq!(
+            HashAlgo::Sha256.tag_genesis_data("Genesis Block".into()),
+            "Genesis Block"
+        );
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn blake3_gives_different_roots() {
+        assert_ne!(
+            HashAlgo::Blake3.merkle_root(&txs()),
+            HashAlgo::Sha256.merkle_root(&txs())
+        );
+        assert_eq!(HashAlgo::Sha256.merkle_root(&txs()), merkle_root(&txs()));
+        let data = Some("hello".to_string());
+        assert_ne!(
+            HashAlgo::Blake3.data_hash(&data),
+            HashAlgo::Sha256.data_hash(&data)
+        );
+
+        let mut genesis = crate::chain::genesis_block();
+        genesis.data = Some(HashAlgo::Blake3.tag_genesis_data("testnet".into()));
+        assert_eq!(genesis.data.as_deref(), Some("testnet [hash-algo=blake3]"));
+        assert_eq!(HashAlgo::of_genesis(&genesis), Ok(HashAlgo::Blake3));
+        assert_eq!("blake3".parse(), Ok(HashAlgo::Blake3));
+    }
+}
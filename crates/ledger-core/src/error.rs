@@ -0,0 +1,90 @@
+//! The error type returned by [`Chain`](crate::chain::Chain), so callers can
+//! tell a storage failure from a rejected block or a mining timeout without
+//! matching on message text.
+
+use crate::{verify::BlockError, TxError};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum LedgerError {
+    /// The backing store failed to read or write.
+    #[error("storage error: {0:#}")]
+    Storage(anyhow::Error),
+    /// A block failed verification or disagrees with the stored chain.
+    #[error("verification failed: {0}")]
+    Verification(String),
+    /// Mining hit its deadline or was cancelled before finding a block.
+    #[error("mining stopped before a block was found")]
+    MiningTimeout,
+    /// Every nonce was tried without meeting the target.
+    #[error("no nonce meets the target")]
+    NonceSpaceExhausted,
+    /// A nonce shard was asked for that is not below the shard count.
+    #[error("shard {shard} is not below shard count {count}")]
+    InvalidShard { shard: u64, count: u64 },
+    /// A mined block was not stored: while it was mined, another block took
+    /// its index or replaced the parent it was mined on.
+    #[error("block {0} was not stored: the chain changed while it was mined")]
+    StaleParent(u64),
+    /// Something the operation needs is not in the chain.
+    #[error("not found: {0}")]
+    NotFound(String),
+    /// A transaction failed its own checks, e.g. a zero amount.
+    #[error("invalid transaction: {0}")]
+    InvalidTx(#[from] TxError),
+}
+
+impl LedgerError {
+    /// Stable, machine-readable name of the variant, e.g. for API clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LedgerError::Storage(_) => "storage",
+            LedgerError::Verification(_) => "verification",
+            LedgerError::MiningTimeout => "mining_timeout",
+            LedgerError::NonceSpaceExhausted => "nonce_space_exhausted",
+            LedgerError::InvalidShard { .. } => "invalid_shard",
+            LedgerError::StaleParent(_) => "stale_parent",
+            LedgerError::NotFound(_) => "not_found",
+            LedgerError::InvalidTx(_) => "invalid_tx",
+        }
+    }
+}
+
+/// Store backends report failures as `anyhow` errors; they all surface as
+/// [`LedgerError::Storage`].
+impl From<anyhow::Error> for LedgerError {
+    fn from(e: anyhow::Error) -> Self {
+        LedgerError::Storage(e)
+    }
+}
+
+impl From<BlockError> for LedgerError {
+    fn from(e: BlockError) -> Self {
+        LedgerError::Verification(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_stable() {
+        let storage: LedgerError = anyhow::anyhow!("disk full").into();
+        assert_eq!(storage.code(), "storage");
+        assert_eq!(storage.to_string(), "storage error: disk full");
+        let bad: LedgerError = BlockError::MerkleMismatch.into();
+        assert_eq!(bad.code(), "verification");
+        assert_eq!(LedgerError::MiningTimeout.code(), "mining_timeout");
+        assert_eq!(LedgerError::StaleParent(4).code(), "stale_parent");
+        assert_eq!(
+            LedgerError::NonceSpaceExhausted.code(),
+            "nonce_space_exhausted"
+        );
+        let shard = LedgerError::InvalidShard { shard: 3, count: 2 };
+        assert_eq!(shard.code(), "invalid_shard");
+        assert_eq!(shard.to_string(), "shard 3 is not below shard count 2");
+        assert_eq!(LedgerError::NotFound("block 3".into()).code(), "not_found");
+        assert_eq!(LedgerError::from(TxError::ZeroAmount).code(), "invalid_tx");
+    }
+}
@@ -0,0 +1,163 @@
+//! Account balances derived from the transactions in the chain.
+//!
+//! Balances are rebuilt by replaying blocks in order. A store that prunes
+//! block bodies keeps the balances of the pruned range instead, and replay
+//! starts from those.
+
+use crate::{Block, Transaction};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Default)]
+pub struct BalanceLedger {
+    balances: HashMap<String, i64>,
+}
+
+impl BalanceLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A ledger starting from known balances, e.g. those of a pruned range.
+    pub fn from_balances(balances: impl IntoIterator<Item = (String, i64)>) -> Self {
+        Self {
+            balances: balances.into_iter().collect(),
+        }
+    }
+
+    /// Every account seen so far with its balance, in no particular order.
+    pub fn balances(&self) -> impl Iterator<Item = (&str, i64)> {
+        self.balances.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+
+    /// Apply every transaction in `block`, in order. When the block opens
+    /// with a coinbase, its recipient also collects the block's fees;
+    /// otherwise the fees are burned.
+    pub fn apply_block(&mut self, block: &Block) {
+        for tx in &block.txs {
+            self.apply_tx(tx);
+        }
+        if let Some(coinbase) = block.txs.first().filter(|tx| tx.is_coinbase()) {
+            let fees = block
+                .txs
+                .iter()
+                .fold(0u64, |sum, tx| sum.saturating_add(tx.fee));
+            self.credit(&coinbase.to, fees);
+        }
+    }
+
+    /// Move `amount` from `from` to `to`, charging `from` the fee as well. A
+    /// coinbase (empty `from`) is issuance: it credits `to` and debits nobody.
+    pub fn apply_tx(&mut self, tx: &Transaction) {
+        if !tx.is_coinbase() {
+            let cost = i64::try_from(tx.amount.saturating_add(tx.fee)).unwrap_or(i64::MAX);
+            let from = self.balances.entry(tx.from.clone()).or_default();
+            *from = from.saturating_sub(cost);
+        }
+        self.credit(&tx.to, tx.amount);
+    }
+
+    fn credit(&mut self, account: &str, amount: u64) {
+        let amount = i64::try_from(amount).unwrap_or(i64::MAX);
+        let balance = self.balances.entry(account.to_string()).or_default();
+        *balance = balance.saturating_add(amount);
+    }
+
+    /// Whether applying `tx`, fee included, keeps its sender's balance
+    /// non-negative. Minting transactions are always affordable.
+    pub fn can_afford(&self, tx: &Transaction) -> bool {
+        tx.is_coinbase()
+            || i64::try_from(tx.amount.saturating_add(tx.fee))
+                .is_ok_and(|cost| self.balance_of(&tx.from) >= cost)
+    }
+
+    /// Current balance of `account`; accounts never seen hold 0.
+    pub fn balance_of(&self, account: &str) -> i64 {
+        self.balances.get(account).copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constants::HASH_SIZE, BlockHeader};
+
+    fn tx(from: &str, to: &str, amount: u64) -> Transaction {
+        Transaction {
+            from: from.into(),
+            to: to.into(),
+            amount,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        }
+    }
+
+    fn block(index: u64, txs: Vec<Transaction>) -> Block {
+        Block {
+            header: BlockHeader::new(
+                index,
+                [0u8; HASH_SIZE],
+                [0u8; HASH_SIZE],
+                [0u8; HASH_SIZE],
+                0,
+            ),
+            data: None,
+            txs,
+        }
+    }
+
+    #[test]
+    fn two_transfers() {
+        let mut ledger = BalanceLedger::new();
+        ledger.apply_block(&block(0, vec![tx("", "alice", 100)]));
+        ledger.apply_block(&block(1, vec![tx("alice", "bob", 30)]));
+        ledger.apply_block(&block(2, vec![tx("bob", "carol", 10)]));
+        assert_eq!(ledger.balance_of("alice"), 70);
+        assert_eq!(ledger.balance_of("bob"), 20);
+        assert_eq!(ledger.balance_of("carol"), 10);
+        assert_eq!(ledger.balance_of(""), 0);
+    }
+
+    #[test]
+    fn unknown_account_is_zero() {
+        let mut ledger = BalanceLedger::new();
+        ledger.apply_block(&block(0, vec![tx("alice", "bob", 5)]));
+        assert_eq!(ledger.balance_of("mallory"), 0);
+        assert_eq!(ledger.balance_of("alice"), -5);
+    }
+
+    #[test]
+    fn fees_go_to_the_coinbase_recipient() {
+        let paying = |amount, fee| Transaction {
+            fee,
+            ..tx("alice", "bob", amount)
+        };
+        let mut ledger = BalanceLedger::new();
+        ledger.apply_block(&block(0, vec![tx("", "alice", 100)]));
+        ledger.apply_block(&block(
+            1,
+            vec![tx("", "miner", 50), paying(10, 3), paying(20, 2)],
+        ));
+        assert_eq!(ledger.balance_of("alice"), 100 - 30 - 5);
+        assert_eq!(ledger.balance_of("bob"), 30);
+        assert_eq!(ledger.balance_of("miner"), 55);
+
+        // without a coinbase the fees are burned
+        ledger.apply_block(&block(2, vec![paying(1, 4)]));
+        assert_eq!(ledger.balance_of("alice"), 60);
+        assert_eq!(ledger.balance_of("miner"), 55);
+        assert!(!ledger.can_afford(&paying(58, 3)));
+        assert!(ledger.can_afford(&paying(57, 3)));
+    }
+
+    #[test]
+    fn affordability() {
+        let mut ledger = BalanceLedger::new();
+        ledger.apply_tx(&tx("", "alice", 10));
+        assert!(ledger.can_afford(&tx("alice", "bob", 10)));
+        assert!(!ledger.can_afford(&tx("alice", "bob", 11)));
+        assert!(!ledger.can_afford(&tx("bob", "alice", 1)));
+        assert!(ledger.can_afford(&tx("", "bob", u64::MAX)));
+    }
+}
@@ -1,28 +1,195 @@
+pub mod audit;
 pub mod constants;
+pub mod difficulty;
+pub mod digest;
+pub mod error;
+pub mod ledger;
 pub mod mine;
+pub mod verify;
+
+/// The types most code touching a chain needs, importable in one line:
+///
+/// ```
+/// use ledger_core::prelude::{Block, Chain, ChainStore, Hash, Transaction};
+/// ```
+pub mod prelude {
+    pub use crate::chain::{Chain, ChainStore};
+    pub use crate::{Block, Hash, Transaction};
+}
 
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use constants::{BYTE, HASH_SIZE};
+use constants::{BYTE, HASH_SIZE, MAX_ACCOUNT_NAME_LEN};
+use digest::{Digest32, HashAlgo, Sha256Hasher};
 
 pub type Hash = [u8; HASH_SIZE];
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
 pub struct Transaction {
     pub from: String,
     pub to: String,
     pub amount: u64,
     pub timestamp: u64,
+    /// Paid to the miner of the block that includes the transaction, on top of `amount`.
+    #[serde(default)]
+    pub fee: u64,
+    /// Ed25519 signature over [`Transaction::signing_bytes`], set by [`Transaction::sign`].
+    #[serde(default)]
+    pub signature: Option<Vec<u8>>,
+    /// Public half of the key that produced `signature`.
+    #[serde(default)]
+    pub pubkey: Option<Vec<u8>>,
+}
+
+impl Serialize for Transaction {
+    /// JSON leaves out a zero fee and unset signature fields, matching
+    /// [`canonical_tx_bytes`]; binary formats such as bincode are not
+    /// self-describing and always get every field.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let compact = serializer.is_human_readable();
+        let mut st = serializer.serialize_struct("Transaction", 7)?;
+        st.serialize_field("from", &self.from)?;
+        st.serialize_field("to", &self.to)?;
+        st.serialize_field("amount", &self.amount)?;
+        st.serialize_field("timestamp", &self.timestamp)?;
+        if compact && self.fee == 0 {
+            st.skip_field("fee")?;
+        } else {
+            st.serialize_field("fee", &self.fee)?;
+        }
+        for (name, value) in [("signature", &self.signature), ("pubkey", &self.pubkey)] {
+            if compact && value.is_none() {
+                st.skip_field(name)?;
+            } else {
+                st.serialize_field(name, value)?;
+            }
+        }
+        st.end()
+    }
+}
+
+impl Transaction {
+    /// A reward paying `amount` to `to` out of nothing. Coinbase transactions
+    /// have an empty `from`, which [`ledger::BalanceLedger`] treats as issuance.
+    pub fn coinbase(to: impl Into<String>, amount: u64) -> Self {
+        Self {
+            from: String::new(),
+            to: to.into(),
+            amount,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        }
+    }
+
+    /// Encoded size of the transaction in bytes; a block's
+    /// [`Block::size_bytes`] grows by exactly this much per transaction.
+    pub fn size_bytes(&self) -> usize {
+        bincode::serialized_size(self).expect("transactions always serialize") as usize
+    }
+
+    /// Whether this is a reward issued by [`Transaction::coinbase`].
+    pub fn is_coinbase(&self) -> bool {
+        self.from.is_empty()
+    }
+
+    /// The bytes a signature commits to: [`canonical_tx_bytes`] without the
+    /// signature and public key. Names are JSON-quoted, so no choice of
+    /// `from`, `to` or `fee` can encode the same as another transfer.
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        canonical_tx_bytes(&Transaction {
+            signature: None,
+            pubkey: None,
+            ..self.clone()
+        })
+    }
+
+    fn signing_digest(&self) -> Hash {
+        Sha256::digest(self.signing_bytes()).into()
+    }
+
+    /// Sign the transaction with `key`, recording the signature and the
+    /// matching public key.
+    pub fn sign(&mut self, key: &ed25519_dalek::SigningKey) {
+        use ed25519_dalek::Signer;
+        let signature = key.sign(&self.signing_digest());
+        self.signature = Some(signature.to_bytes().to_vec());
+        self.pubkey = Some(key.verifying_key().to_bytes().to_vec());
+    }
+
+    /// Whether the transaction carries a valid signature from its `pubkey`.
+    /// Unsigned transactions and malformed keys or signatures are `false`.
+    pub fn verify_signature(&self) -> bool {
+        use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+        let (Some(signature), Some(pubkey)) = (&self.signature, &self.pubkey) else {
+            return false;
+        };
+        let Ok(pubkey) = <[u8; 32]>::try_from(pubkey.as_slice()) else {
+            return false;
+        };
+        let Ok(key) = VerifyingKey::from_bytes(&pubkey) else {
+            return false;
+        };
+        let Ok(signature) = Signature::from_slice(signature) else {
+            return false;
+        };
+        key.verify(&self.signing_digest(), &signature).is_ok()
+    }
+
+    /// Check the field constraints every transaction must meet before it may
+    /// enter the mempool. An empty `from` is the reserved coinbase sender.
+    pub fn validate(&self) -> Result<(), TxError> {
+        self.validate_with_max_name_len(MAX_ACCOUNT_NAME_LEN)
+    }
+
+    /// [`Transaction::validate`] with a custom limit on account name length.
+    pub fn validate_with_max_name_len(&self, max: usize) -> Result<(), TxError> {
+        if self.to.is_empty() {
+            return Err(TxError::EmptyRecipient);
+        }
+        if self.from == self.to {
+            return Err(TxError::SelfTransfer);
+        }
+        if self.amount == 0 {
+            return Err(TxError::ZeroAmount);
+        }
+        for (field, name) in [("from", &self.from), ("to", &self.to)] {
+            if name.chars().count() > max {
+                return Err(TxError::NameTooLong { field, max });
+            }
+        }
+        Ok(())
+    }
 }
 
-impl PartialEq for Transaction {
-    fn eq(&self, other: &Self) -> bool {
-        self.timestamp == other.timestamp
-            && self.amount == other.amount
-            && self.from == other.from
-            && self.to == other.to
+/// Why [`Transaction::validate`] rejected a transaction.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum TxError {
+    #[error("recipient must not be empty")]
+    EmptyRecipient,
+    #[error("sender and recipient must differ")]
+    SelfTransfer,
+    #[error("amount must be greater than zero")]
+    ZeroAmount,
+    #[error("{field} is longer than {max} characters")]
+    NameTooLong { field: &'static str, max: usize },
+}
+
+impl TxError {
+    /// The transaction field the error is about.
+    pub fn field(&self) -> &'static str {
+        match self {
+            TxError::EmptyRecipient | TxError::SelfTransfer => "to",
+            TxError::ZeroAmount => "amount",
+            TxError::NameTooLong { field, .. } => field,
+        }
     }
 }
 
@@ -37,22 +204,37 @@ pub struct BlockHeader {
 }
 
 impl BlockHeader {
+    /// A header stamped with the current time; see [`BlockHeader::new_at`].
     pub fn new(
         index: u64,
         previous_hash: Hash,
         data_hash: Hash,
         merkle_root: Hash,
         nonce: u64,
+    ) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        Self::new_at(index, previous_hash, data_hash, merkle_root, nonce, now)
+    }
+
+    /// A header with an explicit `timestamp`, so tests get the same hash on
+    /// every run.
+    pub fn new_at(
+        index: u64,
+        previous_hash: Hash,
+        data_hash: Hash,
+        merkle_root: Hash,
+        nonce: u64,
+        timestamp: u64,
     ) -> Self {
         Self {
             index,
             previous_hash,
             data_hash,
             merkle_root,
-            timestamp: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            timestamp,
             nonce,
         }
     }
@@ -77,23 +259,71 @@ pub struct Block {
 }
 
 impl Block {
+    /// A block holding `txs` and `data`, stamped with the current time, whose
+    /// header's merkle root and data hash are computed from them (SHA-256).
+    pub fn new(
+        index: u64,
+        previous_hash: Hash,
+        txs: Vec<Transaction>,
+        data: Option<String>,
+        nonce: u64,
+    ) -> Block {
+        let header = BlockHeader::new(
+            index,
+            previous_hash,
+            block_data_hash(&data),
+            merkle_root(&txs),
+            nonce,
+        );
+        Block { header, data, txs }
+    }
+
     pub fn hash(&self) -> Hash {
         block_header_hash(self.header)
     }
+
+    /// Encoded size of the block in bytes, as bincode (and so the stores) writes it.
+    pub fn size_bytes(&self) -> usize {
+        bincode::serialized_size(self).expect("blocks always serialize") as usize
+    }
+
+    /// Whether the header's merkle root commits to `txs`.
+    pub fn merkle_root_matches(&self) -> bool {
+        self.merkle_root_matches_with(HashAlgo::Sha256)
+    }
+
+    /// [`Block::merkle_root_matches`] for a chain hashed with `algo`.
+    pub fn merkle_root_matches_with(&self, algo: HashAlgo) -> bool {
+        algo.merkle_root(&self.txs) == self.header.merkle_root
+    }
+
+    /// Whether the header's data hash commits to `data`.
+    pub fn data_hash_matches(&self) -> bool {
+        self.data_hash_matches_with(HashAlgo::Sha256)
+    }
+
+    /// [`Block::data_hash_matches`] for a chain hashed with `algo`.
+    pub fn data_hash_matches_with(&self, algo: HashAlgo) -> bool {
+        algo.data_hash(&self.data) == self.header.data_hash
+    }
 }
 
 pub fn block_header_hash(header: BlockHeader) -> Hash {
-    let mut hasher = Sha256::new();
-    hasher.update(header.hash_bytes());
-    hash_fn(hasher)
+    block_header_hash_with::<Sha256Hasher>(header)
+}
+
+/// [`block_header_hash`] with any [`Digest32`].
+pub fn block_header_hash_with<H: Digest32>(header: BlockHeader) -> Hash {
+    H::digest(&header.hash_bytes())
 }
 
 pub fn block_data_hash(data: &Option<String>) -> Hash {
-    let mut hasher = Sha256::new();
-    if let Some(d) = data {
-        hasher.update(d.as_bytes());
-    }
-    hash_fn(hasher)
+    block_data_hash_with::<Sha256Hasher>(data)
+}
+
+/// [`block_data_hash`] with any [`Digest32`]. No data hashes the empty input.
+pub fn block_data_hash_with<H: Digest32>(data: &Option<String>) -> Hash {
+    H::digest(data.as_deref().unwrap_or_default().as_bytes())
 }
 
 pub fn hash_fn(hasher: Sha256) -> Hash {
@@ -104,40 +334,124 @@ pub fn hash_fn(hasher: Sha256) -> Hash {
 }
 
 pub fn merkle_root(txs: &[Transaction]) -> Hash {
+    merkle_root_with::<Sha256Hasher>(txs)
+}
+
+/// [`merkle_root`] with any [`Digest32`].
+pub fn merkle_root_with<H: Digest32>(txs: &[Transaction]) -> Hash {
     if txs.is_empty() {
         return [0u8; HASH_SIZE];
     }
-    let mut level: Vec<Hash> = txs
-        .iter()
-        .map(|t| {
-            let mut hasher = Sha256::new();
-            hasher.update(serde_json::to_vec(t).unwrap());
-            let digest = hasher.finalize();
-            let mut out = [0u8; HASH_SIZE];
-            out.copy_from_slice(&digest[..]);
-            out
-        })
-        .collect();
+    let mut level: Vec<Hash> = txs.iter().map(merkle_leaf::<H>).collect();
+    while level.len() > 1 {
+        level = merkle_parents::<H>(&level);
+    }
+    level[0]
+}
 
+/// Sibling hashes from leaf to root proving that `txs[index]` is under
+/// [`merkle_root`]`(txs)`. Each step carries `true` when the sibling sits to
+/// the right of the running hash. `None` if `index` is out of range.
+pub fn merkle_proof(txs: &[Transaction], index: usize) -> Option<Vec<(Hash, bool)>> {
+    if index >= txs.len() {
+        return None;
+    }
+    let mut level: Vec<Hash> = txs.iter().map(merkle_leaf::<Sha256Hasher>).collect();
+    let mut pos = index;
+    let mut proof = Vec::new();
     while level.len() > 1 {
-        let mut next = Vec::with_capacity(level.len().div_ceil(2));
-        for pair in level.chunks(2) {
-            let (a, b) = if pair.len() == 2 {
-                (pair[0], pair[1])
+        let sibling_on_right = pos.is_multiple_of(2);
+        let sibling = if sibling_on_right {
+            // an odd node out is paired with itself, as in merkle_root
+            *level.get(pos + 1).unwrap_or(&level[pos])
+        } else {
+            level[pos - 1]
+        };
+        proof.push((sibling, sibling_on_right));
+        level = merkle_parents::<Sha256Hasher>(&level);
+        pos /= 2;
+    }
+    Some(proof)
+}
+
+/// Whether `proof`, as built by [`merkle_proof`], links `leaf` to `root`.
+pub fn verify_merkle_proof(leaf: &Transaction, proof: &[(Hash, bool)], root: &Hash) -> bool {
+    let computed = proof.iter().fold(
+        merkle_leaf::<Sha256Hasher>(leaf),
+        |acc, (sibling, sibling_on_right)| {
+            if *sibling_on_right {
+                merkle_pair::<Sha256Hasher>(&acc, sibling)
             } else {
-                (pair[0], pair[0])
-            };
-            let mut hasher = Sha256::new();
-            hasher.update(a);
-            hasher.update(b);
-            let digest = hasher.finalize();
-            let mut out = [0u8; HASH_SIZE];
-            out.copy_from_slice(&digest[..]);
-            next.push(out);
+                merkle_pair::<Sha256Hasher>(sibling, &acc)
+            }
+        },
+    );
+    &computed == root
+}
+
+/// SHA-256 of a transaction's [`canonical_tx_bytes`]: its leaf in
+/// [`merkle_root`] and the id
+/// [`Chain::get_transaction`](chain::Chain::get_transaction) looks up.
+pub fn tx_hash(tx: &Transaction) -> Hash {
+    merkle_leaf::<Sha256Hasher>(tx)
+}
+
+/// Put `txs` in block order: by `timestamp`, ties broken by [`tx_hash`], so
+/// the same set of transactions always seals into the same block contents
+/// whatever order they were queued or selected in.
+pub fn sort_block_txs(txs: &mut [Transaction]) {
+    txs.sort_by_cached_key(block_order_key);
+}
+
+/// The key [`sort_block_txs`] orders by, for sorting values that wrap a
+/// transaction.
+pub fn block_order_key(tx: &Transaction) -> (u64, Hash) {
+    (tx.timestamp, tx_hash(tx))
+}
+
+/// The bytes a transaction hashes to: compact JSON with fields in declaration
+/// order, leaving out a zero `fee` and unset `signature`/`pubkey`. Written out
+/// here rather than through `Serialize` so neither the storage encoding nor a
+/// serde change can move existing tx hashes or merkle roots.
+pub fn canonical_tx_bytes(tx: &Transaction) -> Vec<u8> {
+    fn bytes_field(out: &mut String, name: &str, value: &Option<Vec<u8>>) {
+        if let Some(bytes) = value {
+            let items: Vec<String> = bytes.iter().map(u8::to_string).collect();
+            out.push_str(&format!(",\"{name}\":[{}]", items.join(",")));
         }
-        level = next;
     }
-    level[0]
+
+    let quote = |s: &str| serde_json::to_string(s).expect("strings always serialize");
+    let mut out = format!(
+        "{{\"from\":{},\"to\":{},\"amount\":{},\"timestamp\":{}",
+        quote(&tx.from),
+        quote(&tx.to),
+        tx.amount,
+        tx.timestamp
+    );
+    if tx.fee != 0 {
+        out.push_str(&format!(",\"fee\":{}", tx.fee));
+    }
+    bytes_field(&mut out, "signature", &tx.signature);
+    bytes_field(&mut out, "pubkey", &tx.pubkey);
+    out.push('}');
+    out.into_bytes()
+}
+
+fn merkle_leaf<H: Digest32>(tx: &Transaction) -> Hash {
+    H::digest(&canonical_tx_bytes(tx))
+}
+
+fn merkle_pair<H: Digest32>(a: &Hash, b: &Hash) -> Hash {
+    H::digest(&[a.as_slice(), b.as_slice()].concat())
+}
+
+/// One level up the tree; an odd last node is hashed with itself.
+fn merkle_parents<H: Digest32>(level: &[Hash]) -> Vec<Hash> {
+    level
+        .chunks(2)
+        .map(|pair| merkle_pair::<H>(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+        .collect()
 }
 
 pub mod pow {
@@ -149,7 +463,15 @@ pub mod pow {
     /// Mine the genesis block by incrementing nonce until the number of leading zero bits
     /// in the block hash >= `target_zeros`.
     /// Use mine_genesis_block only for the genesis block; use chain::Chain::mine_with_txs_parallel for other blocks.
-    pub fn mine_genesis_block(mut block: Block, target_zeros: u32) -> Block {
+    pub fn mine_genesis_block(block: Block, target_zeros: u32) -> Block {
+        mine_block(block, target_zeros)
+    }
+
+    /// Single-threaded miner: tries nonces in ascending order from the block's
+    /// current nonce and returns the block with the first one whose hash has at
+    /// least `target_zeros` leading zero bits. Unlike the parallel miner the
+    /// result is reproducible, which suits benchmarks and small targets.
+    pub fn mine_block(mut block: Block, target_zeros: u32) -> Block {
         loop {
             let mut hasher = Sha256::new();
             hasher.update(block.header.hash_bytes());
@@ -164,48 +486,361 @@ pub mod pow {
         }
     }
 
+    /// [`mine_block`] measuring the header hash with `algo` instead of SHA-256.
+    pub fn mine_block_with_algo(
+        mut block: Block,
+        target_zeros: u32,
+        algo: crate::digest::HashAlgo,
+    ) -> Block {
+        if algo == crate::digest::HashAlgo::Sha256 {
+            return mine_block(block, target_zeros);
+        }
+        while count_leading_zero_bits(&algo.header_hash(block.header)) < target_zeros {
+            block.header.nonce = block.header.nonce.wrapping_add(1);
+        }
+        block
+    }
+
+    /// Leading zero bits of `hash`, read as four big-endian `u64` words so
+    /// the mining loop usually settles it on the first word.
     pub fn count_leading_zero_bits(hash: &Hash) -> u32 {
         let mut total = 0u32;
-        for b in hash {
-            if *b == 0 {
-                total += 8;
-            } else {
-                total += b.leading_zeros();
-                break;
+        for word in hash.chunks_exact(8) {
+            let word = u64::from_be_bytes(word.try_into().expect("8-byte chunk"));
+            if word != 0 {
+                return total + word.leading_zeros();
             }
+            total += 64;
         }
         total
     }
+
+    /// A proof-of-work threshold: a hash meets it when, read as a 256-bit
+    /// big-endian number, it is no greater than the target. Unlike a
+    /// leading-zero-bit count this can move by less than a factor of two.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Target(pub [u8; HASH_SIZE]);
+
+    impl Target {
+        /// The target met by exactly the hashes with at least `bits` leading
+        /// zero bits; 256 or more leaves only the all-zero hash.
+        pub fn from_leading_zero_bits(bits: u32) -> Self {
+            let mut target = [0xFFu8; HASH_SIZE];
+            let bits = bits.min(HASH_SIZE as u32 * 8) as usize;
+            target[..bits / 8].fill(0);
+            if bits / 8 < HASH_SIZE {
+                target[bits / 8] >>= bits % 8;
+            }
+            Target(target)
+        }
+
+        /// Whether `hash` is at or below the target, i.e. a valid proof of work.
+        pub fn meets(&self, hash: &Hash) -> bool {
+            hash <= &self.0
+        }
+    }
+
+    impl std::fmt::Display for Target {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(&hex::encode(self.0))
+        }
+    }
+
+    /// Approximate work behind a block hash: `2^leading_zero_bits`, the
+    /// expected number of hashes needed to find it. Saturates at `u128::MAX`.
+    pub fn block_work(hash: &Hash) -> u128 {
+        1u128
+            .checked_shl(count_leading_zero_bits(hash))
+            .unwrap_or(u128::MAX)
+    }
 }
 
 pub mod chain {
     use crate::{
-        constants::POW_TARGET_DIFFICULTY, mine::mine_block_parallel, pow::mine_genesis_block,
+        constants::{
+            DIFFICULTY_WINDOW, MAX_FUTURE_DRIFT_SECS, POW_TARGET_DIFFICULTY, TARGET_BLOCK_SECS,
+        },
+        difficulty::next_target_with,
+        digest::HashAlgo,
+        error::LedgerError,
+        mine::{mine_block_parallel_in_shard, now_secs, NonceShard},
+        pow::count_leading_zero_bits,
+        verify::{verify_block_with_drift, BlockError, ValidationFailure, ValidationReport},
     };
 
     use super::*;
     use anyhow::{Context, Result};
-    use std::sync::Arc;
+    use std::io::{BufRead, Write};
+    use std::sync::{atomic::AtomicBool, Arc};
+    use std::time::Instant;
+    use tracing::warn;
 
     /// Trait the storage backends should implement for the chain to operate.
     /// This lives in `ledger-core` to avoid a circular dependency.
     pub trait ChainStore: Send + Sync {
         fn put_block(&self, block: &Block) -> Result<()>;
+        /// Store several blocks, in order. The default calls `put_block` for
+        /// each; stores that can write them in one go should override it.
+        fn put_blocks(&self, blocks: &[Block]) -> Result<()> {
+            blocks.iter().try_for_each(|block| self.put_block(block))
+        }
+        /// Store `block` only if its index is free and the block stored just
+        /// below it still has [`Block::hash`] `parent` (`None`: no block
+        /// there), returning whether it was stored. The default checks and
+        /// writes in separate calls; stores that can do both in one step
+        /// should override it.
+        fn put_block_after(&self, block: &Block, parent: Option<Hash>) -> Result<bool> {
+            let index = block.header.index;
+            if let Some(below) = index.checked_sub(1) {
+                if self.get_block(below)?.map(|b| b.hash()) != parent {
+                    return Ok(false);
+                }
+            }
+            if self.get_block(index)?.is_some() {
+                return Ok(false);
+            }
+            self.put_block(block)?;
+            Ok(true)
+        }
+        /// Put `block` in place of the one stored at its index, or just store
+        /// it if the index is free. The hash and transaction indexes follow the
+        /// new block, and so does the tip if the old block was the tip; blocks
+        /// above are left alone. The default deletes then puts in separate
+        /// calls; stores that can swap in one step should override it.
+        fn replace_block(&self, block: &Block) -> Result<()> {
+            self.delete_block(block.header.index)?;
+            self.put_block(block)
+        }
         fn get_block(&self, index: u64) -> Result<Option<Block>>;
+        /// Look a block up by its header hash. The default scans from genesis
+        /// to tip; stores with a hash index should override it.
+        fn get_block_by_hash(&self, hash: &Hash) -> Result<Option<Block>> {
+            for index in 0..=self.tip_height()? {
+                if let Some(block) = self.get_block(index)? {
+                    if block.hash() == *hash {
+                        return Ok(Some(block));
+                    }
+                }
+            }
+            Ok(None)
+        }
+        /// Index of the block holding the transaction with this
+        /// [`tx_hash`](crate::tx_hash), if any. The default scans every block
+        /// from the tip down; stores with a transaction index should override it.
+        fn tx_block_index(&self, tx_hash: &Hash) -> Result<Option<u64>> {
+            for block in self.iter_blocks(self.tip_height()?, true) {
+                let block = block?;
+                if block.txs.iter().any(|tx| crate::tx_hash(tx) == *tx_hash) {
+                    return Ok(Some(block.header.index));
+                }
+            }
+            Ok(None)
+        }
+        /// Whether the block at `index` was pruned to a header-only stub,
+        /// whose merkle root and data hash no longer match its (empty) body.
+        /// Stores that never prune keep the default.
+        fn is_pruned(&self, _index: u64) -> Result<bool> {
+            Ok(false)
+        }
         fn tip_height(&self) -> Result<u64>;
         fn tip_hash(&self) -> Result<Option<Hash>>;
+        /// Remove the block at `index`. If it was the tip, the tip moves to the
+        /// highest block still stored. Deleting a missing index is a no-op.
+        fn delete_block(&self, index: u64) -> Result<()>;
+        /// Up to `limit` blocks starting at `start` (inclusive), walking down
+        /// when `desc` is set and up otherwise. Missing indices are skipped.
+        /// The default calls `get_block` once per index; stores that can scan
+        /// a key range should override it.
+        fn list_blocks_range(&self, start: u64, limit: u32, desc: bool) -> Result<Vec<Block>> {
+            let tip = self.tip_height()?;
+            // `limit` comes from callers and may be far above the chain's length
+            let mut out = Vec::with_capacity(u64::from(limit).min(tip.saturating_add(1)) as usize);
+            let indices: Box<dyn Iterator<Item = u64>> = if desc {
+                Box::new((0..=start.min(tip)).rev())
+            } else {
+                Box::new(start..=tip)
+            };
+            for index in indices {
+                if out.len() >= limit as usize {
+                    break;
+                }
+                if let Some(block) = self.get_block(index)? {
+                    out.push(block);
+                }
+            }
+            Ok(out)
+        }
+        /// Blocks from `from` (inclusive) up to the tip, or down to genesis
+        /// when `desc` is set, read one at a time so a full-chain scan never
+        /// holds more than one block. Missing indices are skipped. The default
+        /// calls `get_block` once per index; stores that can scan a key range
+        /// should override it.
+        fn iter_blocks(
+            &self,
+            from: u64,
+            desc: bool,
+        ) -> Box<dyn Iterator<Item = Result<Block>> + '_> {
+            let tip = match self.tip_height() {
+                Ok(tip) => tip,
+                Err(e) => return Box::new(std::iter::once(Err(e))),
+            };
+            let indices: Box<dyn Iterator<Item = u64>> = if desc {
+                Box::new((0..=from.min(tip)).rev())
+            } else {
+                Box::new(from..=tip)
+            };
+            Box::new(indices.filter_map(move |index| self.get_block(index).transpose()))
+        }
+        /// Sum of [`block_work`](crate::pow::block_work) over every stored
+        /// block up to the tip; `0` for an empty store. The default walks the
+        /// whole chain; stores that keep a running total should override it.
+        fn tip_work(&self) -> Result<u128> {
+            self.iter_blocks(0, false).try_fold(0u128, |total, block| {
+                Ok(total.saturating_add(crate::pow::block_work(&block?.hash())))
+            })
+        }
         fn close(&self) -> Result<()>;
     }
 
+    /// Outcome of [`Chain::import_ndjson`].
+    #[derive(Debug, Default, Clone, PartialEq, Eq)]
+    pub struct ImportStats {
+        /// Blocks verified and stored (or already stored with the same hash).
+        pub accepted: u64,
+        /// Lines left unimported: the offending block and everything after it.
+        pub rejected: u64,
+        /// Index of the block import stopped at, if it stopped early. For a
+        /// line that isn't a block this is the index that was expected next.
+        pub stopped_at: Option<u64>,
+    }
+
+    /// Blocks [`Chain::import_ndjson`] writes to the store per
+    /// [`ChainStore::put_blocks`] call.
+    pub const IMPORT_BATCH: usize = 256;
+
+    /// How [`Chain::accept_block`] placed a block.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum AcceptOutcome {
+        /// The block extended the tip and was stored.
+        Extended,
+        /// The same block is already stored; nothing changed.
+        Duplicate,
+        /// Its parent is stored but is not the tip, so it belongs to a
+        /// competing branch. Not stored: switching branches needs total work.
+        Fork,
+        /// Its `previous_hash` is not the block we hold just below it.
+        Orphan,
+    }
+
+    /// What [`Chain::ensure_genesis_with`] puts in a fresh chain's genesis block.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct GenesisConfig {
+        pub data: String,
+        /// Accounts funded by the genesis block, as `(account, amount)`.
+        pub allocations: Vec<(String, u64)>,
+    }
+
+    impl Default for GenesisConfig {
+        fn default() -> Self {
+            Self {
+                data: "Genesis Block".to_string(),
+                allocations: Vec::new(),
+            }
+        }
+    }
+
+    /// Who is paid for mining and how much; see [`Chain::with_reward`].
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct BlockReward {
+        pub miner: String,
+        pub amount: u64,
+    }
+
+    /// Timestamp for a new block on top of `parent`: now, or a second past
+    /// the parent if that is later, since a block must be dated strictly
+    /// after its parent.
+    fn next_timestamp(parent: Option<&Block>) -> u64 {
+        let after_parent = parent.map_or(0, |p| p.header.timestamp.saturating_add(1));
+        now_secs().max(after_parent)
+    }
+
     /// Simple chain façade that delegates persistence to a `ChainStore`.
     #[derive(Clone)]
     pub struct Chain<C: ChainStore> {
         store: Arc<C>,
+        reward: Option<BlockReward>,
+        hash_algo: HashAlgo,
+        max_future_drift: u64,
+        nonce_shard: NonceShard,
     }
 
     impl<C: ChainStore> Chain<C> {
         pub fn new(store: Arc<C>) -> Self {
-            Self { store }
+            Self {
+                store,
+                reward: None,
+                hash_algo: HashAlgo::default(),
+                max_future_drift: MAX_FUTURE_DRIFT_SECS,
+                nonce_shard: NonceShard::ALL,
+            }
+        }
+
+        /// How far ahead of local time, in seconds, imported and accepted
+        /// blocks may be dated. Defaults to [`MAX_FUTURE_DRIFT_SECS`].
+        pub fn with_max_future_drift(mut self, secs: u64) -> Self {
+            self.max_future_drift = secs;
+            self
+        }
+
+        /// Hash headers, data and merkle trees with `algo` instead of SHA-256.
+        /// It must match the algorithm the stored chain was created with.
+        /// Stores still index blocks by their SHA-256 [`Block::hash`].
+        pub fn with_hash_algo(mut self, algo: HashAlgo) -> Self {
+            self.hash_algo = algo;
+            self
+        }
+
+        /// Only try nonces in `shard` when mining, so nodes given the other
+        /// shards of the same count search the rest of the space.
+        pub fn with_nonce_shard(mut self, shard: NonceShard) -> Self {
+            self.nonce_shard = shard;
+            self
+        }
+
+        /// The shard of the nonce space this chain mines in.
+        pub fn nonce_shard(&self) -> NonceShard {
+            self.nonce_shard
+        }
+
+        pub fn hash_algo(&self) -> HashAlgo {
+            self.hash_algo
+        }
+
+        /// `block`'s header hash under this chain's algorithm: what its
+        /// proof of work is measured on and what its child links to.
+        pub fn block_hash(&self, block: &Block) -> Hash {
+            self.hash_algo.header_hash(block.header)
+        }
+
+        /// Prepend a coinbase paying `reward` to every block this chain mines.
+        pub fn with_reward(mut self, reward: BlockReward) -> Self {
+            self.reward = Some(reward);
+            self
+        }
+
+        /// The reward configured with [`Chain::with_reward`], if any.
+        pub fn reward(&self) -> Option<&BlockReward> {
+            self.reward.as_ref()
+        }
+
+        /// `txs` with the coinbase, if any, in front, so it is covered by the merkle root.
+        fn with_coinbase(&self, txs: Vec<Transaction>) -> Vec<Transaction> {
+            match &self.reward {
+                Some(r) => std::iter::once(Transaction::coinbase(&r.miner, r.amount))
+                    .chain(txs)
+                    .collect(),
+                None => txs,
+            }
         }
 
         pub fn store(&self) -> &Arc<C> {
@@ -213,12 +848,35 @@ pub mod chain {
         }
 
         /// Ensure a genesis block exists. Idempotent.
-        pub fn ensure_genesis(&self) -> Result<()> {
-            let height = self.store.tip_height()?;
+        pub fn ensure_genesis(&self) -> Result<(), LedgerError> {
+            self.ensure_genesis_with(&GenesisConfig::default())
+        }
+
+        /// Like [`Chain::ensure_genesis`], but a missing genesis is built from
+        /// `cfg`. A chain that already has a genesis block keeps it, and is
+        /// refused if it was created with a different [`HashAlgo`].
+        pub fn ensure_genesis_with(&self, cfg: &GenesisConfig) -> Result<(), LedgerError> {
+            let height = self.height()?;
+            if let Some(genesis) = self.store.get_block(0)? {
+                let recorded = HashAlgo::of_genesis(&genesis).map_err(LedgerError::Verification)?;
+                if recorded != self.hash_algo {
+                    return Err(LedgerError::Verification(format!(
+                        "chain was created with {recorded} hashing but {} is configured",
+                        self.hash_algo
+                    )));
+                }
+            }
             // Height 0 can mean "empty" or "genesis at index 0". Check presence of block 0.
-            if height == 0 && self.store.get_block(0)?.is_none() {
-                let genesis = genesis_block();
-                let genesis_block = mine_genesis_block(genesis, POW_TARGET_DIFFICULTY); // Mine genesis hash with 20 leading zero bits
+            if height == 0 && !self.contains(0)? {
+                let algo = self.hash_algo;
+                let mut genesis = genesis_block_with(
+                    algo.tag_genesis_data(cfg.data.clone()),
+                    cfg.allocations.clone(),
+                );
+                genesis.header.data_hash = algo.data_hash(&genesis.data);
+                genesis.header.merkle_root = algo.merkle_root(&genesis.txs);
+                // Mine genesis hash with 20 leading zero bits
+                let genesis_block = pow::mine_block_with_algo(genesis, POW_TARGET_DIFFICULTY, algo);
                 self.store.put_block(&genesis_block).with_context(|| {
                     format!(
                         "failed to persist genesis block at index {}",
@@ -229,125 +887,1648 @@ pub mod chain {
             Ok(())
         }
 
+        /// A page of blocks; see [`ChainStore::list_blocks_range`].
+        pub fn list_blocks_range(
+            &self,
+            start: u64,
+            limit: u32,
+            desc: bool,
+        ) -> Result<Vec<Block>, LedgerError> {
+            Ok(self.store.list_blocks_range(start, limit, desc)?)
+        }
+
+        /// The block at the tip, or `None` for an empty store. Saves callers of
+        /// [`Chain::tip`] a second lookup that could see a different tip.
+        pub fn tip_block(&self) -> Result<Option<Block>, LedgerError> {
+            if self.store.tip_hash()?.is_none() {
+                return Ok(None);
+            }
+            Ok(self.store.get_block(self.height()?)?)
+        }
+
+        /// The block whose [`Chain::block_hash`] is `hash`. SHA-256 chains use
+        /// the store's hash index; other algorithms scan down from the tip.
+        pub fn get_block_by_hash(&self, hash: &Hash) -> Result<Option<Block>, LedgerError> {
+            if self.hash_algo == HashAlgo::Sha256 {
+                return Ok(self.store.get_block_by_hash(hash)?);
+            }
+            if self.store.tip_hash()?.is_none() {
+                return Ok(None);
+            }
+            for block in self.store.iter_blocks(self.height()?, true) {
+                let block = block?;
+                if self.block_hash(&block) == *hash {
+                    return Ok(Some(block));
+                }
+            }
+            Ok(None)
+        }
+
+        /// Find a transaction by its [`tx_hash`](crate::tx_hash), returning it
+        /// with the index of the block that holds it.
+        pub fn get_transaction(
+            &self,
+            tx_hash: &Hash,
+        ) -> Result<Option<(u64, Transaction)>, LedgerError> {
+            let Some(index) = self.store.tx_block_index(tx_hash)? else {
+                return Ok(None);
+            };
+            let Some(block) = self.store.get_block(index)? else {
+                return Ok(None);
+            };
+            Ok(block
+                .txs
+                .into_iter()
+                .find(|tx| crate::tx_hash(tx) == *tx_hash)
+                .map(|tx| (index, tx)))
+        }
+
+        /// Index of the tip block. Like [`Chain::tip`] this is 0 both for an
+        /// empty chain and for one holding only genesis; use
+        /// [`Chain::contains`]`(0)` to tell them apart.
+        pub fn height(&self) -> Result<u64, LedgerError> {
+            Ok(self.store.tip_height()?)
+        }
+
+        /// Whether a block is stored at `index`.
+        pub fn contains(&self, index: u64) -> Result<bool, LedgerError> {
+            Ok(self.store.get_block(index)?.is_some())
+        }
+
         /// Return (height, tip_hash). Height is 0 for empty or at genesis index 0.
-        pub fn tip(&self) -> Result<(u64, Option<Hash>)> {
+        pub fn tip(&self) -> Result<(u64, Option<Hash>), LedgerError> {
             Ok((self.store.tip_height()?, self.store.tip_hash()?))
         }
 
+        /// The last `window` blocks up to the tip, oldest first; fewer when the
+        /// chain is shorter.
+        fn recent_blocks(&self, window: usize) -> Result<Vec<Block>, LedgerError> {
+            if window == 0 || self.store.tip_hash()?.is_none() {
+                return Ok(Vec::new());
+            }
+            let limit = u32::try_from(window).unwrap_or(u32::MAX);
+            let start = self.height()?.saturating_sub(u64::from(limit) - 1);
+            self.list_blocks_range(start, limit, false)
+        }
+
+        /// Mean seconds between consecutive blocks over the last `window`
+        /// blocks, or `None` when fewer than two blocks are in range.
+        pub fn average_block_time(&self, window: usize) -> Result<Option<f64>, LedgerError> {
+            let blocks = self.recent_blocks(window)?;
+            Ok(average_gap(&blocks))
+        }
+
+        /// Hashes per second the network needed to produce the last `window`
+        /// blocks: the mean [`block_work`](crate::pow::block_work) of each block
+        /// after the first, over [`Chain::average_block_time`]. `None` with
+        /// fewer than two blocks or when they all share a timestamp.
+        ///
+        /// Headers don't record the target a block was mined at, so the work
+        /// is read off each hash rather than derived from the target. A hash
+        /// often has more leading zeros than its target asked for, so this
+        /// leans high, and one lucky block can dominate a short window.
+        pub fn estimated_hashrate(&self, window: usize) -> Result<Option<f64>, LedgerError> {
+            let blocks = self.recent_blocks(window)?;
+            let Some(avg) = average_gap(&blocks).filter(|avg| *avg > 0.0) else {
+                return Ok(None);
+            };
+            let work: f64 = blocks[1..]
+                .iter()
+                .map(|block| pow::block_work(&self.block_hash(block)) as f64)
+                .sum();
+            Ok(Some(work / (blocks.len() - 1) as f64 / avg))
+        }
+
         pub fn mine_with_txs_parallel(
             &mut self,
             txs: Vec<Transaction>,
             data: Option<String>,
             target: u32,
-        ) -> anyhow::Result<(Block, [u8; HASH_SIZE])> {
-            let index = self.store.tip_height()? + 1;
-            let prev_hash = self.store.tip_hash()?;
-            let (block, hash) = mine_block_parallel(
+        ) -> Result<(Block, [u8; HASH_SIZE]), LedgerError> {
+            let (index, prev_hash) = self.next_parent()?;
+            self.mine_on_top_of(prev_hash, index, txs, data, target)
+        }
+
+        /// Mine and persist block `index` on top of `prev`, for callers that
+        /// already know the parent instead of reading it from the tip. If a
+        /// block lands at `index`, or replaces the one below it, while mining,
+        /// nothing is stored and this returns [`LedgerError::StaleParent`].
+        pub fn mine_on_top_of(
+            &mut self,
+            prev: Hash,
+            index: u64,
+            txs: Vec<Transaction>,
+            data: Option<String>,
+            target: u32,
+        ) -> Result<(Block, Hash), LedgerError> {
+            let txs = self.with_coinbase(txs);
+            let parent = self.stored_parent(index)?;
+            let timestamp = next_timestamp(parent.as_ref());
+            let (block, hash) = mine_block_parallel_in_shard(
+                self.hash_algo,
                 index,
-                prev_hash.expect("tip hash should be available"),
+                prev,
                 txs,
                 data,
                 target,
-            );
-            self.store.put_block(&block).with_context(|| {
-                format!("failed to persist block at index {}", block.header.index)
+                timestamp,
+                self.nonce_shard,
+                None,
+                Arc::new(AtomicBool::new(false)),
+            )
+            .ok_or(LedgerError::NonceSpaceExhausted)?;
+            self.store_mined(&block, parent.as_ref())?;
+            Ok((block, hash))
+        }
+
+        /// The block stored just below `index`, if any.
+        fn stored_parent(&self, index: u64) -> Result<Option<Block>, LedgerError> {
+            Ok(match index.checked_sub(1) {
+                Some(below) => self.store.get_block(below)?,
+                None => None,
+            })
+        }
+
+        /// Persist a freshly mined `block`, unless a block stored in the
+        /// meantime took its index or replaced `parent`, the block it was
+        /// mined on; that is [`LedgerError::StaleParent`].
+        fn store_mined(&self, block: &Block, parent: Option<&Block>) -> Result<(), LedgerError> {
+            let index = block.header.index;
+            let stored = self
+                .store
+                .put_block_after(block, parent.map(Block::hash))
+                .with_context(|| format!("failed to persist block at index {index}"))?;
+            if !stored {
+                return Err(LedgerError::StaleParent(index));
+            }
+            Ok(())
+        }
+
+        /// Index and parent hash for the block after the current tip.
+        fn next_parent(&self) -> Result<(u64, Hash), LedgerError> {
+            let mut prev_hash = self.store.tip_hash()?.ok_or_else(|| {
+                LedgerError::NotFound("chain tip; call ensure_genesis first".into())
             })?;
+            let height = self.height()?;
+            // the store keys its tip by SHA-256; other algorithms rehash the tip header
+            if self.hash_algo != HashAlgo::Sha256 {
+                let tip = self
+                    .store
+                    .get_block(height)?
+                    .ok_or_else(|| LedgerError::NotFound(format!("tip block {height}")))?;
+                prev_hash = self.block_hash(&tip);
+            }
+            Ok((height + 1, prev_hash))
+        }
 
+        /// Like [`Chain::mine_with_txs_parallel`], but stops at `deadline` or when
+        /// `cancel` is set. Returns [`LedgerError::MiningTimeout`] if mining was
+        /// stopped, and [`LedgerError::StaleParent`] if the tip moved while
+        /// mining; nothing is persisted in either case.
+        pub fn mine_with_deadline(
+            &mut self,
+            txs: Vec<Transaction>,
+            data: Option<String>,
+            target: u32,
+            deadline: Option<Instant>,
+            cancel: Arc<AtomicBool>,
+        ) -> Result<(Block, Hash), LedgerError> {
+            let (index, prev_hash) = self.next_parent()?;
+            let txs = self.with_coinbase(txs);
+            let parent = self.stored_parent(index)?;
+            let timestamp = next_timestamp(parent.as_ref());
+            let Some((block, hash)) = mine_block_parallel_in_shard(
+                self.hash_algo,
+                index,
+                prev_hash,
+                txs,
+                data,
+                target,
+                timestamp,
+                self.nonce_shard,
+                deadline,
+                cancel,
+            ) else {
+                return Err(LedgerError::MiningTimeout);
+            };
+            self.store_mined(&block, parent.as_ref())?;
             Ok((block, hash))
         }
+
+        /// Mine the next block at a target retargeted from the last
+        /// [`DIFFICULTY_WINDOW`] stored headers, aiming for one block every
+        /// [`TARGET_BLOCK_SECS`]. Returns the block, its hash and the target used.
+        pub fn mine_next_block(
+            &mut self,
+            txs: Vec<Transaction>,
+            data: Option<String>,
+        ) -> Result<(Block, Hash, u32), LedgerError> {
+            let height = self.height()?;
+            let from = (height + 1).saturating_sub(DIFFICULTY_WINDOW as u64);
+            let mut headers = Vec::with_capacity(DIFFICULTY_WINDOW);
+            for index in from..=height {
+                if let Some(block) = self.store.get_block(index)? {
+                    headers.push(block.header);
+                }
+            }
+            let target = next_target_with(
+                self.hash_algo,
+                &headers,
+                TARGET_BLOCK_SECS,
+                DIFFICULTY_WINDOW,
+            );
+            let (block, hash) = self.mine_with_txs_parallel(txs, data, target)?;
+            Ok((block, hash, target))
+        }
+
+        /// Write blocks `from..=to` to `w` as NDJSON, one serialized [`Block`]
+        /// per line, and return how many were written. Blocks are streamed
+        /// from the store one at a time; gaps are logged and skipped, and so
+        /// are [pruned](ChainStore::is_pruned) stubs, which can't be imported.
+        pub fn export_ndjson<W: Write>(
+            &self,
+            w: &mut W,
+            from: u64,
+            to: u64,
+        ) -> Result<u64, LedgerError> {
+            let mut written = 0;
+            let mut expected = from;
+            let mut pruned = 0u64;
+            for block in self.store.iter_blocks(from, false) {
+                let block = block?;
+                let index = block.header.index;
+                if index > to {
+                    break;
+                }
+                if index > expected {
+                    warn!("export: no blocks at indices {expected}..{index}, skipping");
+                }
+                expected = index + 1;
+                // a stub would fail its merkle check on import
+                if self.store.is_pruned(index)? {
+                    pruned += 1;
+                    continue;
+                }
+                serde_json::to_writer(&mut *w, &block)
+                    .with_context(|| format!("failed to write block {index}"))?;
+                w.write_all(b"\n").context("failed to write export")?;
+                written += 1;
+            }
+            if pruned > 0 {
+                warn!("export: skipped {pruned} pruned blocks");
+            }
+            Ok(written)
+        }
+
+        /// Read blocks from NDJSON as written by [`Chain::export_ndjson`],
+        /// verifying each against its parent at `target` before storing it.
+        /// Import stops at the first block that fails to parse or verify, or
+        /// that conflicts with a different block already stored at its index.
+        /// Verified blocks are stored [`IMPORT_BATCH`] at a time.
+        pub fn import_ndjson<R: BufRead>(
+            &mut self,
+            r: R,
+            target: u32,
+        ) -> Result<ImportStats, LedgerError> {
+            let mut stats = ImportStats::default();
+            let mut prev: Option<Block> = None;
+            let mut batch = Vec::with_capacity(IMPORT_BATCH);
+            let mut lines = r.lines();
+            while let Some(line) = lines.next() {
+                let line = line.context("failed to read import")?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let expected = prev.as_ref().map(|p| p.header.index + 1);
+                let stop = match serde_json::from_str::<Block>(&line) {
+                    Ok(block) => match self.check_import(&block, prev.as_ref(), target) {
+                        Ok(is_new) => {
+                            stats.accepted += 1;
+                            if is_new {
+                                batch.push(block.clone());
+                            }
+                            if batch.len() >= IMPORT_BATCH {
+                                self.store_batch(&mut batch)?;
+                            }
+                            prev = Some(block);
+                            None
+                        }
+                        Err(e) => {
+                            warn!("import: stopping at block {}: {e}", block.header.index);
+                            Some(block.header.index)
+                        }
+                    },
+                    Err(e) => {
+                        let index = expected.unwrap_or(0);
+                        warn!("import: line for block {index} is not a block: {e}");
+                        Some(index)
+                    }
+                };
+                if stop.is_some() {
+                    stats.stopped_at = stop;
+                    stats.rejected = 1 + lines
+                        .map_while(|l| l.ok())
+                        .filter(|l| !l.trim().is_empty())
+                        .count() as u64;
+                    break;
+                }
+            }
+            self.store_batch(&mut batch)?;
+            Ok(stats)
+        }
+
+        fn store_batch(&self, batch: &mut Vec<Block>) -> Result<(), LedgerError> {
+            if !batch.is_empty() {
+                self.store
+                    .put_blocks(batch)
+                    .context("failed to persist imported blocks")?;
+                batch.clear();
+            }
+            Ok(())
+        }
+
+        /// Verify `block` against `prev` (or its stored parent). `Ok(false)`
+        /// means the same block is already stored and needn't be written.
+        fn check_import(
+            &self,
+            block: &Block,
+            prev: Option<&Block>,
+            target: u32,
+        ) -> Result<bool, LedgerError> {
+            let index = block.header.index;
+            let stored_parent;
+            let parent = match (prev, index) {
+                (Some(prev), _) => Some(prev),
+                (None, 0) => None,
+                (None, _) => {
+                    stored_parent = self
+                        .store
+                        .get_block(index - 1)?
+                        .ok_or_else(|| LedgerError::NotFound(format!("parent of block {index}")))?;
+                    Some(&stored_parent)
+                }
+            };
+            verify_block_with_drift(block, parent, target, self.hash_algo, self.max_future_drift)?;
+            if let Some(existing) = self.store.get_block(index)? {
+                if existing.hash() != block.hash() {
+                    return Err(LedgerError::Verification(format!(
+                        "a different block is already stored at index {index}"
+                    )));
+                }
+                return Ok(false);
+            }
+            Ok(true)
+        }
+
+        /// Verify `block`'s proof of work and contents, then place it relative to
+        /// the stored chain: append it if it extends the tip, otherwise report it
+        /// as a duplicate, a fork off an earlier block, or an orphan. Only
+        /// [`AcceptOutcome::Extended`] changes the store.
+        pub fn accept_block(
+            &mut self,
+            block: Block,
+            target: u32,
+        ) -> Result<AcceptOutcome, LedgerError> {
+            verify_block_with_drift(&block, None, target, self.hash_algo, self.max_future_drift)?;
+            let index = block.header.index;
+            if let Some(existing) = self.store.get_block(index)? {
+                if existing.hash() == block.hash() {
+                    return Ok(AcceptOutcome::Duplicate);
+                }
+            }
+            let parent = match index.checked_sub(1) {
+                Some(below) => self.store.get_block(below)?,
+                None => None,
+            };
+            let Some(parent) = parent.filter(|p| self.block_hash(p) == block.header.previous_hash)
+            else {
+                return Ok(AcceptOutcome::Orphan);
+            };
+            if self.height()? != parent.header.index {
+                return Ok(AcceptOutcome::Fork);
+            }
+            verify_block_with_drift(
+                &block,
+                Some(&parent),
+                target,
+                self.hash_algo,
+                self.max_future_drift,
+            )?;
+            self.store
+                .put_block(&block)
+                .with_context(|| format!("failed to persist block at index {index}"))?;
+            Ok(AcceptOutcome::Extended)
+        }
+
+        /// Swap the tip for `candidate` when it is a rival at the same height on
+        /// the same parent whose hash carries strictly more
+        /// [`block_work`](crate::pow::block_work); the loser is deleted. Ties
+        /// keep the incumbent, and genesis is never replaced. The candidate is
+        /// verified against `target` and the parent before any comparison, and
+        /// swapped in with [`ChainStore::replace_block`].
+        /// Returns whether the tip changed.
+        pub fn replace_tip_if_heavier(
+            &mut self,
+            candidate: Block,
+            target: u32,
+        ) -> Result<bool, LedgerError> {
+            let Some(tip) = self.tip_block()? else {
+                return Ok(false);
+            };
+            let index = tip.header.index;
+            if index == 0
+                || candidate.header.index != index
+                || candidate.header.previous_hash != tip.header.previous_hash
+            {
+                return Ok(false);
+            }
+            let parent = self.store.get_block(index - 1)?;
+            verify_block_with_drift(
+                &candidate,
+                parent.as_ref(),
+                target,
+                self.hash_algo,
+                self.max_future_drift,
+            )?;
+            let work = |block: &Block| pow::block_work(&self.block_hash(block));
+            if work(&candidate) <= work(&tip) {
+                return Ok(false);
+            }
+            self.store
+                .replace_block(&candidate)
+                .with_context(|| format!("failed to replace block at index {index}"))?;
+            Ok(true)
+        }
+
+        /// Delete every block above `height`, returning them tip first so their
+        /// transactions can be put back into a mempool. Rolling back to a height
+        /// at or above the tip removes nothing.
+        pub fn rollback_to(&mut self, height: u64) -> Result<Vec<Block>, LedgerError> {
+            let tip = self.height()?;
+            let mut removed = Vec::new();
+            for index in (height.saturating_add(1)..=tip).rev() {
+                if let Some(block) = self.store.get_block(index)? {
+                    self.store
+                        .delete_block(index)
+                        .with_context(|| format!("failed to delete block at index {index}"))?;
+                    removed.push(block);
+                }
+            }
+            Ok(removed)
+        }
+
+        /// Find the highest index at which a candidate header chain (e.g. from a peer)
+        /// agrees with ours, i.e. the point a reorg would roll back to.
+        ///
+        /// `headers` must be contiguous and ascending. Starting from the candidate's
+        /// base, each header's hash is compared against our stored block at the same
+        /// index; the last matching index is returned. If the very first header already
+        /// differs, its `previous_hash` is checked against our block just below it.
+        /// Returns `None` when the chains diverge at genesis or share no block.
+        pub fn find_fork_point(&self, headers: &[BlockHeader]) -> Result<Option<u64>, LedgerError> {
+            let Some(base) = headers.first() else {
+                return Ok(None);
+            };
+
+            let mut last_common = match base.index.checked_sub(1) {
+                Some(below) => self
+                    .store
+                    .get_block(below)?
+                    .filter(|b| self.block_hash(b) == base.previous_hash)
+                    .map(|_| below),
+                None => None,
+            };
+
+            for header in headers {
+                match self.store.get_block(header.index)? {
+                    Some(ours) if self.block_hash(&ours) == self.hash_algo.header_hash(*header) => {
+                        last_common = Some(header.index);
+                    }
+                    _ => break,
+                }
+            }
+            Ok(last_common)
+        }
+
+        /// Walk stored blocks `from..=to`, checking that indices are contiguous,
+        /// each `previous_hash` matches the block below (including the one just
+        /// under `from`), and each header hash meets `target` leading zero bits.
+        /// Stops at the first offending block.
+        pub fn validate_range(
+            &self,
+            from: u64,
+            to: u64,
+            target: u32,
+        ) -> Result<(), ValidationReport> {
+            let load = |index: u64| {
+                self.store.get_block(index).map_err(|e| ValidationReport {
+                    index,
+                    reason: ValidationFailure::Store(e.to_string()),
+                })
+            };
+            let fail = |index: u64, err: BlockError| ValidationReport {
+                index,
+                reason: err.into(),
+            };
+
+            let mut prev = match from.checked_sub(1) {
+                Some(below) => load(below)?,
+                None => None,
+            };
+            for index in from..=to {
+                let block = load(index)?.ok_or(ValidationReport {
+                    index,
+                    reason: ValidationFailure::Missing,
+                })?;
+                if let Some(prev) = &prev {
+                    let expected = self.block_hash(prev);
+                    if block.header.previous_hash != expected {
+                        return Err(fail(
+                            index,
+                            BlockError::BrokenLink {
+                                expected,
+                                found: block.header.previous_hash,
+                            },
+                        ));
+                    }
+                }
+                if block.header.index != index {
+                    return Err(fail(
+                        index,
+                        BlockError::NonContiguousIndex {
+                            parent: index.wrapping_sub(1),
+                            found: block.header.index,
+                        },
+                    ));
+                }
+                let zeros = count_leading_zero_bits(&self.block_hash(&block));
+                if zeros < target {
+                    return Err(fail(index, BlockError::InsufficientWork { zeros, target }));
+                }
+                prev = Some(block);
+            }
+            Ok(())
+        }
+    }
+
+    /// Mean timestamp delta across `blocks` (oldest first).
+    fn average_gap(blocks: &[Block]) -> Option<f64> {
+        if blocks.len() < 2 {
+            return None;
+        }
+        let (first, last) = (blocks.first()?, blocks.last()?);
+        let span = last.header.timestamp.saturating_sub(first.header.timestamp);
+        Some(span as f64 / (blocks.len() - 1) as f64)
+    }
+
+    /// A zero-transaction genesis block with zeroed prev-hash and merkle-root.
+    pub fn genesis_block() -> Block {
+        let cfg = GenesisConfig::default();
+        genesis_block_with(cfg.data, cfg.allocations)
+    }
+
+    /// A genesis block carrying `data` that pre-funds every `(account, amount)`
+    /// in `allocations` with a coinbase transaction, in order.
+    pub fn genesis_block_with(data: String, allocations: Vec<(String, u64)>) -> Block {
+        let data = Some(data);
+        let txs: Vec<Transaction> = allocations
+            .into_iter()
+            .map(|(account, amount)| Transaction::coinbase(account, amount))
+            .collect();
+        let header = BlockHeader::new(
+            0,
+            [0u8; HASH_SIZE],
+            block_data_hash(&data),
+            merkle_root(&txs),
+            0,
+        );
+        Block { header, txs, data }
+    }
+
+    /// [`genesis_block`] dated `timestamp` rather than now.
+    pub fn genesis_block_at(timestamp: u64) -> Block {
+        let mut genesis = genesis_block();
+        genesis.header.timestamp = timestamp;
+        genesis
+    }
+}
+
+#[cfg(test)]
+mod inmem_store_tests {
+    use super::*;
+    use crate::chain::{Chain, ChainStore};
+    use anyhow::Result;
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, RwLock};
+
+    #[derive(Default)]
+    struct InMemStore {
+        blocks: RwLock<BTreeMap<u64, Block>>,
+        tip: RwLock<Option<Hash>>,
+    }
+
+    impl ChainStore for InMemStore {
+        fn put_block(&self, block: &Block) -> Result<()> {
+            self.blocks
+                .write()
+                .unwrap()
+                .insert(block.header.index, block.clone());
+            *self.tip.write().unwrap() = Some(block.hash());
+            Ok(())
+        }
+
+        fn get_block(&self, index: u64) -> Result<Option<Block>> {
+            Ok(self.blocks.read().unwrap().get(&index).cloned())
+        }
+
+        fn tip_height(&self) -> Result<u64> {
+            Ok(self
+                .blocks
+                .read()
+                .unwrap()
+                .keys()
+                .next_back()
+                .copied()
+                .unwrap_or(0))
+        }
+
+        fn tip_hash(&self) -> Result<Option<Hash>> {
+            Ok(*self.tip.read().unwrap())
+        }
+
+        fn delete_block(&self, index: u64) -> Result<()> {
+            let mut blocks = self.blocks.write().unwrap();
+            blocks.remove(&index);
+            *self.tip.write().unwrap() = blocks.values().next_back().map(Block::hash);
+            Ok(())
+        }
+
+        fn close(&self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn mine_block_example_inmem() {
+        let store = InMemStore::default();
+        let mut chain = sha_chain(store);
+        chain.ensure_genesis().unwrap();
+
+        let txs = vec![
+            Transaction {
+                from: "Alice".into(),
+                to: "Bob".into(),
+                amount: 10,
+                timestamp: 1_600_000_000,
+                fee: 0,
+                signature: None,
+                pubkey: None,
+            },
+            Transaction {
+                from: "Bob".into(),
+                to: "Charlie".into(),
+                amount: 5,
+                timestamp: 1_600_000_100,
+                fee: 0,
+                signature: None,
+                pubkey: None,
+            },
+        ];
+
+        let (_b, hash) = chain.mine_with_txs_parallel(txs, None, 16).unwrap();
+        assert!(pow::count_leading_zero_bits(&hash) >= 16);
+    }
+
+    #[test]
+    fn genesis_allocations_change_the_genesis() {
+        use crate::chain::GenesisConfig;
+        let deploy = |allocations: Vec<(String, u64)>| {
+            let chain = sha_chain(InMemStore::default());
+            let cfg = GenesisConfig {
+                data: "testnet".into(),
+                allocations,
+            };
+            chain.ensure_genesis_with(&cfg).unwrap();
+            // a second call keeps the genesis that is already there
+            chain
+                .ensure_genesis_with(&GenesisConfig::default())
+                .unwrap();
+            chain.store().get_block(0).unwrap().unwrap()
+        };
+        let a = deploy(vec![("alice".into(), 100), ("bob".into(), 5)]);
+        let b = deploy(vec![("alice".into(), 100)]);
+        assert_ne!(a.hash(), b.hash());
+        assert_ne!(a.header.merkle_root, b.header.merkle_root);
+        assert_eq!(a.header.merkle_root, merkle_root(&a.txs));
+        assert_eq!(a.data.as_deref(), Some("testnet"));
+        assert!(pow::count_leading_zero_bits(&a.hash()) >= constants::POW_TARGET_DIFFICULTY);
+
+        let mut ledger = crate::ledger::BalanceLedger::new();
+        ledger.apply_block(&a);
+        assert_eq!(ledger.balance_of("alice"), 100);
+        assert_eq!(ledger.balance_of("bob"), 5);
+    }
+
+    #[test]
+    fn mined_blocks_are_dated_after_their_parent() {
+        // far more blocks than seconds pass while mining them
+        let chain = chain_with_blocks(5);
+        let stamps: Vec<u64> = (0..=5)
+            .map(|i| {
+                chain
+                    .store()
+                    .get_block(i)
+                    .unwrap()
+                    .unwrap()
+                    .header
+                    .timestamp
+            })
+            .collect();
+        assert!(stamps.windows(2).all(|w| w[0] < w[1]), "{stamps:?}");
+        assert_eq!(chain.validate_range(0, 5, 1), Ok(()));
+    }
+
+    #[test]
+    fn mined_blocks_start_with_the_coinbase() {
+        let mut chain = sha_chain(InMemStore::default()).with_reward(crate::chain::BlockReward {
+            miner: "miner".into(),
+            amount: 50,
+        });
+        chain.ensure_genesis().unwrap();
+        let tx = Transaction {
+            from: "miner".into(),
+            to: "Bob".into(),
+            amount: 20,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        };
+
+        let (block, _) = chain.mine_with_txs_parallel(vec![tx], None, 1).unwrap();
+        assert_eq!(block.txs.len(), 2);
+        assert!(block.txs[0].is_coinbase());
+        assert_eq!(block.txs[0].to, "miner");
+        assert_eq!(block.txs[0].amount, 50);
+        assert_eq!(block.header.merkle_root, merkle_root(&block.txs));
+
+        let mut ledger = crate::ledger::BalanceLedger::new();
+        ledger.apply_block(&block);
+        assert_eq!(ledger.balance_of("miner"), 30);
+        chain.mine_with_txs_parallel(vec![], None, 1).unwrap();
+        let (block, _) = chain
+            .mine_with_deadline(vec![], None, 1, None, Default::default())
+            .unwrap();
+        assert!(block.txs[0].is_coinbase());
+    }
+
+    #[test]
+    fn tip_block_is_the_last_mined() {
+        let mut chain = sha_chain(InMemStore::default());
+        assert!(chain.tip_block().unwrap().is_none());
+        chain.ensure_genesis().unwrap();
+        chain.mine_with_txs_parallel(vec![], None, 1).unwrap();
+        let (mined, hash) = chain.mine_with_txs_parallel(vec![], None, 1).unwrap();
+
+        let tip = chain.tip_block().unwrap().unwrap();
+        assert_eq!(tip.header.index, 2);
+        assert_eq!(tip.hash(), hash);
+        assert_eq!(tip.header.merkle_root, mined.header.merkle_root);
+    }
+
+    #[test]
+    fn cancelled_mining_is_a_timeout() {
+        use crate::error::LedgerError;
+        use std::sync::atomic::AtomicBool;
+        let mut chain = sha_chain(InMemStore::default());
+        let err = chain.mine_with_txs_parallel(vec![], None, 1).unwrap_err();
+        assert!(matches!(err, LedgerError::NotFound(_)), "{err}");
+
+        chain.ensure_genesis().unwrap();
+        let cancel = Arc::new(AtomicBool::new(true));
+        let err = chain
+            .mine_with_deadline(vec![], None, 255, None, cancel)
+            .unwrap_err();
+        assert!(matches!(err, LedgerError::MiningTimeout));
+        assert_eq!(chain.store().tip_height().unwrap(), 0);
+    }
+
+    #[test]
+    fn height_and_contains() {
+        let chain = sha_chain(InMemStore::default());
+        assert_eq!(chain.height().unwrap(), 0);
+        assert!(!chain.contains(0).unwrap());
+
+        chain.ensure_genesis().unwrap();
+        assert_eq!(chain.height().unwrap(), 0);
+        assert!(chain.contains(0).unwrap());
+        assert!(!chain.contains(1).unwrap());
+    }
+
+    /// A SHA-256 chain whatever the `blake3` feature makes the default, since
+    /// these tests build and check blocks with the SHA-256 helpers.
+    fn sha_chain(store: InMemStore) -> Chain<InMemStore> {
+        Chain::new(Arc::new(store)).with_hash_algo(crate::digest::HashAlgo::Sha256)
+    }
+
+    fn chain_with_blocks(n: u64) -> Chain<InMemStore> {
+        let mut chain = sha_chain(InMemStore::default());
+        chain.ensure_genesis().unwrap();
+        for i in 0..n {
+            chain
+                .mine_with_txs_parallel(vec![], Some(format!("block {}", i + 1)), 1)
+                .unwrap();
+        }
+        chain
+    }
+
+    #[test]
+    fn sharded_chain_mines_in_its_shard() {
+        let shard = crate::mine::NonceShard::new(1, 4).unwrap();
+        let mut chain = sha_chain(InMemStore::default()).with_nonce_shard(shard);
+        chain.ensure_genesis().unwrap();
+        for i in 0..3 {
+            let (block, _) = chain
+                .mine_with_txs_parallel(vec![], Some(format!("block {i}")), 4)
+                .unwrap();
+            assert!(shard.contains(block.header.nonce), "{}", block.header.nonce);
+        }
+    }
+
+    #[test]
+    fn mining_without_genesis_is_an_error() {
+        let mut chain = sha_chain(InMemStore::default());
+        let err = chain.mine_with_txs_parallel(vec![], None, 1).unwrap_err();
+        assert!(err.to_string().contains("ensure_genesis"), "{err}");
+        assert!(chain.store().get_block(1).unwrap().is_none());
+
+        // with the parent given explicitly there is nothing to look up
+        let genesis = crate::chain::genesis_block();
+        let (block, _) = chain
+            .mine_on_top_of(genesis.hash(), 1, vec![], None, 1)
+            .unwrap();
+        assert_eq!(block.header.previous_hash, genesis.hash());
+        assert_eq!(
+            chain.store().get_block(1).unwrap().unwrap().hash(),
+            block.hash()
+        );
+    }
+
+    #[test]
+    fn mined_block_is_not_stored_over_a_taken_index() {
+        let mut chain = chain_with_blocks(1);
+        let genesis = chain.store().get_block(0).unwrap().unwrap();
+        let stored = chain.store().get_block(1).unwrap().unwrap();
+        // as if another block landed at index 1 while this one was mined
+        let err = chain
+            .mine_on_top_of(genesis.hash(), 1, vec![], Some("late".into()), 1)
+            .unwrap_err();
+        assert!(
+            matches!(err, crate::error::LedgerError::StaleParent(1)),
+            "{err}"
+        );
+        assert_eq!(
+            chain.store().get_block(1).unwrap().unwrap().hash(),
+            stored.hash()
+        );
+    }
+
+    #[test]
+    fn export_ndjson_roundtrips() {
+        let chain = chain_with_blocks(2);
+        let mut out = Vec::new();
+        assert_eq!(chain.export_ndjson(&mut out, 0, 2).unwrap(), 3);
+        let lines: Vec<&str> = std::str::from_utf8(&out).unwrap().lines().collect();
+        assert_eq!(lines.len(), 3);
+        for (i, line) in lines.iter().enumerate() {
+            let block: Block = serde_json::from_str(line).unwrap();
+            let stored = chain.store().get_block(i as u64).unwrap().unwrap();
+            assert_eq!(block.hash(), stored.hash());
+            assert_eq!(block.data, stored.data);
+        }
+
+        // gaps are skipped rather than failing the export
+        chain.store().blocks.write().unwrap().remove(&1);
+        let mut out = Vec::new();
+        assert_eq!(chain.export_ndjson(&mut out, 0, 5).unwrap(), 2);
+    }
+
+    fn export(chain: &Chain<InMemStore>) -> Vec<u8> {
+        let (height, _) = chain.tip().unwrap();
+        let mut out = Vec::new();
+        chain.export_ndjson(&mut out, 0, height).unwrap();
+        out
+    }
+
+    #[test]
+    fn import_ndjson_valid_export() {
+        let source = chain_with_blocks(3);
+        let mut target = sha_chain(InMemStore::default());
+        let stats = target.import_ndjson(&export(&source)[..], 1).unwrap();
+        assert_eq!(
+            stats,
+            crate::chain::ImportStats {
+                accepted: 4,
+                rejected: 0,
+                stopped_at: None
+            }
+        );
+        assert_eq!(target.tip().unwrap(), source.tip().unwrap());
+    }
+
+    #[test]
+    fn import_ndjson_stops_at_tampered_block() {
+        let source = chain_with_blocks(3);
+        tamper(&source, 2, |b| b.data = Some("forged".into()));
+        let mut target = sha_chain(InMemStore::default());
+        let stats = target.import_ndjson(&export(&source)[..], 1).unwrap();
+        assert_eq!(stats.accepted, 2);
+        assert_eq!(stats.rejected, 2);
+        assert_eq!(stats.stopped_at, Some(2));
+        assert_eq!(target.tip().unwrap().0, 1);
+        assert!(target.store().get_block(2).unwrap().is_none());
+    }
+
+    fn headers(chain: &Chain<InMemStore>) -> Vec<BlockHeader> {
+        let (height, _) = chain.tip().unwrap();
+        (0..=height)
+            .map(|i| chain.store().get_block(i).unwrap().unwrap().header)
+            .collect()
+    }
+
+    #[test]
+    fn find_fork_point_fully_agreeing() {
+        let chain = chain_with_blocks(4);
+        let candidate = headers(&chain);
+        assert_eq!(chain.find_fork_point(&candidate).unwrap(), Some(4));
+        // a peer that only sends a suffix still lines up
+        assert_eq!(chain.find_fork_point(&candidate[2..]).unwrap(), Some(4));
+    }
+
+    #[test]
+    fn find_fork_point_partially_diverging() {
+        let chain = chain_with_blocks(4);
+        let mut candidate = headers(&chain);
+        candidate[3].nonce += 1;
+        candidate[4].nonce += 1;
+        assert_eq!(chain.find_fork_point(&candidate).unwrap(), Some(2));
+        // suffix starting at the divergent block: fork point found via previous_hash
+        assert_eq!(chain.find_fork_point(&candidate[3..]).unwrap(), Some(2));
+    }
+
+    #[test]
+    fn find_fork_point_longer_candidate() {
+        let chain = chain_with_blocks(2);
+        let mut candidate = headers(&chain);
+        let mut extra = candidate[2];
+        extra.index = 3;
+        extra.previous_hash = block_header_hash(candidate[2]);
+        candidate.push(extra);
+        assert_eq!(chain.find_fork_point(&candidate).unwrap(), Some(2));
+    }
+
+    #[test]
+    fn find_fork_point_genesis_diverging() {
+        let chain = chain_with_blocks(2);
+        let mut candidate = headers(&chain);
+        candidate[0].nonce += 1;
+        assert_eq!(chain.find_fork_point(&candidate).unwrap(), None);
+        assert_eq!(chain.find_fork_point(&[]).unwrap(), None);
+    }
+
+    #[test]
+    fn mine_next_block_uses_retargeted_difficulty() {
+        use crate::difficulty::{MAX_TARGET, MIN_TARGET};
+
+        let mut chain = chain_with_blocks(2);
+        let (block, hash, target) = chain.mine_next_block(vec![], None).unwrap();
+        assert_eq!(block.header.index, 3);
+        assert!((MIN_TARGET..=MAX_TARGET).contains(&target));
+        assert!(pow::count_leading_zero_bits(&hash) >= target);
+    }
+
+    #[test]
+    fn rollback_single_block() {
+        let mut chain = chain_with_blocks(3);
+        let block2 = chain.store().get_block(2).unwrap().unwrap();
+        let removed = chain.rollback_to(2).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].header.index, 3);
+        assert_eq!(chain.tip().unwrap(), (2, Some(block2.hash())));
+        // nothing above the tip to roll back
+        assert!(chain.rollback_to(5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rollback_to_genesis() {
+        let mut chain = chain_with_blocks(3);
+        let genesis = chain.store().get_block(0).unwrap().unwrap();
+        let removed = chain.rollback_to(0).unwrap();
+        let indices: Vec<u64> = removed.iter().map(|b| b.header.index).collect();
+        assert_eq!(indices, vec![3, 2, 1]);
+        assert_eq!(chain.tip().unwrap(), (0, Some(genesis.hash())));
+        // the chain can be extended again from genesis
+        let (block, _) = chain.mine_with_txs_parallel(vec![], None, 1).unwrap();
+        assert_eq!(block.header.previous_hash, genesis.hash());
+    }
+
+    #[test]
+    fn get_block_by_hash_default_scan() {
+        let chain = chain_with_blocks(3);
+        let block = chain.store().get_block(2).unwrap().unwrap();
+        let found = chain.store().get_block_by_hash(&block.hash()).unwrap();
+        assert_eq!(found.unwrap().header.index, 2);
+        assert!(chain
+            .store()
+            .get_block_by_hash(&[0xAB; HASH_SIZE])
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn validate_range_accepts_sound_segment() {
+        let chain = chain_with_blocks(4);
+        assert_eq!(chain.validate_range(0, 4, 1), Ok(()));
+        assert_eq!(chain.validate_range(2, 3, 1), Ok(()));
+    }
+
+    #[test]
+    fn validate_range_reports_first_offender() {
+        use crate::verify::{BlockError, ValidationFailure};
+
+        let chain = chain_with_blocks(4);
+        tamper(&chain, 3, |b| b.header.previous_hash = [7u8; HASH_SIZE]);
+        let report = chain.validate_range(0, 4, 1).unwrap_err();
+        assert_eq!(report.index, 3);
+        assert!(matches!(
+            report.reason,
+            ValidationFailure::Block(BlockError::BrokenLink { .. })
+        ));
+        // the link into `from` is checked against the block below it
+        assert_eq!(chain.validate_range(3, 4, 1).unwrap_err().index, 3);
+
+        chain.store().blocks.write().unwrap().remove(&2);
+        let report = chain.validate_range(0, 4, 1).unwrap_err();
+        assert_eq!(
+            (report.index, report.reason),
+            (2, ValidationFailure::Missing)
+        );
+
+        let report = chain.validate_range(0, 0, 256).unwrap_err();
+        assert!(matches!(
+            report.reason,
+            ValidationFailure::Block(BlockError::InsufficientWork { target: 256, .. })
+        ));
+    }
+
+    fn tamper(chain: &Chain<InMemStore>, index: u64, f: impl FnOnce(&mut Block)) {
+        let mut block = chain.store().get_block(index).unwrap().unwrap();
+        f(&mut block);
+        chain.store().put_block(&block).unwrap();
+    }
+
+    #[test]
+    fn full_audit_clean_chain() {
+        let chain = chain_with_blocks(3);
+        let report = audit::full_audit(&chain, 1).unwrap();
+        assert_eq!(report.blocks_checked, 4);
+        assert!(report.is_clean(), "{:?}", report.issues);
+    }
+
+    #[test]
+    fn full_audit_reports_every_issue() {
+        use audit::IssueKind;
+
+        let chain = chain_with_blocks(5);
+        tamper(&chain, 1, |b| {
+            b.txs.push(Transaction {
+                from: "mallory".into(),
+                to: "mallory".into(),
+                amount: 1_000,
+                timestamp: 0,
+                fee: 0,
+                signature: None,
+                pubkey: None,
+            })
+        });
+        // a nonce whose hash has no leading zeros; this also breaks block 3's link
+        tamper(&chain, 2, |b| {
+            while pow::count_leading_zero_bits(&b.hash()) > 0 {
+                b.header.nonce += 1;
+            }
+        });
+        tamper(&chain, 3, |b| b.data = Some("forged".into()));
+        // re-mined so only the timestamp is wrong
+        tamper(&chain, 5, |b| {
+            b.header.timestamp = 0;
+            *b = pow::mine_genesis_block(b.clone(), 1);
+        });
+
+        let report = audit::full_audit(&chain, 1).unwrap();
+        assert_eq!(report.blocks_checked, 6);
+        let found: Vec<(u64, &IssueKind)> =
+            report.issues.iter().map(|i| (i.index, &i.kind)).collect();
+        assert_eq!(found.len(), 5, "{found:?}");
+        assert!(matches!(found[0], (1, IssueKind::MerkleMismatch)));
+        assert!(matches!(
+            found[1],
+            (
+                2,
+                IssueKind::InsufficientWork {
+                    zeros: 0,
+                    target: 1
+                }
+            )
+        ));
+        assert!(matches!(found[2], (3, IssueKind::BrokenLink { .. })));
+        assert!(matches!(found[3], (3, IssueKind::DataHashMismatch)));
+        assert!(matches!(
+            found[4],
+            (5, IssueKind::TimestampRegression { found: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn accept_block_classifies_extend_duplicate_fork_and_orphan() {
+        use crate::chain::AcceptOutcome;
+        use crate::mine::{mine_block_parallel, mine_block_parallel_at};
+        let mut chain = sha_chain(InMemStore::default());
+        chain.ensure_genesis().unwrap();
+        let genesis = chain.store().get_block(0).unwrap().unwrap();
+        let ts = genesis.header.timestamp + 1;
+
+        let (next, _) =
+            mine_block_parallel_at(1, genesis.hash(), vec![], Some("a".into()), 4, ts).unwrap();
+        assert_eq!(
+            chain.accept_block(next.clone(), 4).unwrap(),
+            AcceptOutcome::Extended
+        );
+        assert_eq!(chain.store().tip_hash().unwrap(), Some(next.hash()));
+        assert_eq!(
+            chain.accept_block(next.clone(), 4).unwrap(),
+            AcceptOutcome::Duplicate
+        );
+
+        let (rival, _) =
+            mine_block_parallel_at(1, genesis.hash(), vec![], Some("b".into()), 4, ts).unwrap();
+        assert_eq!(chain.accept_block(rival, 4).unwrap(), AcceptOutcome::Fork);
+
+        let (orphan, _) = mine_block_parallel(2, [7u8; HASH_SIZE], vec![], None, 4).unwrap();
+        assert_eq!(
+            chain.accept_block(orphan, 4).unwrap(),
+            AcceptOutcome::Orphan
+        );
+        assert_eq!(chain.store().tip_height().unwrap(), 1);
+
+        // proof of work is checked before anything else
+        let (weak, _) = mine_block_parallel(2, next.hash(), vec![], None, 0).unwrap();
+        assert!(chain.accept_block(weak, 64).is_err());
+    }
+
+    /// A block on `parent` whose hash has exactly `bits` leading zero bits.
+    fn rival_with_work(parent: &Block, data: &str, bits: u32) -> Block {
+        let (mut block, _) = crate::mine::mine_block_parallel_at(
+            parent.header.index + 1,
+            parent.hash(),
+            vec![],
+            Some(data.into()),
+            0,
+            parent.header.timestamp + 1,
+        )
+        .unwrap();
+        while pow::count_leading_zero_bits(&block.hash()) != bits {
+            block.header.nonce = block.header.nonce.wrapping_add(1);
+        }
+        block
+    }
+
+    /// `chain_with_blocks(n)` with its blocks restamped to `stamps`.
+    fn chain_stamped(stamps: &[u64]) -> Chain<InMemStore> {
+        let chain = chain_with_blocks(stamps.len() as u64 - 1);
+        for (index, ts) in stamps.iter().enumerate() {
+            let mut block = chain.store().get_block(index as u64).unwrap().unwrap();
+            block.header.timestamp = *ts;
+            chain.store().put_block(&block).unwrap();
+        }
+        chain
+    }
+
+    #[test]
+    fn average_block_time_over_a_window() {
+        let chain = chain_stamped(&[1_000, 1_010, 1_030, 1_060, 1_100]);
+        assert_eq!(chain.average_block_time(3).unwrap(), Some(35.0));
+        assert_eq!(chain.average_block_time(2).unwrap(), Some(40.0));
+        // a window past genesis covers the whole chain
+        assert_eq!(chain.average_block_time(100).unwrap(), Some(25.0));
+        assert_eq!(chain.average_block_time(1).unwrap(), None);
+        assert_eq!(chain.average_block_time(0).unwrap(), None);
+
+        let single = chain_with_blocks(0);
+        assert_eq!(single.average_block_time(10).unwrap(), None);
+        assert_eq!(single.estimated_hashrate(10).unwrap(), None);
+    }
+
+    #[test]
+    fn estimated_hashrate_divides_work_by_block_time() {
+        let chain = chain_stamped(&[1_000, 1_010, 1_030, 1_060, 1_100]);
+        let work = |index: u64| {
+            let block = chain.store().get_block(index).unwrap().unwrap();
+            pow::block_work(&block.hash()) as f64
+        };
+        let expected = (work(3) + work(4)) / 2.0 / 35.0;
+        assert_eq!(chain.estimated_hashrate(3).unwrap(), Some(expected));
+
+        let frozen = chain_stamped(&[1_000, 1_000, 1_000]);
+        assert_eq!(frozen.estimated_hashrate(3).unwrap(), None);
+    }
+
+    #[test]
+    fn heavier_rival_replaces_tip() {
+        let mut chain = chain_with_blocks(1);
+        let genesis = chain.store().get_block(0).unwrap().unwrap();
+        let tip = chain.tip_block().unwrap().unwrap();
+        let bits = pow::count_leading_zero_bits(&tip.hash());
+
+        let heavier = rival_with_work(&genesis, "heavier", bits + 1);
+        assert!(chain.replace_tip_if_heavier(heavier.clone(), 1).unwrap());
+        assert_eq!(chain.height().unwrap(), 1);
+        assert_eq!(chain.store().tip_hash().unwrap(), Some(heavier.hash()));
+        assert_eq!(
+            chain.store().get_block(1).unwrap().unwrap().hash(),
+            heavier.hash()
+        );
+    }
+
+    #[test]
+    fn lighter_or_equal_rival_keeps_tip() {
+        let mut chain = chain_with_blocks(1);
+        let genesis = chain.store().get_block(0).unwrap().unwrap();
+        let tip = chain.tip_block().unwrap().unwrap();
+        let bits = pow::count_leading_zero_bits(&tip.hash());
+
+        let equal = rival_with_work(&genesis, "equal", bits);
+        assert!(!chain.replace_tip_if_heavier(equal, 0).unwrap());
+        let lighter = rival_with_work(&genesis, "lighter", 0);
+        assert!(!chain.replace_tip_if_heavier(lighter, 0).unwrap());
+        // not a rival at all: it builds on the tip instead of its parent
+        let child = rival_with_work(&tip, "child", bits + 1);
+        assert!(!chain.replace_tip_if_heavier(child, 0).unwrap());
+        assert_eq!(chain.store().tip_hash().unwrap(), Some(tip.hash()));
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn blake3_chain_mines_and_validates() {
+        use crate::digest::HashAlgo;
+        let mut chain =
+            Chain::new(Arc::new(InMemStore::default())).with_hash_algo(HashAlgo::Blake3);
+        chain.ensure_genesis().unwrap();
+        let genesis = chain.store().get_block(0).unwrap().unwrap();
+        assert_eq!(HashAlgo::of_genesis(&genesis), Ok(HashAlgo::Blake3));
+
+        let (block, hash) = chain.mine_with_txs_parallel(vec![], None, 8).unwrap();
+        assert_eq!(block.header.previous_hash, chain.block_hash(&genesis));
+        assert_eq!(hash, HashAlgo::Blake3.header_hash(block.header));
+        chain.validate_range(0, 1, 8).unwrap();
+
+        // reopening the same store as a SHA-256 chain is refused
+        let sha = Chain::new(chain.store().clone()).with_hash_algo(HashAlgo::Sha256);
+        let err = sha.ensure_genesis().unwrap_err();
+        assert!(err.to_string().contains("created with blake3"), "{err}");
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn blake3_chain_retargets_and_finds_forks_with_blake3() {
+        use crate::constants::{DIFFICULTY_WINDOW, TARGET_BLOCK_SECS};
+        use crate::difficulty::next_target_with;
+        use crate::digest::HashAlgo;
+        let mut chain =
+            Chain::new(Arc::new(InMemStore::default())).with_hash_algo(HashAlgo::Blake3);
+        chain.ensure_genesis().unwrap();
+        for _ in 0..3 {
+            chain.mine_with_txs_parallel(vec![], None, 6).unwrap();
+        }
+        let headers: Vec<BlockHeader> = (0..=3)
+            .map(|i| chain.store().get_block(i).unwrap().unwrap().header)
+            .collect();
+        let expected = next_target_with(
+            HashAlgo::Blake3,
+            &headers,
+            TARGET_BLOCK_SECS,
+            DIFFICULTY_WINDOW,
+        );
+        let (_, hash, target) = chain.mine_next_block(vec![], None).unwrap();
+        assert_eq!(target, expected);
+        assert!(pow::count_leading_zero_bits(&hash) >= target);
+
+        // our own headers agree all the way up
+        assert_eq!(chain.find_fork_point(&headers[1..]).unwrap(), Some(3));
+
+        let third = chain.store().get_block(3).unwrap().unwrap();
+        let found = chain.get_block_by_hash(&chain.block_hash(&third)).unwrap();
+        assert_eq!(found.map(|b| b.header.index), Some(3));
+        assert!(chain.get_block_by_hash(&third.hash()).unwrap().is_none());
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn full_audit_hashes_with_the_chain_algo() {
+        use crate::digest::HashAlgo;
+        let mut chain =
+            Chain::new(Arc::new(InMemStore::default())).with_hash_algo(HashAlgo::Blake3);
+        chain.ensure_genesis().unwrap();
+        let tx = Transaction {
+            from: "alice".into(),
+            to: "bob".into(),
+            amount: 3,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        };
+        for _ in 0..3 {
+            chain
+                .mine_with_txs_parallel(vec![tx.clone()], Some("blake".into()), 6)
+                .unwrap();
+        }
+        let report = audit::full_audit(&chain, 6).unwrap();
+        assert!(report.is_clean(), "{:?}", report.issues);
+
+        tamper(&chain, 2, |b| b.txs[0].amount = 1_000);
+        let report = audit::full_audit(&chain, 6).unwrap();
+        let kinds: Vec<_> = report.issues.iter().map(|i| (i.index, &i.kind)).collect();
+        assert_eq!(kinds, [(2, &audit::IssueKind::MerkleMismatch)]);
     }
 
-    /// A zero-transaction genesis block with zeroed prev-hash and merkle-root.
-    pub fn genesis_block() -> Block {
-        let data = Some("Genesis Block".to_string());
-        let data_hash = block_data_hash(&data);
-        let header = BlockHeader::new(0, [0u8; HASH_SIZE], data_hash, [0u8; HASH_SIZE], 0);
-        Block {
-            header,
-            txs: vec![],
-            data,
-        }
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn chain_refuses_a_block_hashed_with_another_algo() {
+        use crate::digest::HashAlgo;
+        use std::sync::atomic::AtomicBool;
+        let mut chain = sha_chain(InMemStore::default());
+        chain.ensure_genesis().unwrap();
+        let genesis = chain.store().get_block(0).unwrap().unwrap();
+        let tx = Transaction {
+            from: "Alice".into(),
+            to: "Bob".into(),
+            amount: 10,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        };
+        let (block, _) = crate::mine::mine_block_parallel_with_algo(
+            HashAlgo::Blake3,
+            1,
+            genesis.hash(),
+            vec![tx],
+            None,
+            4,
+            None,
+            Arc::new(AtomicBool::new(false)),
+        )
+        .unwrap();
+
+        let line = serde_json::to_string(&block).unwrap();
+        let stats = chain.import_ndjson(line.as_bytes(), 4).unwrap();
+        assert_eq!(stats.accepted, 0);
+        assert_eq!(stats.stopped_at, Some(1));
+        assert_eq!(chain.store().tip_height().unwrap(), 0);
     }
 }
 
 #[cfg(test)]
-mod inmem_store_tests {
+mod tests {
     use super::*;
-    use crate::chain::{Chain, ChainStore};
-    use anyhow::Result;
-    use std::collections::BTreeMap;
-    use std::sync::{Arc, RwLock};
 
-    #[derive(Default)]
-    struct InMemStore {
-        blocks: RwLock<BTreeMap<u64, Block>>,
-        tip: RwLock<Option<Hash>>,
+    #[test]
+    fn mine_block_returns_minimal_nonce() {
+        let header = BlockHeader::new(1, [7u8; HASH_SIZE], [0u8; HASH_SIZE], [0u8; HASH_SIZE], 0);
+        let block = Block {
+            header,
+            data: None,
+            txs: vec![],
+        };
+        let mined = pow::mine_block(block.clone(), 6);
+        assert!(pow::count_leading_zero_bits(&mined.hash()) >= 6);
+        for nonce in 0..mined.header.nonce {
+            let mut h = block.header;
+            h.nonce = nonce;
+            assert!(pow::count_leading_zero_bits(&block_header_hash(h)) < 6);
+        }
+        // deterministic: mining the same block again finds the same nonce
+        assert_eq!(pow::mine_block(block, 6).header.nonce, mined.header.nonce);
     }
 
-    impl ChainStore for InMemStore {
-        fn put_block(&self, block: &Block) -> Result<()> {
-            self.blocks
-                .write()
-                .unwrap()
-                .insert(block.header.index, block.clone());
-            *self.tip.write().unwrap() = Some(block.hash());
-            Ok(())
-        }
+    #[test]
+    fn mining_gives_up_at_deadline() {
+        use std::sync::{atomic::AtomicBool, Arc};
+        use std::time::{Duration, Instant};
+
+        let deadline = Instant::now() + Duration::from_millis(100);
+        let mined = mine::mine_block_parallel_with_deadline(
+            1,
+            [0u8; HASH_SIZE],
+            vec![],
+            None,
+            200,
+            Some(deadline),
+            Arc::new(AtomicBool::new(false)),
+        );
+        assert!(mined.is_none());
+        assert!(Instant::now() < deadline + Duration::from_secs(2));
+    }
 
-        fn get_block(&self, index: u64) -> Result<Option<Block>> {
-            Ok(self.blocks.read().unwrap().get(&index).cloned())
-        }
+    #[test]
+    fn mining_stops_when_cancelled() {
+        use std::sync::{atomic::AtomicBool, Arc};
+
+        let cancel = Arc::new(AtomicBool::new(true));
+        let mined = mine::mine_block_parallel_with_deadline(
+            1,
+            [0u8; HASH_SIZE],
+            vec![],
+            None,
+            200,
+            None,
+            cancel,
+        );
+        assert!(mined.is_none());
+    }
 
-        fn tip_height(&self) -> Result<u64> {
-            Ok(self
-                .blocks
-                .read()
-                .unwrap()
-                .keys()
-                .next_back()
-                .copied()
-                .unwrap_or(0))
-        }
+    #[test]
+    fn dated_blocks_are_reproducible() {
+        let genesis = chain::genesis_block_at(1_600_000_000);
+        assert_eq!(
+            genesis.hash(),
+            chain::genesis_block_at(1_600_000_000).hash()
+        );
+        let (block, hash) =
+            mine::mine_block_parallel_at(1, genesis.hash(), vec![], None, 4, 1_600_000_060)
+                .unwrap();
+        assert_eq!(block.header.timestamp, 1_600_000_060);
+        // only the nonce was left to the race between workers
+        let again = BlockHeader::new_at(
+            1,
+            genesis.hash(),
+            block_data_hash(&None),
+            merkle_root(&[]),
+            block.header.nonce,
+            1_600_000_060,
+        );
+        assert_eq!(block_header_hash(again), hash);
+    }
 
-        fn tip_hash(&self) -> Result<Option<Hash>> {
-            Ok(*self.tip.read().unwrap())
-        }
+    #[test]
+    fn mining_reports_progress() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (block, hash) =
+            mine::mine_block_parallel_with_progress(1, [0u8; HASH_SIZE], vec![], None, 4, tx)
+                .unwrap();
+        assert_eq!(block.hash(), hash);
+        // the sender is gone once mining returns, so this drains every report
+        let reports: Vec<u64> = rx.iter().collect();
+        assert!(!reports.is_empty());
+        assert!(reports.windows(2).all(|w| w[0] <= w[1]));
+    }
 
-        fn close(&self) -> Result<()> {
-            Ok(())
-        }
+    #[test]
+    #[tracing_test::traced_test]
+    fn mining_logs_a_span() {
+        let (_, hash) = mine::mine_block_parallel(7, [0u8; HASH_SIZE], vec![], None, 4).unwrap();
+        let target = pow::Target::from_leading_zero_bits(4);
+        assert!(logs_contain(&format!("mine{{index=7 target={target}}}")));
+        assert!(logs_contain(&format!("hash={}", hex::encode(hash))));
     }
 
     #[test]
-    fn mine_block_example_inmem() {
-        let store = InMemStore::default();
-        let mut chain = Chain::new(Arc::new(store));
-        chain.ensure_genesis().unwrap();
+    fn mining_to_a_fine_target() {
+        // sits between 4 and 5 leading zero bits, which no bit count can express
+        let mut threshold = [0xFFu8; HASH_SIZE];
+        threshold[0] = 0x0C;
+        let target = pow::Target(threshold);
+        let (block, hash) =
+            mine::mine_block_parallel_to_target(1, [0u8; HASH_SIZE], vec![], None, target).unwrap();
+        assert_eq!(block.hash(), hash);
+        assert!(target.meets(&hash));
+    }
 
-        let txs = vec![
-            Transaction {
-                from: "Alice".into(),
-                to: "Bob".into(),
-                amount: 10,
-                timestamp: 1_600_000_000,
-            },
-            Transaction {
-                from: "Bob".into(),
-                to: "Charlie".into(),
-                amount: 5,
-                timestamp: 1_600_000_100,
-            },
-        ];
+    #[test]
+    fn nonce_shards_split_the_search() {
+        use std::time::{Duration, Instant};
+
+        let shard_count = 3;
+        let mut nonces = Vec::new();
+        for shard in 0..shard_count {
+            let deadline = Instant::now() + Duration::from_secs(30);
+            let mined = mine::mine_block_parallel_shard(
+                1,
+                [0u8; HASH_SIZE],
+                vec![],
+                Some("shared".into()),
+                8,
+                shard,
+                shard_count,
+                Some(deadline),
+            )
+            .unwrap();
+            // a shard may run out of time, but never strays from its slice
+            if let Some((block, hash)) = mined {
+                assert_eq!(block.header.nonce % shard_count, shard);
+                assert!(pow::count_leading_zero_bits(&hash) >= 8);
+                nonces.push(block.header.nonce);
+            }
+        }
+        assert!(!nonces.is_empty());
+
+        let expired = mine::mine_block_parallel_shard(
+            1,
+            [0u8; HASH_SIZE],
+            vec![],
+            None,
+            64,
+            1,
+            2,
+            Some(Instant::now()),
+        )
+        .unwrap();
+        assert!(expired.is_none());
+
+        let bad = mine::mine_block_parallel_shard(1, [0u8; HASH_SIZE], vec![], None, 1, 2, 2, None);
+        assert!(matches!(
+            bad,
+            Err(crate::error::LedgerError::InvalidShard { shard: 2, count: 2 })
+        ));
+
+        assert_eq!(mine::NonceShard::new(2, 2), None);
+        assert!(mine::NonceShard::new(1, 2).unwrap().contains(7));
+    }
 
-        let (_b, hash) = chain.mine_with_txs_parallel(txs, None, 16).unwrap();
-        assert!(pow::count_leading_zero_bits(&hash) >= 16);
+    #[test]
+    fn block_new_fills_in_derived_hashes() {
+        let tx = Transaction {
+            from: "alice".into(),
+            to: "bob".into(),
+            amount: 3,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        };
+        for (txs, data) in [
+            (vec![], None),
+            (vec![tx.clone()], None),
+            (vec![tx.clone(), tx], Some("memo".to_string())),
+        ] {
+            let block = Block::new(4, [9u8; HASH_SIZE], txs, data, 17);
+            assert!(block.merkle_root_matches());
+            assert!(block.data_hash_matches());
+            assert_eq!(block.header.index, 4);
+            assert_eq!(block.header.nonce, 17);
+        }
+        // no data still hashes the empty string, not a zeroed placeholder
+        let empty = Block::new(1, [0u8; HASH_SIZE], vec![], None, 0);
+        assert_ne!(empty.header.data_hash, [0u8; HASH_SIZE]);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::thread::sleep;
+    #[test]
+    fn block_integrity_checks() {
+        let txs = vec![Transaction {
+            from: "alice".into(),
+            to: "bob".into(),
+            amount: 7,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        }];
+        let data = Some("hello".to_string());
+        let mut block = Block {
+            header: BlockHeader::new(
+                1,
+                [0u8; HASH_SIZE],
+                block_data_hash(&data),
+                merkle_root(&txs),
+                0,
+            ),
+            data,
+            txs,
+        };
+        assert!(block.merkle_root_matches());
+        assert!(block.data_hash_matches());
+
+        block.header.merkle_root[0] ^= 1;
+        assert!(!block.merkle_root_matches());
+        assert_eq!(
+            verify::verify_block(&block, None, 0),
+            Err(verify::BlockError::MerkleMismatch)
+        );
+        block.header.merkle_root[0] ^= 1;
+        block.data = Some("hullo".into());
+        assert!(block.merkle_root_matches());
+        assert!(!block.data_hash_matches());
+    }
 
     #[test]
     fn leading_zero_bits_examples() {
@@ -362,6 +2543,66 @@ mod tests {
         assert_eq!(pow::count_leading_zero_bits(&h), 9);
     }
 
+    #[test]
+    fn target_threshold() {
+        let mut hash = [0u8; HASH_SIZE];
+        hash[0] = 0x12;
+        hash[1] = 0x34;
+        let mut at = [0u8; HASH_SIZE];
+        at[..2].copy_from_slice(&[0x12, 0x34]);
+        assert!(pow::Target(at).meets(&hash));
+        at[1] = 0x35;
+        assert!(pow::Target(at).meets(&hash));
+        at[1] = 0x33;
+        at[2..].fill(0xFF);
+        assert!(!pow::Target(at).meets(&hash));
+    }
+
+    #[test]
+    fn target_from_leading_zero_bits() {
+        assert_eq!(pow::Target::from_leading_zero_bits(0).0, [0xFF; HASH_SIZE]);
+        assert_eq!(pow::Target::from_leading_zero_bits(256).0, [0; HASH_SIZE]);
+        let t = pow::Target::from_leading_zero_bits(12);
+        assert_eq!(t.0[..3], [0x00, 0x0F, 0xFF]);
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..1_000 {
+            let mut h: Hash = rand::Rng::gen(&mut rng);
+            let zeroed = rand::Rng::gen_range(&mut rng, 0..=4);
+            h[..zeroed].fill(0);
+            let bits = rand::Rng::gen_range(&mut rng, 0..=40);
+            assert_eq!(
+                pow::Target::from_leading_zero_bits(bits).meets(&h),
+                pow::count_leading_zero_bits(&h) >= bits
+            );
+        }
+    }
+
+    #[test]
+    fn leading_zero_bits_matches_bytewise() {
+        fn bytewise(hash: &Hash) -> u32 {
+            let mut total = 0u32;
+            for b in hash {
+                if *b == 0 {
+                    total += 8;
+                } else {
+                    total += b.leading_zeros();
+                    break;
+                }
+            }
+            total
+        }
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..10_000 {
+            let mut h: Hash = rand::Rng::gen(&mut rng);
+            // zero a random prefix so the later words get exercised too
+            let zeroed = rand::Rng::gen_range(&mut rng, 0..=HASH_SIZE);
+            h[..zeroed].fill(0);
+            assert_eq!(pow::count_leading_zero_bits(&h), bytewise(&h), "{h:02x?}");
+        }
+    }
+
     #[test]
     fn merkle_root_example() {
         let txs = vec![
@@ -370,18 +2611,27 @@ mod tests {
                 to: "Bob".to_string(),
                 amount: 10,
                 timestamp: 1_600_000_000,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
             Transaction {
                 from: "Bob".to_string(),
                 to: "Charlie".to_string(),
                 amount: 5,
                 timestamp: 1_600_000_100,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
             Transaction {
                 from: "Charlie".to_string(),
                 to: "Dave".to_string(),
                 amount: 2,
                 timestamp: 1_600_000_200,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
         ];
         let root = merkle_root(&txs);
@@ -406,20 +2656,25 @@ mod tests {
                 to: "Bob".to_string(),
                 amount: 10,
                 timestamp: 1_600_000_000,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
             Transaction {
                 from: "Bob".to_string(),
                 to: "Charlie".to_string(),
                 amount: 5,
                 timestamp: 1_600_000_100,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
         ];
         let data = None;
         let data_hash = block_data_hash(&data);
         let merkle = merkle_root(&txs);
-        let header = BlockHeader::new(1, [0u8; HASH_SIZE], data_hash, merkle, 0);
-        let mut block = Block { header, txs, data };
-        block.header.timestamp = 1_600_000_200; // Fix timestamp for test consistency
+        let header = BlockHeader::new_at(1, [0u8; HASH_SIZE], data_hash, merkle, 0, 1_600_000_200);
+        let block = Block { header, txs, data };
         let hash = block.hash();
         // The expected hash value changed from previous versions due to the intentional breaking change
         // in hash calculation logic, specifically the introduction of the new data_hash field.
@@ -434,18 +2689,27 @@ mod tests {
             to: "Bob".to_string(),
             amount: 10,
             timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         let tx2 = Transaction {
             from: "Alice".to_string(),
             to: "Bob".to_string(),
             amount: 10,
             timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         let tx3 = Transaction {
             from: "Alice".to_string(),
             to: "Charlie".to_string(),
             amount: 10,
             timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         assert_eq!(tx1, tx2);
         assert_ne!(tx1, tx3);
@@ -481,6 +2745,9 @@ mod tests {
             to: "Bob".to_string(),
             amount: 10,
             timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         let json = serde_json::to_string(&tx).unwrap();
         let expected_json = r#"{"from":"Alice","to":"Bob","amount":10,"timestamp":1600000000}"#;
@@ -497,12 +2764,18 @@ mod tests {
                 to: "Bob".to_string(),
                 amount: 10,
                 timestamp: 1_600_000_000,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
             Transaction {
                 from: "Bob".to_string(),
                 to: "Charlie".to_string(),
                 amount: 5,
                 timestamp: 1_600_000_100,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
         ];
         let merkle = merkle_root(&txs);
@@ -524,6 +2797,259 @@ mod tests {
         assert_eq!(block.txs, deserialized.txs);
     }
 
+    fn unsigned_tx() -> Transaction {
+        Transaction {
+            from: "Alice".to_string(),
+            to: "Bob".to_string(),
+            amount: 10,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        }
+    }
+
+    #[test]
+    fn block_size_is_header_data_and_txs() {
+        let header = BlockHeader::new(1, [0; HASH_SIZE], [0; HASH_SIZE], [0; HASH_SIZE], 0);
+        let empty = Block {
+            header,
+            data: None,
+            txs: vec![],
+        };
+        let long_name = Transaction {
+            to: "x".repeat(1000),
+            ..unsigned_tx()
+        };
+        let full = Block {
+            txs: vec![unsigned_tx(), long_name.clone()],
+            ..empty.clone()
+        };
+        assert_eq!(
+            full.size_bytes(),
+            empty.size_bytes() + unsigned_tx().size_bytes() + long_name.size_bytes()
+        );
+        assert!(long_name.size_bytes() > 1000);
+    }
+
+    #[test]
+    fn valid_tx_passes_validation() {
+        assert_eq!(unsigned_tx().validate(), Ok(()));
+        // an empty sender is the coinbase, not an error
+        let mint = Transaction {
+            from: String::new(),
+            ..unsigned_tx()
+        };
+        assert_eq!(mint.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_empty_recipient() {
+        let tx = Transaction {
+            to: String::new(),
+            ..unsigned_tx()
+        };
+        assert_eq!(tx.validate(), Err(TxError::EmptyRecipient));
+    }
+
+    #[test]
+    fn validate_rejects_self_transfer() {
+        let tx = Transaction {
+            to: "Alice".to_string(),
+            ..unsigned_tx()
+        };
+        assert_eq!(tx.validate(), Err(TxError::SelfTransfer));
+    }
+
+    #[test]
+    fn validate_rejects_zero_amount() {
+        let tx = Transaction {
+            amount: 0,
+            ..unsigned_tx()
+        };
+        assert_eq!(tx.validate(), Err(TxError::ZeroAmount));
+        assert_eq!(TxError::ZeroAmount.field(), "amount");
+    }
+
+    #[test]
+    fn validate_rejects_long_names() {
+        let tx = Transaction {
+            from: "a".repeat(constants::MAX_ACCOUNT_NAME_LEN + 1),
+            ..unsigned_tx()
+        };
+        let err = tx.validate().unwrap_err();
+        assert_eq!(err.field(), "from");
+        assert_eq!(
+            unsigned_tx().validate_with_max_name_len(3),
+            Err(TxError::NameTooLong {
+                field: "from",
+                max: 3
+            })
+        );
+        assert_eq!(unsigned_tx().validate_with_max_name_len(5), Ok(()));
+    }
+
+    #[test]
+    fn signed_tx_verifies() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let mut tx = unsigned_tx();
+        assert!(!tx.verify_signature());
+        tx.sign(&key);
+        assert!(tx.verify_signature());
+        assert_eq!(
+            tx.pubkey.as_deref(),
+            Some(&key.verifying_key().to_bytes()[..])
+        );
+    }
+
+    #[test]
+    fn tampered_amount_fails_verification() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let mut tx = unsigned_tx();
+        tx.sign(&key);
+        tx.amount += 1;
+        assert!(!tx.verify_signature());
+    }
+
+    #[test]
+    fn signing_bytes_keep_fields_apart() {
+        let split = |from: &str, to: &str, fee| Transaction {
+            from: from.to_string(),
+            to: to.to_string(),
+            fee,
+            ..unsigned_tx()
+        };
+        assert_ne!(
+            split("a|b", "c", 0).signing_bytes(),
+            split("a", "b|c", 0).signing_bytes()
+        );
+        // a fee can't be faked by shifting the other fields along
+        let shifted = Transaction {
+            amount: 1_600_000_000,
+            timestamp: 5,
+            ..split("a", "b|10", 0)
+        };
+        assert_ne!(shifted.signing_bytes(), split("a", "b", 5).signing_bytes());
+        // the signature fields are not part of what is signed
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let mut signed = split("a", "b", 5);
+        signed.sign(&key);
+        assert_eq!(signed.signing_bytes(), split("a", "b", 5).signing_bytes());
+    }
+
+    #[test]
+    fn signed_and_unsigned_txs_have_distinct_roots() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let unsigned = unsigned_tx();
+        let mut signed = unsigned.clone();
+        signed.sign(&key);
+        assert_ne!(
+            merkle_root(std::slice::from_ref(&unsigned)),
+            merkle_root(std::slice::from_ref(&signed))
+        );
+
+        // unsigned JSON is unchanged and still deserializes without the new fields
+        let json = serde_json::to_string(&unsigned).unwrap();
+        assert!(!json.contains("signature"));
+        let back: Transaction = serde_json::from_str(&json).unwrap();
+        assert!(back.signature.is_none() && back.pubkey.is_none());
+        let back: Transaction =
+            serde_json::from_str(&serde_json::to_string(&signed).unwrap()).unwrap();
+        assert!(back.verify_signature());
+    }
+
+    fn txs(n: u64) -> Vec<Transaction> {
+        (0..n)
+            .map(|i| Transaction {
+                from: format!("User{i}"),
+                to: format!("User{}", i + 1),
+                amount: i,
+                timestamp: 1_600_000_000 + i,
+                fee: 0,
+                signature: None,
+                pubkey: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merkle_proof_odd_level() {
+        let txs = txs(3);
+        let root = merkle_root(&txs);
+        let proof = merkle_proof(&txs, 2).unwrap();
+        assert_eq!(proof.len(), 2);
+        // the odd leaf is its own sibling
+        assert_eq!(proof[0], (merkle_leaf::<Sha256Hasher>(&txs[2]), true));
+        assert!(verify_merkle_proof(&txs[2], &proof, &root));
+        for i in 0..txs.len() {
+            let proof = merkle_proof(&txs, i).unwrap();
+            assert!(verify_merkle_proof(&txs[i], &proof, &root));
+        }
+        assert!(!verify_merkle_proof(&txs[0], &proof, &root));
+        assert!(merkle_proof(&txs, 3).is_none());
+    }
+
+    #[test]
+    fn merkle_proof_swapped_sibling_fails() {
+        let txs = txs(4);
+        let root = merkle_root(&txs);
+        let mut proof = merkle_proof(&txs, 1).unwrap();
+        assert!(verify_merkle_proof(&txs[1], &proof, &root));
+        proof[0].0 = merkle_leaf::<Sha256Hasher>(&txs[2]);
+        assert!(!verify_merkle_proof(&txs[1], &proof, &root));
+        let mut proof = merkle_proof(&txs, 1).unwrap();
+        proof[0].1 = !proof[0].1;
+        assert!(!verify_merkle_proof(&txs[1], &proof, &root));
+    }
+
+    #[test]
+    fn canonical_tx_bytes_are_pinned() {
+        let mut tx = Transaction {
+            from: "alice".into(),
+            to: "b\"ob".into(),
+            amount: 10,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        };
+        assert_eq!(
+            canonical_tx_bytes(&tx),
+            br#"{"from":"alice","to":"b\"ob","amount":10,"timestamp":1600000000}"#
+        );
+        tx.fee = 2;
+        tx.signature = Some(vec![1, 255]);
+        tx.pubkey = Some(vec![]);
+        assert_eq!(
+            canonical_tx_bytes(&tx),
+            br#"{"from":"alice","to":"b\"ob","amount":10,"timestamp":1600000000,"fee":2,"signature":[1,255],"pubkey":[]}"#
+        );
+    }
+
+    #[test]
+    fn canonical_tx_bytes_survive_storage_round_trip() {
+        let txs = [
+            Transaction::coinbase("miner", 50),
+            Transaction {
+                from: "alice".into(),
+                to: "bob".into(),
+                amount: 7,
+                timestamp: 1_600_000_000,
+                fee: 3,
+                signature: Some(vec![9; 64]),
+                pubkey: Some(vec![4; 32]),
+            },
+        ];
+        for tx in txs {
+            // the JSON encoding still agrees, so existing merkle roots hold
+            assert_eq!(canonical_tx_bytes(&tx), serde_json::to_vec(&tx).unwrap());
+            let stored: Transaction =
+                bincode::deserialize(&bincode::serialize(&tx).unwrap()).unwrap();
+            assert_eq!(canonical_tx_bytes(&stored), canonical_tx_bytes(&tx));
+            assert_eq!(tx_hash(&stored), tx_hash(&tx));
+        }
+    }
+
     #[test]
     fn merkle_root_empty_txs() {
         let txs: Vec<Transaction> = vec![];
@@ -538,6 +3064,9 @@ mod tests {
             to: "Bob".to_string(),
             amount: 10,
             timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         }];
         let root = merkle_root(&txs);
         let mut hasher = Sha256::new();
@@ -556,12 +3085,18 @@ mod tests {
                 to: "Bob".to_string(),
                 amount: 10,
                 timestamp: 1_600_000_000,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
             Transaction {
                 from: "Bob".to_string(),
                 to: "Charlie".to_string(),
                 amount: 5,
                 timestamp: 1_600_000_100,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
         ];
         let root = merkle_root(&txs);
@@ -592,18 +3127,27 @@ mod tests {
                 to: "Bob".to_string(),
                 amount: 10,
                 timestamp: 1_600_000_000,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
             Transaction {
                 from: "Bob".to_string(),
                 to: "Charlie".to_string(),
                 amount: 5,
                 timestamp: 1_600_000_100,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
             Transaction {
                 from: "Charlie".to_string(),
                 to: "Dave".to_string(),
                 amount: 2,
                 timestamp: 1_600_000_200,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
         ];
         let root = merkle_root(&txs);
@@ -620,6 +3164,9 @@ mod tests {
                 to: format!("User{}", i + 1),
                 amount: i as u64,
                 timestamp: 1_600_000_000 + i as u64 * 100,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             });
         }
         let root = merkle_root(&txs);
@@ -637,22 +3184,34 @@ mod tests {
                 to: "Bob".to_string(),
                 amount: 10,
                 timestamp: 1_600_000_000,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
             Transaction {
                 from: "Bob".to_string(),
                 to: "Charlie".to_string(),
                 amount: 5,
                 timestamp: 1_600_000_100,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
         ];
         let merkle = merkle_root(&txs);
-        let header = BlockHeader::new(1, [0u8; HASH_SIZE], [0u8; HASH_SIZE], merkle, 0);
-        let mut block = Block {
+        let header = BlockHeader::new_at(
+            1,
+            [0u8; HASH_SIZE],
+            [0u8; HASH_SIZE],
+            merkle,
+            0,
+            1_600_000_200,
+        );
+        let block = Block {
             header,
             txs,
             data: None,
         };
-        block.header.timestamp = 1_600_000_200; // Fix timestamp for test consistency
         let hash1 = block.hash();
         let hash2 = block.hash();
         assert_eq!(hash1, hash2);
@@ -669,12 +3228,18 @@ mod tests {
                 to: "Bob".to_string(),
                 amount: 10,
                 timestamp: 1_600_000_000,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
             Transaction {
                 from: "Bob".to_string(),
                 to: "Charlie".to_string(),
                 amount: 5,
                 timestamp: 1_600_000_100,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
         ];
         let merkle1 = merkle_root(&txs1);
@@ -704,24 +3269,43 @@ mod tests {
                 to: "Bob".to_string(),
                 amount: 10,
                 timestamp: 1_600_000_000,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
             Transaction {
                 from: "Bob".to_string(),
                 to: "Charlie".to_string(),
                 amount: 5,
                 timestamp: 1_600_000_100,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
         ];
         let merkle1 = merkle_root(&txs1);
-        let header1 = BlockHeader::new(1, [0u8; HASH_SIZE], [0u8; HASH_SIZE], merkle1, 0);
+        let header1 = BlockHeader::new_at(
+            1,
+            [0u8; HASH_SIZE],
+            [0u8; HASH_SIZE],
+            merkle1,
+            0,
+            1_600_000_000,
+        );
         let block1 = Block {
             header: header1,
             txs: txs1.clone(),
             data: None,
         };
-        sleep(std::time::Duration::from_millis(1000)); // Ensure timestamp would differ
         let merkle2 = merkle_root(&txs1);
-        let header2 = BlockHeader::new(1, [0u8; HASH_SIZE], [0u8; HASH_SIZE], merkle2, 0);
+        let header2 = BlockHeader::new_at(
+            1,
+            [0u8; HASH_SIZE],
+            [0u8; HASH_SIZE],
+            merkle2,
+            0,
+            1_600_000_001,
+        );
         let block2 = Block {
             header: header2,
             txs: txs1,
@@ -737,12 +3321,18 @@ mod tests {
             to: "Bob".to_string(),
             amount: 10,
             timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         let tx2 = Transaction {
             from: "Alice".to_string(),
             to: "Bob".to_string(),
             amount: 10,
             timestamp: 1_600_000_001,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         assert_ne!(tx1, tx2);
     }
@@ -754,14 +3344,44 @@ mod tests {
             to: "Bob".to_string(),
             amount: 10,
             timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         let tx2 = Transaction {
             from: "Alice".to_string(),
             to: "Bob".to_string(),
             amount: 20,
             timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        };
+        assert_ne!(tx1, tx2);
+    }
+
+    #[test]
+    fn transaction_inequality_different_fee_or_signature() {
+        let tx1 = Transaction {
+            from: "Alice".to_string(),
+            to: "Bob".to_string(),
+            amount: 10,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        };
+        let tx2 = Transaction {
+            fee: 5,
+            ..tx1.clone()
         };
         assert_ne!(tx1, tx2);
+        let tx3 = Transaction {
+            signature: Some(vec![1; 64]),
+            pubkey: Some(vec![2; 32]),
+            ..tx1.clone()
+        };
+        assert_ne!(tx1, tx3);
     }
 
     #[test]
@@ -771,12 +3391,18 @@ mod tests {
             to: "Bob".to_string(),
             amount: 10,
             timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         let tx2 = Transaction {
             from: "Alice".to_string(),
             to: "Charlie".to_string(),
             amount: 10,
             timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         assert_ne!(tx1, tx2);
     }
@@ -788,12 +3414,18 @@ mod tests {
             to: "Bob".to_string(),
             amount: 10,
             timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         let tx2 = Transaction {
             from: "Eve".to_string(),
             to: "Bob".to_string(),
             amount: 10,
             timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         assert_ne!(tx1, tx2);
     }
@@ -806,22 +3438,34 @@ mod tests {
                 to: "Bob".to_string(),
                 amount: 10,
                 timestamp: 1_600_000_000,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
             Transaction {
                 from: "Bob".to_string(),
                 to: "Charlie".to_string(),
                 amount: 5,
                 timestamp: 1_600_000_100,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             },
         ];
         let merkle = merkle_root(&txs);
-        let header = BlockHeader::new(1, [0u8; HASH_SIZE], [0u8; HASH_SIZE], merkle, 0);
+        let header = BlockHeader::new_at(
+            1,
+            [0u8; HASH_SIZE],
+            [0u8; HASH_SIZE],
+            merkle,
+            0,
+            1_600_000_200,
+        );
         let mut block = Block {
             header,
             txs,
             data: None,
         };
-        block.header.timestamp = 1_600_000_200; // Fix timestamp for test consistency
         let hash1 = block.hash();
         block.header.nonce += 1;
         let hash2 = block.hash();
@@ -1,55 +1,386 @@
 use crate::{
-    block_data_hash, block_header_hash, constants::HASH_SIZE, merkle_root,
-    pow::count_leading_zero_bits, Block, BlockHeader, Transaction,
+    constants::{HASH_SIZE, MAX_TXS_PER_BLOCK},
+    digest::HashAlgo,
+    error::LedgerError,
+    pow::Target,
+    Block, BlockHeader, Transaction,
 };
 use rayon::prelude::*;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tracing::info;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    mpsc, Arc,
+};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{info, info_span, warn};
 
 /// Mines a block by searching nonces in parallel until a header hash has at least `target` leading zero bits.
-/// Returns the mined Block (with header.nonce set) and its hash.
+/// Returns the mined Block (with header.nonce set) and its hash, or
+/// [`LedgerError::NonceSpaceExhausted`] if no nonce meets `target`.
 pub fn mine_block_parallel(
     index: u64,
     prev_hash: [u8; HASH_SIZE],
     txs: Vec<Transaction>,
     data: Option<String>,
     target: u32,
-) -> (Block, [u8; HASH_SIZE]) {
-    // Construct a header "template" (we'll vary only the nonce per attempt).
-    let timestamp = SystemTime::now()
+) -> Result<(Block, [u8; HASH_SIZE]), LedgerError> {
+    mine_block_parallel_with_deadline(
+        index,
+        prev_hash,
+        txs,
+        data,
+        target,
+        None,
+        Arc::new(AtomicBool::new(false)),
+    )
+    .ok_or(LedgerError::NonceSpaceExhausted)
+}
+
+/// [`mine_block_parallel`] with the header dated `timestamp` rather than
+/// now. Workers race, so the nonce may still differ between runs; use
+/// [`crate::pow::mine_block`] when the hash itself must repeat.
+pub fn mine_block_parallel_at(
+    index: u64,
+    prev_hash: [u8; HASH_SIZE],
+    txs: Vec<Transaction>,
+    data: Option<String>,
+    target: u32,
+    timestamp: u64,
+) -> Result<(Block, [u8; HASH_SIZE]), LedgerError> {
+    mine_parallel(
+        HashAlgo::Sha256,
+        index,
+        prev_hash,
+        txs,
+        data,
+        Target::from_leading_zero_bits(target),
+        timestamp,
+        NonceShard::ALL,
+        None,
+        Arc::new(AtomicBool::new(false)),
+        None,
+    )
+    .ok_or(LedgerError::NonceSpaceExhausted)
+}
+
+/// [`mine_block_parallel`] searching for a header hash that meets `target`
+/// rather than a leading-zero-bit count, for difficulty steps finer than a
+/// factor of two.
+pub fn mine_block_parallel_to_target(
+    index: u64,
+    prev_hash: [u8; HASH_SIZE],
+    txs: Vec<Transaction>,
+    data: Option<String>,
+    target: Target,
+) -> Result<(Block, [u8; HASH_SIZE]), LedgerError> {
+    mine_parallel(
+        HashAlgo::Sha256,
+        index,
+        prev_hash,
+        txs,
+        data,
+        target,
+        now_secs(),
+        NonceShard::ALL,
+        None,
+        Arc::new(AtomicBool::new(false)),
+        None,
+    )
+    .ok_or(LedgerError::NonceSpaceExhausted)
+}
+
+/// One of `count` disjoint slices of the nonce space: the nonces `n` with
+/// `n % count == index`. Miners given different shards of the same count
+/// never try the same nonce.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NonceShard {
+    index: u64,
+    count: u64,
+}
+
+impl NonceShard {
+    /// The whole nonce space.
+    pub const ALL: NonceShard = NonceShard { index: 0, count: 1 };
+
+    /// Shard `index` of `count`, or `None` unless `index < count`.
+    pub fn new(index: u64, count: u64) -> Option<Self> {
+        (index < count).then_some(Self { index, count })
+    }
+
+    /// Remainder the shard's nonces leave when divided by [`count`](Self::count).
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// How many shards the nonce space is split into.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Whether `nonce` falls in this shard.
+    pub fn contains(&self, nonce: u64) -> bool {
+        nonce % self.count == self.index
+    }
+
+    /// The `k`th nonce of the shard.
+    fn nonce(&self, k: u64) -> u64 {
+        k * self.count + self.index
+    }
+
+    /// `k` of the shard's last nonce.
+    fn last(&self) -> u64 {
+        (u64::MAX - self.index) / self.count
+    }
+}
+
+impl Default for NonceShard {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// [`mine_block_parallel_with_deadline`] searching only the nonces of shard
+/// `shard` of `shard_count`, so that many machines can mine the same block
+/// without repeating each other's work. Returns `None` once `deadline` passes,
+/// e.g. because another shard will have found the block by then, and
+/// [`LedgerError::InvalidShard`] unless `shard` is below `shard_count`.
+#[allow(clippy::too_many_arguments)]
+pub fn mine_block_parallel_shard(
+    index: u64,
+    prev_hash: [u8; HASH_SIZE],
+    txs: Vec<Transaction>,
+    data: Option<String>,
+    target: u32,
+    shard: u64,
+    shard_count: u64,
+    deadline: Option<Instant>,
+) -> Result<Option<(Block, [u8; HASH_SIZE])>, LedgerError> {
+    let shard = NonceShard::new(shard, shard_count).ok_or(LedgerError::InvalidShard {
+        shard,
+        count: shard_count,
+    })?;
+    Ok(mine_block_parallel_in_shard(
+        HashAlgo::Sha256,
+        index,
+        prev_hash,
+        txs,
+        data,
+        target,
+        now_secs(),
+        shard,
+        deadline,
+        Arc::new(AtomicBool::new(false)),
+    ))
+}
+
+/// How many nonces each worker tries between checks of the deadline and cancel flag.
+const STOP_CHECK_INTERVAL: u64 = 4096;
+
+/// How often [`mine_block_parallel_with_progress`] reports the nonces tried.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Like [`mine_block_parallel`], also sending the approximate number of
+/// nonces tried so far to `progress` every 50ms, and once more when done.
+/// The count moves in steps of a few thousand. Sending stops early if the
+/// receiver is dropped; mining carries on.
+pub fn mine_block_parallel_with_progress(
+    index: u64,
+    prev_hash: [u8; HASH_SIZE],
+    txs: Vec<Transaction>,
+    data: Option<String>,
+    target: u32,
+    progress: mpsc::Sender<u64>,
+) -> Result<(Block, [u8; HASH_SIZE]), LedgerError> {
+    let tried = AtomicU64::new(0);
+    let done = AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            while !done.load(Ordering::Relaxed) {
+                std::thread::sleep(PROGRESS_INTERVAL);
+                if progress.send(tried.load(Ordering::Relaxed)).is_err() {
+                    return;
+                }
+            }
+        });
+        let mined = mine_parallel(
+            HashAlgo::Sha256,
+            index,
+            prev_hash,
+            txs,
+            data,
+            Target::from_leading_zero_bits(target),
+            now_secs(),
+            NonceShard::ALL,
+            None,
+            Arc::new(AtomicBool::new(false)),
+            Some(&tried),
+        )
+        .ok_or(LedgerError::NonceSpaceExhausted);
+        done.store(true, Ordering::Relaxed);
+        let _ = progress.send(tried.load(Ordering::Relaxed));
+        mined
+    })
+}
+
+/// Like [`mine_block_parallel`], but gives up and returns `None` once `deadline`
+/// passes or `cancel` is set. Workers poll both every few thousand nonces.
+/// Transactions past [`MAX_TXS_PER_BLOCK`] are dropped; callers are expected
+/// to leave them queued instead.
+pub fn mine_block_parallel_with_deadline(
+    index: u64,
+    prev_hash: [u8; HASH_SIZE],
+    txs: Vec<Transaction>,
+    data: Option<String>,
+    target: u32,
+    deadline: Option<Instant>,
+    cancel: Arc<AtomicBool>,
+) -> Option<(Block, [u8; HASH_SIZE])> {
+    mine_block_parallel_with_algo(
+        HashAlgo::Sha256,
+        index,
+        prev_hash,
+        txs,
+        data,
+        target,
+        deadline,
+        cancel,
+    )
+}
+
+/// [`mine_block_parallel_with_deadline`] hashing the header, data and merkle
+/// tree with `algo` instead of SHA-256.
+#[allow(clippy::too_many_arguments)]
+pub fn mine_block_parallel_with_algo(
+    algo: HashAlgo,
+    index: u64,
+    prev_hash: [u8; HASH_SIZE],
+    txs: Vec<Transaction>,
+    data: Option<String>,
+    target: u32,
+    deadline: Option<Instant>,
+    cancel: Arc<AtomicBool>,
+) -> Option<(Block, [u8; HASH_SIZE])> {
+    mine_block_parallel_in_shard(
+        algo,
+        index,
+        prev_hash,
+        txs,
+        data,
+        target,
+        now_secs(),
+        NonceShard::ALL,
+        deadline,
+        cancel,
+    )
+}
+
+/// [`mine_block_parallel_with_algo`] with the header dated `timestamp`,
+/// restricted to the nonces of `shard`.
+#[allow(clippy::too_many_arguments)]
+pub fn mine_block_parallel_in_shard(
+    algo: HashAlgo,
+    index: u64,
+    prev_hash: [u8; HASH_SIZE],
+    txs: Vec<Transaction>,
+    data: Option<String>,
+    target: u32,
+    timestamp: u64,
+    shard: NonceShard,
+    deadline: Option<Instant>,
+    cancel: Arc<AtomicBool>,
+) -> Option<(Block, [u8; HASH_SIZE])> {
+    mine_parallel(
+        algo,
+        index,
+        prev_hash,
+        txs,
+        data,
+        Target::from_leading_zero_bits(target),
+        timestamp,
+        shard,
+        deadline,
+        cancel,
+        None,
+    )
+}
+
+/// Seconds since the Unix epoch; a clock set before 1970 reads as 0.
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .expect("time went backwards")
-        .as_secs();
-
-    let merkle = merkle_root(&txs);
-    let data_hash = block_data_hash(&data);
-
-    // We’ll reuse this structure and mutate the nonce per attempt.
-    let mut header = BlockHeader::new(index, prev_hash, data_hash, merkle, timestamp);
-    header.nonce = 0;
-
-    // Prepare immutable parts for hashing closure
-    let base_header = header;
-
-    // Parallel search over the entire u64 range. Rayon will split this range across threads.
-    let found = (0u64..=u64::MAX)
-        .into_par_iter()
-        .find_any(|nonce| {
-            let mut h = base_header;
-            h.nonce = *nonce;
-            let hash = block_header_hash(h);
-            count_leading_zero_bits(&hash) >= target
-        })
-        .expect("nonce space exhausted (practically impossible)");
+        .map_or(0, |d| d.as_secs())
+}
+
+/// The search behind every `mine_block_parallel*` variant. When `tried` is
+/// given, each worker adds to it as it passes a stop check.
+#[allow(clippy::too_many_arguments)]
+fn mine_parallel(
+    algo: HashAlgo,
+    index: u64,
+    prev_hash: [u8; HASH_SIZE],
+    mut txs: Vec<Transaction>,
+    data: Option<String>,
+    target: Target,
+    timestamp: u64,
+    shard: NonceShard,
+    deadline: Option<Instant>,
+    cancel: Arc<AtomicBool>,
+    tried: Option<&AtomicU64>,
+) -> Option<(Block, [u8; HASH_SIZE])> {
+    let _span = info_span!("mine", index, target = %target).entered();
+    let started = Instant::now();
+    if txs.len() > MAX_TXS_PER_BLOCK {
+        warn!(
+            "block {index}: dropping {} txs past MAX_TXS_PER_BLOCK",
+            txs.len() - MAX_TXS_PER_BLOCK
+        );
+        txs.truncate(MAX_TXS_PER_BLOCK);
+    }
+
+    let merkle = algo.merkle_root(&txs);
+    let data_hash = algo.data_hash(&data);
+
+    // Construct a header "template" (we'll vary only the nonce per attempt).
+    let base_header = BlockHeader::new_at(index, prev_hash, data_hash, merkle, 0, timestamp);
+
+    // Parallel search over the shard's slice of the u64 range, by position
+    // `k` within the shard. Rayon will split this range across threads.
+    // Set once the deadline passes or the caller cancels; every worker then
+    // bails out of `find_any` on its next nonce.
+    let stopped = AtomicBool::new(false);
+    let found = (0u64..=shard.last()).into_par_iter().find_any(|k| {
+        if k % STOP_CHECK_INTERVAL == 0 {
+            if let Some(tried) = tried {
+                tried.fetch_add(STOP_CHECK_INTERVAL, Ordering::Relaxed);
+            }
+            if cancel.load(Ordering::Relaxed) || deadline.is_some_and(|d| Instant::now() >= d) {
+                stopped.store(true, Ordering::Relaxed);
+            }
+        }
+        if stopped.load(Ordering::Relaxed) {
+            return true;
+        }
+        let mut h = base_header;
+        h.nonce = shard.nonce(*k);
+        let hash = algo.header_hash(h);
+        target.meets(&hash)
+    })?;
+    let found = shard.nonce(found);
 
     // Build final block with the winning nonce and hash
     let mut final_header = base_header;
     final_header.nonce = found;
-    let final_hash = block_header_hash(final_header);
+    let final_hash = algo.header_hash(final_header);
+    if !target.meets(&final_hash) {
+        // `find_any` returned because mining was stopped, not because it succeeded
+        return None;
+    }
 
     info!(
-        "Mined block {} with nonce {} and hash {:x?}",
-        index, found, final_hash
+        nonce = found,
+        hash = %hex::encode(final_hash),
+        txs = txs.len(),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "mined block"
     );
 
     let block = Block {
@@ -57,5 +388,5 @@ pub fn mine_block_parallel(
         data,
         txs,
     };
-    (block, final_hash)
+    Some((block, final_hash))
 }
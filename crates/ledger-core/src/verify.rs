@@ -0,0 +1,224 @@
+//! Stand-alone validation of a single mined block, e.g. one received from a peer.
+
+use crate::{
+    constants::MAX_FUTURE_DRIFT_SECS, digest::HashAlgo, mine::now_secs,
+    pow::count_leading_zero_bits, Block, Hash,
+};
+use thiserror::Error;
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum BlockError {
+    #[error("header hash has {zeros} leading zero bits, need {target}")]
+    InsufficientWork { zeros: u32, target: u32 },
+    #[error("merkle root does not match the block's transactions")]
+    MerkleMismatch,
+    #[error("data hash does not match the block's data")]
+    DataHashMismatch,
+    #[error(
+        "previous_hash {} does not match parent hash {}",
+        hex::encode(found),
+        hex::encode(expected)
+    )]
+    BrokenLink { expected: Hash, found: Hash },
+    #[error("index {found} does not follow parent index {parent}")]
+    NonContiguousIndex { parent: u64, found: u64 },
+    #[error("timestamp {timestamp} is more than {max_drift}s ahead of local time {now}")]
+    FutureTimestamp {
+        timestamp: u64,
+        now: u64,
+        max_drift: u64,
+    },
+    #[error("timestamp {found} is not after parent timestamp {parent}")]
+    TimestampRegression { parent: u64, found: u64 },
+}
+
+/// Why [`Chain::validate_range`](crate::chain::Chain::validate_range) stopped.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum ValidationFailure {
+    #[error("block is missing from the store")]
+    Missing,
+    #[error("store error: {0}")]
+    Store(String),
+    #[error(transparent)]
+    Block(#[from] BlockError),
+}
+
+/// The first offending index found while validating a stored segment.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("block {index}: {reason}")]
+pub struct ValidationReport {
+    pub index: u64,
+    pub reason: ValidationFailure,
+}
+
+/// Check that `block` carries valid proof of work for `target` leading zero
+/// bits, that its merkle root and data hash match its contents, that it is
+/// dated no more than [`MAX_FUTURE_DRIFT_SECS`] ahead of local time, and, when
+/// `prev` is given, that it links to, directly follows and is dated after
+/// that block.
+pub fn verify_block(block: &Block, prev: Option<&Block>, target: u32) -> Result<(), BlockError> {
+    verify_block_with_algo(block, prev, target, HashAlgo::Sha256)
+}
+
+/// [`verify_block`] for a chain hashed with `algo`.
+pub fn verify_block_with_algo(
+    block: &Block,
+    prev: Option<&Block>,
+    target: u32,
+    algo: HashAlgo,
+) -> Result<(), BlockError> {
+    verify_block_with_drift(block, prev, target, algo, MAX_FUTURE_DRIFT_SECS)
+}
+
+/// [`verify_block_with_algo`] allowing timestamps up to `max_future_drift`
+/// seconds ahead of local time.
+///
+/// A child must be dated strictly after its parent, so
+/// [`Chain`](crate::chain::Chain) mining stamps a block at least a second
+/// past its parent even when both are found within the same second.
+pub fn verify_block_with_drift(
+    block: &Block,
+    prev: Option<&Block>,
+    target: u32,
+    algo: HashAlgo,
+    max_future_drift: u64,
+) -> Result<(), BlockError> {
+    let zeros = count_leading_zero_bits(&algo.header_hash(block.header));
+    if zeros < target {
+        return Err(BlockError::InsufficientWork { zeros, target });
+    }
+    if !block.merkle_root_matches_with(algo) {
+        return Err(BlockError::MerkleMismatch);
+    }
+    if !block.data_hash_matches_with(algo) {
+        return Err(BlockError::DataHashMismatch);
+    }
+    if let Some(prev) = prev {
+        let expected = algo.header_hash(prev.header);
+        if block.header.previous_hash != expected {
+            return Err(BlockError::BrokenLink {
+                expected,
+                found: block.header.previous_hash,
+            });
+        }
+        if prev.header.index.checked_add(1) != Some(block.header.index) {
+            return Err(BlockError::NonContiguousIndex {
+                parent: prev.header.index,
+                found: block.header.index,
+            });
+        }
+        if block.header.timestamp <= prev.header.timestamp {
+            return Err(BlockError::TimestampRegression {
+                parent: prev.header.timestamp,
+                found: block.header.timestamp,
+            });
+        }
+    }
+    let now = now_secs();
+    if block.header.timestamp > now.saturating_add(max_future_drift) {
+        return Err(BlockError::FutureTimestamp {
+            timestamp: block.header.timestamp,
+            now,
+            max_drift: max_future_drift,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Transaction;
+    use crate::{chain::genesis_block, mine::mine_block_parallel_at, pow::mine_genesis_block};
+
+    fn pair() -> (Block, Block) {
+        let genesis = mine_genesis_block(genesis_block(), 4);
+        let tx = Transaction {
+            from: "alice".into(),
+            to: "bob".into(),
+            amount: 7,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        };
+        let ts = genesis.header.timestamp + 1;
+        let (next, _) = mine_block_parallel_at(1, genesis.hash(), vec![tx], None, 4, ts).unwrap();
+        (genesis, next)
+    }
+
+    #[test]
+    fn accepts_valid_block() {
+        let (genesis, next) = pair();
+        assert_eq!(verify_block(&genesis, None, 4), Ok(()));
+        assert_eq!(verify_block(&next, Some(&genesis), 4), Ok(()));
+    }
+
+    #[test]
+    fn rejects_each_kind_of_fault() {
+        let (genesis, next) = pair();
+
+        assert!(matches!(
+            verify_block(&next, Some(&genesis), 255),
+            Err(BlockError::InsufficientWork { target: 255, .. })
+        ));
+
+        let mut b = next.clone();
+        b.txs[0].amount = 700;
+        assert_eq!(verify_block(&b, None, 0), Err(BlockError::MerkleMismatch));
+
+        let mut b = next.clone();
+        b.data = Some("forged".into());
+        assert_eq!(verify_block(&b, None, 0), Err(BlockError::DataHashMismatch));
+
+        let mut b = next.clone();
+        b.header.previous_hash = [9u8; 32];
+        assert!(matches!(
+            verify_block(&b, Some(&genesis), 0),
+            Err(BlockError::BrokenLink { .. })
+        ));
+
+        let mut b = next.clone();
+        b.header.timestamp = genesis.header.timestamp - 1;
+        assert_eq!(
+            verify_block(&b, Some(&genesis), 0),
+            Err(BlockError::TimestampRegression {
+                parent: genesis.header.timestamp,
+                found: genesis.header.timestamp - 1,
+            })
+        );
+        // sharing the parent's timestamp is not enough either
+        b.header.timestamp = genesis.header.timestamp;
+        assert_eq!(
+            verify_block(&b, Some(&genesis), 0),
+            Err(BlockError::TimestampRegression {
+                parent: genesis.header.timestamp,
+                found: genesis.header.timestamp,
+            })
+        );
+
+        let mut b = next.clone();
+        b.header.timestamp += MAX_FUTURE_DRIFT_SECS + 3600;
+        assert!(matches!(
+            verify_block(&b, None, 0),
+            Err(BlockError::FutureTimestamp {
+                max_drift: MAX_FUTURE_DRIFT_SECS,
+                ..
+            })
+        ));
+        assert_eq!(
+            verify_block_with_drift(&b, None, 0, HashAlgo::Sha256, 3 * MAX_FUTURE_DRIFT_SECS),
+            Ok(())
+        );
+
+        let mut b = next.clone();
+        b.header.index = 5;
+        assert_eq!(
+            verify_block(&b, Some(&genesis), 0),
+            Err(BlockError::NonContiguousIndex {
+                parent: 0,
+                found: 5
+            })
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! Distribution of transactions per block, served by `/chain/tx-histogram`.
+//!
+//! Building it means reading every block, so the last result is cached
+//! against the tip hash and bucket count and reused until a new block lands.
+
+use serde::Serialize;
+
+/// Default number of buckets when the request does not ask for one.
+pub const DEFAULT_BUCKETS: usize = 10;
+/// Upper bound on `?buckets=`, to keep responses small.
+pub const MAX_BUCKETS: usize = 100;
+
+/// Blocks whose transaction count falls in `lo..=hi`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Bucket {
+    pub lo: usize,
+    pub hi: usize,
+    pub count: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TxHistogram {
+    pub blocks: u64,
+    pub total_txs: u64,
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+    pub buckets: Vec<Bucket>,
+}
+
+impl TxHistogram {
+    /// Tally per-block transaction counts into at most `buckets` equal-width
+    /// buckets spanning `min..=max`. Fewer buckets are produced when the span
+    /// is narrower than the requested count.
+    pub fn from_counts(counts: impl IntoIterator<Item = usize>, buckets: usize) -> Self {
+        let counts: Vec<usize> = counts.into_iter().collect();
+        let min = counts.iter().copied().min().unwrap_or(0);
+        let max = counts.iter().copied().max().unwrap_or(0);
+        let total_txs: u64 = counts.iter().map(|&c| c as u64).sum();
+        let blocks = counts.len() as u64;
+        let mean = if blocks == 0 {
+            0.0
+        } else {
+            total_txs as f64 / blocks as f64
+        };
+
+        let span = max - min + 1;
+        let n = buckets.clamp(1, span);
+        let width = span.div_ceil(n);
+        let mut out: Vec<Bucket> = (0..n)
+            .map(|i| Bucket {
+                lo: min + i * width,
+                hi: (min + (i + 1) * width - 1).min(max),
+                count: 0,
+            })
+            .filter(|b| b.lo <= max)
+            .collect();
+        for c in counts {
+            out[(c - min) / width].count += 1;
+        }
+
+        Self {
+            blocks,
+            total_txs,
+            min,
+            max,
+            mean,
+            buckets: out,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_cover_min_to_max() {
+        let h = TxHistogram::from_counts([0, 1, 2, 3, 4, 9], 5);
+        assert_eq!((h.min, h.max, h.blocks, h.total_txs), (0, 9, 6, 19));
+        let ranges: Vec<_> = h.buckets.iter().map(|b| (b.lo, b.hi, b.count)).collect();
+        assert_eq!(
+            ranges,
+            vec![(0, 1, 2), (2, 3, 2), (4, 5, 1), (6, 7, 0), (8, 9, 1)]
+        );
+    }
+
+    #[test]
+    fn narrow_span_yields_fewer_buckets() {
+        let h = TxHistogram::from_counts([3, 3, 4], 10);
+        assert_eq!(h.buckets.len(), 2);
+        assert_eq!(h.buckets.iter().map(|b| b.count).sum::<u64>(), 3);
+    }
+}
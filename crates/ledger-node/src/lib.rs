@@ -0,0 +1,1449 @@
+//! HTTP API for the ledger node.
+//!
+//! The router and its handlers live in the library half of the crate so the
+//! integration tests can drive them in-process; `main.rs` only parses args,
+//! opens the store, and serves.
+
+pub mod histogram;
+pub mod mempool;
+pub mod metrics;
+pub mod tx_input;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, Request, State,
+    },
+    http::{header, Method, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{delete, get, post},
+    Json, Router,
+};
+use ledger_core::{
+    chain::{AcceptOutcome, Chain, ChainStore},
+    error::LedgerError,
+    ledger::BalanceLedger,
+    pow::count_leading_zero_bits,
+    Block, BlockHeader, Hash, Transaction,
+};
+use ledger_storage::sled_store::SledStore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::{broadcast, Mutex};
+use tokio_stream::{
+    wrappers::{BroadcastStream, ReceiverStream},
+    Stream, StreamExt,
+};
+use tower_http::{
+    cors::{AllowOrigin, CorsLayer},
+    trace::TraceLayer,
+};
+use tracing::{info, warn};
+
+use ledger_core::constants::{
+    BLOCKS_PER_BATCH, HASH_HEX_SIZE, HASH_SIZE, MAX_BLOCKS_PER_REQUEST, MAX_TXS_PER_BLOCK,
+    POW_TARGET_DIFFICULTY,
+};
+
+use crate::histogram::{TxHistogram, DEFAULT_BUCKETS, MAX_BUCKETS};
+use crate::mempool::{Admitted, Mempool, PendingTx, Rejection};
+use crate::metrics::NodeMetrics;
+use crate::tx_input::{FieldError, TxIn};
+
+#[derive(Clone)]
+pub struct AppState {
+    pub chain: Chain<SledStore>,
+    pub mempool: Arc<Mutex<Mempool>>,
+    /// Strip `null` fields from explorer responses (`--compact-json`).
+    pub compact_json: bool,
+    /// When the node started serving, for `uptime_secs`.
+    pub started_at: Instant,
+    /// Longest a single `/mine` request may search before giving up (`--max-mine-secs`).
+    pub max_mine: Option<Duration>,
+    /// Largest encoded block `/mine` will seal (`--max-block-bytes`).
+    pub max_block_bytes: Option<usize>,
+    /// Highest `target` `/mine` accepts (`--max-target`).
+    pub max_target: u32,
+    /// Leading zero bits every block `POST /block` places must meet
+    /// (`--block-target`); the submitter has no say in it.
+    pub block_target: u32,
+    /// Last `/chain/tx-histogram` result, keyed by tip hash and bucket count.
+    pub tx_histogram_cache: Arc<Mutex<Option<(Hash, usize, TxHistogram)>>>,
+    /// Every block `/mine` persists is published here for `/chain/subscribe`.
+    pub new_blocks: broadcast::Sender<BlockRow>,
+    /// Leave out mempool transactions whose sender can't cover them (`--enforce-balances`).
+    pub enforce_balances: bool,
+    /// Counters and timings served at `/metrics`.
+    pub metrics: NodeMetrics,
+    /// Serve `POST /chain/prune` (`--allow-prune`); refused with 403 otherwise.
+    pub allow_prune: bool,
+    /// Origins allowed to call the API from a browser (`--cors-origin`);
+    /// `*` allows any. Empty sends no CORS headers, so only same-origin
+    /// pages can read responses.
+    pub cors_origins: Vec<String>,
+    /// Bearer token the write endpoints require (`--write-token`); `None`
+    /// leaves them open.
+    pub write_token: Option<String>,
+    /// Blocks `POST /block` received before their parent, keyed by the
+    /// `previous_hash` they are waiting on.
+    pub orphan_pool: Arc<Mutex<HashMap<Hash, Block>>>,
+    /// Latest timestamp handed to a submitted tx; see [`AppState::tx_timestamp`].
+    pub last_tx_timestamp: Arc<AtomicU64>,
+}
+
+/// Blocks a slow `/chain/subscribe` client may fall behind before it skips ahead.
+const NEW_BLOCKS_CAPACITY: usize = 64;
+
+/// Orphans `POST /block` buffers at most; more are refused until some connect.
+pub const MAX_ORPHANS: usize = 256;
+
+/// Default `--max-target`: each extra bit doubles the expected search, and
+/// past this a single request can run for hours.
+pub const DEFAULT_MAX_TARGET: u32 = 28;
+
+impl AppState {
+    pub fn new(chain: Chain<SledStore>) -> Self {
+        Self {
+            chain,
+            mempool: Arc::new(Mutex::new(Mempool::new())),
+            compact_json: false,
+            started_at: Instant::now(),
+            max_mine: None,
+            max_block_bytes: None,
+            max_target: DEFAULT_MAX_TARGET,
+            block_target: POW_TARGET_DIFFICULTY,
+            tx_histogram_cache: Arc::new(Mutex::new(None)),
+            new_blocks: broadcast::channel(NEW_BLOCKS_CAPACITY).0,
+            enforce_balances: false,
+            metrics: NodeMetrics::new(),
+            allow_prune: false,
+            cors_origins: Vec::new(),
+            write_token: None,
+            orphan_pool: Arc::new(Mutex::new(HashMap::new())),
+            last_tx_timestamp: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Timestamp for a newly submitted tx: the current second, or one past
+    /// the last one handed out if that is later. Every tx this node stamps
+    /// is unique and [`sort_block_txs`](ledger_core::sort_block_txs) keeps
+    /// them in submission order; a burst of more than one tx a second runs
+    /// ahead of the wall clock until submissions slow down.
+    pub fn tx_timestamp(&self) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let next = |last: u64| now.max(last + 1);
+        let last = self
+            .last_tx_timestamp
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |last| {
+                Some(next(last))
+            })
+            .expect("the update always succeeds");
+        next(last)
+    }
+
+    /// Replace the default mempool, e.g. with one built from CLI limits.
+    pub fn with_mempool(mut self, mempool: Mempool) -> Self {
+        self.mempool = Arc::new(Mutex::new(mempool));
+        self
+    }
+
+    /// Queue the transactions an earlier run left pending in the store,
+    /// behind any already in the mempool (e.g. from `--seed-txs`), then
+    /// persist the pool as it now stands. Returns how many were restored.
+    pub async fn restore_mempool(&self) -> anyhow::Result<usize> {
+        let store = self.chain.store();
+        let mut mempool = self.mempool.lock().await;
+        let mut restored = 0;
+        for (_, tx) in store.pending_txs()? {
+            // stamps handed out before the restart may be ahead of the clock
+            self.last_tx_timestamp
+                .fetch_max(tx.timestamp, Ordering::Relaxed);
+            if mempool.admit(tx).is_ok() {
+                restored += 1;
+            }
+        }
+        store.replace_pending_txs(mempool.pending().iter().map(|p| (p.seq, &p.tx)))?;
+        Ok(restored)
+    }
+
+    /// Explorer row for a stored block, hashed with the chain's algorithm.
+    fn block_row(&self, b: &Block) -> BlockRow {
+        BlockRow::new(b, self.chain.block_hash(b))
+    }
+
+    /// Serialize an explorer response, dropping `null` fields when `compact_json` is set.
+    fn json<T: Serialize>(&self, body: &T) -> Json<Value> {
+        let mut value = serde_json::to_value(body).unwrap_or(Value::Null);
+        if self.compact_json {
+            strip_nulls(&mut value);
+        }
+        Json(value)
+    }
+}
+
+/// Mirror admissions into the persisted mempool. The sled write runs on the
+/// blocking pool, so a slow flush never stalls the runtime. A failure is
+/// logged rather than returned: the transactions are queued either way.
+async fn persist_admitted(state: &AppState, admitted: &[(Transaction, Admitted)]) {
+    let store = state.chain.store().clone();
+    let pending: Vec<(u64, Transaction)> =
+        admitted.iter().map(|(tx, a)| (a.seq, tx.clone())).collect();
+    let evicted: Vec<Transaction> = admitted
+        .iter()
+        .filter_map(|(_, a)| a.evicted.as_ref().map(|p| p.tx.clone()))
+        .collect();
+    let result = tokio::task::spawn_blocking(move || {
+        store
+            .put_pending_txs(pending.iter().map(|(seq, tx)| (*seq, tx)))
+            .and_then(|()| store.remove_pending_txs(&evicted))
+    })
+    .await;
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!("failed to persist mempool: {e:#}"),
+        Err(e) => warn!("failed to persist mempool: {e}"),
+    }
+}
+
+/// Drop transactions that left the mempool for good from its persisted copy.
+fn forget_pending(state: &AppState, txs: &[Transaction]) {
+    if let Err(e) = state.chain.store().remove_pending_txs(txs) {
+        warn!("failed to persist mempool: {e:#}");
+    }
+}
+
+/// Drop the transactions of a block placed by `POST /block` from the
+/// mempool and its persisted copy, so `/mine` doesn't seal them again.
+fn forget_included(state: &AppState, mempool: &mut Mempool, block: &Block) {
+    mempool.remove_included(&block.txs);
+    forget_pending(state, &block.txs);
+}
+
+/// Render `{"error": message}` with the given status.
+fn api_error(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, Json(serde_json::json!({ "error": message.into() }))).into_response()
+}
+
+/// HTTP status for each kind of [`LedgerError`].
+fn ledger_status(e: &LedgerError) -> StatusCode {
+    match e {
+        LedgerError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        LedgerError::Verification(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        LedgerError::MiningTimeout => StatusCode::GATEWAY_TIMEOUT,
+        LedgerError::NonceSpaceExhausted => StatusCode::UNPROCESSABLE_ENTITY,
+        LedgerError::InvalidShard { .. } => StatusCode::BAD_REQUEST,
+        LedgerError::StaleParent(_) => StatusCode::CONFLICT,
+        LedgerError::NotFound(_) => StatusCode::NOT_FOUND,
+        LedgerError::InvalidTx(_) => StatusCode::BAD_REQUEST,
+    }
+}
+
+/// Render `{"error": message, "error_code": code}` with the status for `e`'s kind.
+fn ledger_error(e: LedgerError) -> Response {
+    let body = serde_json::json!({ "error": e.to_string(), "error_code": e.code() });
+    (ledger_status(&e), Json(body)).into_response()
+}
+
+/// Recursively remove `null` members from JSON objects.
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+#[derive(Serialize)]
+struct Health {
+    status: &'static str,
+}
+
+#[derive(Serialize)]
+struct Head {
+    height: u64,
+}
+
+#[derive(Serialize)]
+struct Tip {
+    height: u64,
+    hash: Option<String>,
+}
+
+/// Everything the dashboard needs in one call; `last_block_age_secs` makes a stalled miner obvious.
+#[derive(Serialize)]
+struct ChainStatus {
+    height: u64,
+    tip_hash: Option<String>,
+    last_block_age_secs: Option<u64>,
+    uptime_secs: u64,
+    mempool_size: usize,
+}
+
+/// Outcome of `POST /block`.
+#[derive(Serialize)]
+struct SubmitBlockResult {
+    /// `extended`, `duplicate`, `fork` or `orphan` (buffered until its
+    /// parent arrives).
+    status: &'static str,
+    /// Indices of buffered orphans this block let connect, in order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    connected: Vec<u64>,
+}
+
+#[derive(Deserialize)]
+struct PruneParams {
+    /// Drop the bodies of blocks with a lower index.
+    below: u64,
+}
+
+/// Optional body of `POST /chain/validate`; an absent body checks the whole
+/// chain.
+#[derive(Deserialize, Default)]
+struct ValidateParams {
+    /// First index to check (default: genesis).
+    from: Option<u64>,
+    /// Last index to check (default: the tip).
+    to: Option<u64>,
+    /// Leading zero bits every block must meet (default: 0, links and
+    /// indices only, since `/mine` lets each block pick its own target).
+    target: Option<u32>,
+}
+
+/// Outcome of `POST /chain/validate`.
+#[derive(Serialize)]
+struct ValidateResult {
+    valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bad_index: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+/// Aggregates over every stored block, for `/chain/stats`.
+#[derive(Serialize, Default)]
+struct ChainStats {
+    total_blocks: u64,
+    total_txs: u64,
+    avg_txs_per_block: f64,
+    /// Mean gap between consecutive header timestamps; `None` below two blocks.
+    avg_block_time_secs: Option<f64>,
+    /// Leading zero bits of the tip hash: the work the tip actually carries,
+    /// at least the target it was mined at.
+    tip_target: Option<u32>,
+    /// Leading zero bits of the last [`RECENT_DIFFICULTY_BLOCKS`] block
+    /// hashes, oldest first.
+    recent_difficulty: Vec<u32>,
+    /// [`Chain::estimated_hashrate`] over the same recent blocks, in hashes
+    /// per second; `None` below two blocks or when they share a timestamp.
+    estimated_hashrate: Option<f64>,
+}
+
+/// Blocks `/chain/stats` reports in `recent_difficulty`.
+const RECENT_DIFFICULTY_BLOCKS: usize = 32;
+
+#[derive(Deserialize)]
+pub struct MineParams {
+    /// Leading zeros required in the hash, default is 20. At most
+    /// `max_target`; 0 accepts the first nonce tried.
+    target: Option<u32>,
+    data: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct HistogramParams {
+    buckets: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct ListParams {
+    start: Option<u64>,
+    limit: Option<u32>,
+    dir: Option<String>,
+    /// Wrap the rows as `{"blocks": [...], "next_start": N}`.
+    paged: Option<bool>,
+    /// Keep only blocks with a transaction from or to this account. Applied
+    /// after `limit`, so a page may hold fewer rows than it scanned.
+    account: Option<String>,
+}
+
+/// One row of the `/chain/blocks` explorer listing.
+///
+/// `data` and `data_hash` are omitted entirely for blocks without data rather
+/// than being filled with placeholder values.
+#[derive(Clone, Debug, Serialize)]
+pub struct BlockRow {
+    index: u64,
+    ts: u64,
+    tx_count: usize,
+    hash: String,
+    nonce: u64,
+    previous_hash: String,
+    merkle_root: String,
+    /// Leading zero bits of `hash`, i.e. the difficulty the block satisfies.
+    difficulty: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<String>,
+}
+
+impl BlockRow {
+    /// Row for `b`, whose header hashes to `hash` under the chain's algorithm.
+    fn new(b: &Block, hash: Hash) -> Self {
+        BlockRow {
+            index: b.header.index,
+            ts: b.header.timestamp,
+            tx_count: b.txs.len(),
+            hash: hex::encode(hash),
+            nonce: b.header.nonce,
+            previous_hash: hex::encode(b.header.previous_hash),
+            merkle_root: hex::encode(b.header.merkle_root),
+            difficulty: count_leading_zero_bits(&hash),
+            data_hash: b.data.as_ref().map(|_| hex::encode(b.header.data_hash)),
+            data: b.data.clone(),
+        }
+    }
+}
+
+/// A single block with its full transaction list, as served by `/chain/block/{index}`.
+#[derive(Serialize)]
+pub struct BlockDetail {
+    #[serde(flatten)]
+    row: BlockRow,
+    /// Number of blocks from this one up to and including the tip.
+    confirmations: u64,
+    txs: Vec<Transaction>,
+    /// Set when the body was pruned; `txs` and `data` are then gone.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pruned: bool,
+}
+
+/// Response of `/mine`; only the fields relevant to the outcome are present.
+#[derive(Serialize, Default)]
+pub struct MineResult {
+    mined: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    merkle_root: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tx_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// [`LedgerError::code`] of `error`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_code: Option<&'static str>,
+}
+
+impl MineResult {
+    /// A failed attempt, with the status for `e`'s kind.
+    fn failed(state: &AppState, e: LedgerError) -> Response {
+        let body = state.json(&MineResult {
+            mined: false,
+            error: Some(e.to_string()),
+            error_code: Some(e.code()),
+            ..Default::default()
+        });
+        (ledger_status(&e), body).into_response()
+    }
+}
+
+/// Build the node's HTTP router over the given state.
+pub fn router(state: AppState) -> Router {
+    // every route that changes the chain or mempool sits behind `--write-token`
+    let auth = middleware::from_fn_with_state(state.clone(), require_write_token);
+    let router = Router::new()
+        .route("/health", get(health))
+        .route("/healthz", get(health))
+        .route("/chain/head", get(chain_head))
+        .route("/chain/tip", get(chain_tip))
+        .route("/chain/status", get(chain_status))
+        .route("/chain/genesis", get(chain_genesis))
+        .route("/chain/block/{index}", get(chain_block))
+        .route("/chain/block/by-hash/{hash}", get(chain_block_by_hash))
+        .route("/chain/tx-histogram", get(tx_histogram))
+        .route("/chain/stats", get(chain_stats))
+        .route("/chain/validate", post(validate_chain))
+        .route("/chain/prune", post(prune_chain).layer(auth.clone()))
+        .route("/chain/subscribe", get(chain_subscribe))
+        .route("/ws", get(ws_blocks))
+        .route("/chain/export", get(chain_export))
+        .route("/tx", post(submit_tx).layer(auth.clone()))
+        .route("/tx/batch", post(submit_tx_batch).layer(auth.clone()))
+        .route("/tx/{hash}", get(get_tx))
+        .route("/mine", get(mine).layer(auth.clone()))
+        .route("/block", post(submit_block).layer(auth.clone()))
+        .route("/chain/blocks", get(list_blocks))
+        .route(
+            "/mempool",
+            get(mempool).merge(delete(clear_mempool).layer(auth)),
+        )
+        .route("/mempool/count", get(mempool_count))
+        .route("/metrics", get(metrics))
+        .route("/balance/{account}", get(balance))
+        .layer(TraceLayer::new_for_http());
+    let router = match cors_layer(&state.cors_origins) {
+        Some(cors) => router.layer(cors),
+        None => router,
+    };
+    router.with_state(state)
+}
+
+/// Reject the request with 401 unless it carries `Authorization: Bearer
+/// <token>` matching `--write-token`. A no-op when no token is configured.
+async fn require_write_token(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some(token) = &state.write_token else {
+        return next.run(req).await;
+    };
+    let presented = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if presented.is_some_and(|p| constant_time_eq(p.as_bytes(), token.as_bytes())) {
+        return next.run(req).await;
+    }
+    let mut resp = api_error(StatusCode::UNAUTHORIZED, "missing or invalid bearer token");
+    resp.headers_mut().insert(
+        header::WWW_AUTHENTICATE,
+        header::HeaderValue::from_static("Bearer"),
+    );
+    resp
+}
+
+/// Compare without an early exit, so response timing doesn't reveal how
+/// much of a guessed token was right. Only the length can leak.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// CORS for `origins`, or `None` when no origin is configured. Origins that
+/// aren't valid header values are skipped with a warning.
+fn cors_layer(origins: &[String]) -> Option<CorsLayer> {
+    if origins.is_empty() {
+        return None;
+    }
+    let allow = if origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        let parsed: Vec<header::HeaderValue> = origins
+            .iter()
+            .filter_map(|o| match o.parse() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    warn!("ignoring invalid CORS origin {o:?}");
+                    None
+                }
+            })
+            .collect();
+        AllowOrigin::list(parsed)
+    };
+    Some(
+        CorsLayer::new()
+            .allow_origin(allow)
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
+            .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION]),
+    )
+}
+
+/// Serve the router on `listener` until `shutdown` resolves, let in-flight
+/// requests finish, then close the store so sled flushes to disk.
+pub async fn serve<L>(
+    listener: L,
+    state: AppState,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<()>
+where
+    L: axum::serve::Listener,
+    L::Addr: std::fmt::Debug,
+{
+    let store = state.chain.store().clone();
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(shutdown)
+        .await?;
+    info!("shutting down, flushing store");
+    store.close()
+}
+
+/// Bind a Unix domain socket at `path` for [`serve`], first removing a
+/// socket file left behind by a node that didn't shut down cleanly. A
+/// socket something still answers on, or any other kind of file, is an
+/// error rather than being removed.
+#[cfg(unix)]
+pub fn bind_unix(path: &std::path::Path) -> anyhow::Result<tokio::net::UnixListener> {
+    use anyhow::Context;
+    use std::os::unix::fs::FileTypeExt;
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if !meta.file_type().is_socket() {
+            anyhow::bail!("{} exists and is not a socket", path.display());
+        }
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            anyhow::bail!("{} is already in use", path.display());
+        }
+        std::fs::remove_file(path)
+            .with_context(|| format!("failed to remove stale socket {}", path.display()))?;
+    }
+    tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("failed to bind {}", path.display()))
+}
+
+/// How the node writes its log records (`--log-format`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per record, for log aggregators.
+    Json,
+}
+
+/// The subscriber the node logs through, writing to `writer` in `format`.
+/// Either way `RUST_LOG` selects what gets logged.
+pub fn log_subscriber<W>(format: LogFormat, writer: W) -> Box<dyn tracing::Subscriber + Send + Sync>
+where
+    W: for<'w> tracing_subscriber::fmt::MakeWriter<'w> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(writer);
+    match format {
+        LogFormat::Text => Box::new(builder.finish()),
+        LogFormat::Json => Box::new(builder.json().finish()),
+    }
+}
+
+/// Resolve on Ctrl-C (SIGINT) or, on Unix, SIGTERM.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("failed to listen for Ctrl-C: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                warn!("failed to listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+async fn health() -> Json<Health> {
+    Json(Health { status: "ok" })
+}
+
+/// Carries the tip hash as its `ETag`, so pollers can send it back in
+/// `If-None-Match` and get an empty `304` until a new block lands.
+async fn chain_head(State(state): State<AppState>, headers: header::HeaderMap) -> Response {
+    let (height, hash) = state.chain.tip().unwrap_or((0, None));
+    let Some(hash) = hash else {
+        return Json(Head { height }).into_response();
+    };
+    let etag = format!("\"{}\"", hex::encode(hash));
+    let matches = headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|tag| {
+            let tag = tag.trim();
+            tag == "*" || tag.trim_start_matches("W/") == etag
+        });
+    if matches {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+    ([(header::ETAG, etag)], Json(Head { height })).into_response()
+}
+
+async fn chain_tip(State(state): State<AppState>) -> Json<Value> {
+    // height and hash both come from the one block, so they always agree
+    let tip = state.chain.tip_block().ok().flatten();
+    state.json(&Tip {
+        height: tip.as_ref().map_or(0, |b| b.header.index),
+        hash: tip.map(|b| hex::encode(state.chain.block_hash(&b))),
+    })
+}
+
+async fn chain_status(State(state): State<AppState>) -> Response {
+    let tip = match state.chain.tip_block() {
+        Ok(tip) => tip,
+        Err(e) => return ledger_error(e),
+    };
+    let height = tip.as_ref().map_or(0, |b| b.header.index);
+    let hash = tip.as_ref().map(|b| state.chain.block_hash(b));
+    let tip_ts = tip.map(|b| b.header.timestamp);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mempool_size = state.mempool.lock().await.len();
+    state
+        .json(&ChainStatus {
+            height,
+            tip_hash: hash.map(hex::encode),
+            last_block_age_secs: tip_ts.map(|ts| now.saturating_sub(ts)),
+            uptime_secs: state.started_at.elapsed().as_secs(),
+            mempool_size,
+        })
+        .into_response()
+}
+
+/// Return block 0 so clients can check they are on the same chain as this node.
+async fn chain_genesis(State(state): State<AppState>) -> Response {
+    match state.chain.store().get_block(0) {
+        Ok(Some(block)) => state.json(&state.block_row(&block)).into_response(),
+        Ok(None) => api_error(StatusCode::SERVICE_UNAVAILABLE, "genesis block not ready"),
+        Err(e) => api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+async fn chain_block(State(state): State<AppState>, Path(index): Path<u64>) -> Response {
+    match state.chain.store().get_block(index) {
+        Ok(Some(block)) => block_detail(&state, block),
+        Ok(None) => api_error(StatusCode::NOT_FOUND, format!("block {index} not found")),
+        Err(e) => api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+async fn chain_block_by_hash(
+    State(state): State<AppState>,
+    Path(hex_hash): Path<String>,
+) -> Response {
+    let mut hash: Hash = Default::default();
+    if hex::decode_to_slice(&hex_hash, &mut hash).is_err() {
+        return api_error(
+            StatusCode::BAD_REQUEST,
+            format!("{hex_hash:?} is not a {HASH_HEX_SIZE}-character hex hash"),
+        );
+    }
+    match state.chain.get_block_by_hash(&hash) {
+        Ok(Some(block)) => block_detail(&state, block),
+        Ok(None) => api_error(StatusCode::NOT_FOUND, format!("block {hex_hash} not found")),
+        Err(e) => ledger_error(e),
+    }
+}
+
+/// A mined transaction by its [`tx_hash`](ledger_core::tx_hash), with the
+/// index of the block holding it.
+async fn get_tx(State(state): State<AppState>, Path(hex_hash): Path<String>) -> Response {
+    let mut hash: Hash = Default::default();
+    if hex::decode_to_slice(&hex_hash, &mut hash).is_err() {
+        return api_error(
+            StatusCode::BAD_REQUEST,
+            format!("{hex_hash:?} is not a {HASH_HEX_SIZE}-character hex hash"),
+        );
+    }
+    match state.chain.get_transaction(&hash) {
+        Ok(Some((block, tx))) => state
+            .json(&serde_json::json!({ "tx_hash": hex_hash, "block": block, "tx": tx }))
+            .into_response(),
+        Ok(None) => api_error(
+            StatusCode::NOT_FOUND,
+            format!("transaction {hex_hash} not found"),
+        ),
+        Err(e) => ledger_error(e),
+    }
+}
+
+/// Render a block with its transactions and confirmation count.
+fn block_detail(state: &AppState, block: Block) -> Response {
+    let height = match state.chain.tip() {
+        Ok((height, _)) => height,
+        Err(e) => return ledger_error(e),
+    };
+    let index = block.header.index;
+    let pruned = match state.chain.store().is_pruned(index) {
+        Ok(pruned) => pruned,
+        Err(e) => return api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    state
+        .json(&BlockDetail {
+            row: state.block_row(&block),
+            confirmations: (height + 1).saturating_sub(index),
+            txs: block.txs,
+            pruned,
+        })
+        .into_response()
+}
+
+/// Distribution of transactions per block across the whole chain.
+async fn tx_histogram(State(state): State<AppState>, Query(p): Query<HistogramParams>) -> Response {
+    let buckets = p.buckets.unwrap_or(DEFAULT_BUCKETS).clamp(1, MAX_BUCKETS);
+    let (height, tip_hash) = match state.chain.tip() {
+        Ok((height, Some(hash))) => (height, hash),
+        Ok((_, None)) => return api_error(StatusCode::SERVICE_UNAVAILABLE, "chain is empty"),
+        Err(e) => return ledger_error(e),
+    };
+
+    let mut cache = state.tx_histogram_cache.lock().await;
+    if let Some((hash, n, histogram)) = cache.as_ref() {
+        if *hash == tip_hash && *n == buckets {
+            return state.json(histogram).into_response();
+        }
+    }
+
+    let mut counts = Vec::with_capacity(height as usize + 1);
+    for index in 0..=height {
+        match state.chain.store().get_block(index) {
+            Ok(Some(block)) => counts.push(block.txs.len()),
+            Ok(None) => {}
+            Err(e) => return api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        }
+    }
+    let histogram = TxHistogram::from_counts(counts, buckets);
+    let body = state.json(&histogram);
+    *cache = Some((tip_hash, buckets, histogram));
+    body.into_response()
+}
+
+/// Block and transaction totals and timing, from one pass over the store.
+async fn chain_stats(State(state): State<AppState>) -> Response {
+    let mut stats = ChainStats::default();
+    let mut first_ts = None;
+    let mut last = None;
+    let mut recent = std::collections::VecDeque::with_capacity(RECENT_DIFFICULTY_BLOCKS);
+    for block in state.chain.store().iter_blocks(0, false) {
+        let block = match block {
+            Ok(block) => block,
+            Err(e) => return api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        };
+        stats.total_blocks += 1;
+        stats.total_txs += block.txs.len() as u64;
+        first_ts.get_or_insert(block.header.timestamp);
+        if recent.len() == RECENT_DIFFICULTY_BLOCKS {
+            recent.pop_front();
+        }
+        let zeros = count_leading_zero_bits(&state.chain.block_hash(&block));
+        recent.push_back(zeros);
+        last = Some((block.header, zeros));
+    }
+    stats.recent_difficulty = recent.into();
+    stats.estimated_hashrate = match state.chain.estimated_hashrate(RECENT_DIFFICULTY_BLOCKS) {
+        Ok(rate) => rate,
+        Err(e) => return api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    if let (Some(first_ts), Some((tip, tip_zeros))) = (first_ts, last) {
+        stats.avg_txs_per_block = stats.total_txs as f64 / stats.total_blocks as f64;
+        if stats.total_blocks > 1 {
+            let span = tip.timestamp.saturating_sub(first_ts);
+            stats.avg_block_time_secs = Some(span as f64 / (stats.total_blocks - 1) as f64);
+        }
+        stats.tip_target = Some(tip_zeros);
+    }
+    state.json(&stats).into_response()
+}
+
+/// Re-verify a stored range with [`Chain::validate_range`]. The walk reads
+/// every block in the range, so it runs on the blocking pool.
+async fn validate_chain(
+    State(state): State<AppState>,
+    body: Option<Json<ValidateParams>>,
+) -> Response {
+    let params = body.map(|Json(p)| p).unwrap_or_default();
+    let chain = state.chain.clone();
+    let walk = tokio::task::spawn_blocking(move || {
+        let to = match params.to {
+            Some(to) => to,
+            None => chain.tip()?.0,
+        };
+        let from = params.from.unwrap_or(0);
+        if from > to {
+            return Ok(None);
+        }
+        Ok::<_, LedgerError>(Some(chain.validate_range(
+            from,
+            to,
+            params.target.unwrap_or(0),
+        )))
+    })
+    .await;
+    let result = match walk {
+        Ok(Ok(Some(Ok(())))) => ValidateResult {
+            valid: true,
+            bad_index: None,
+            reason: None,
+        },
+        Ok(Ok(Some(Err(report)))) => ValidateResult {
+            valid: false,
+            bad_index: Some(report.index),
+            reason: Some(report.reason.to_string()),
+        },
+        Ok(Ok(None)) => return api_error(StatusCode::BAD_REQUEST, "from is after to"),
+        Ok(Err(e)) => return ledger_error(e),
+        Err(e) => return api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    Json(result).into_response()
+}
+
+/// Drop the bodies of old blocks with [`SledStore::prune_bodies_below`].
+/// Pruned transactions no longer count towards `/balance` or the histogram.
+async fn prune_chain(State(state): State<AppState>, Query(p): Query<PruneParams>) -> Response {
+    if !state.allow_prune {
+        return api_error(
+            StatusCode::FORBIDDEN,
+            "pruning is disabled; start the node with --allow-prune",
+        );
+    }
+    let store = state.chain.store().clone();
+    let pruned = match tokio::task::spawn_blocking(move || store.prune_bodies_below(p.below)).await
+    {
+        Ok(Ok(pruned)) => pruned,
+        Ok(Err(e)) => return api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        Err(e) => return api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    // cached tx counts describe the bodies that are now gone
+    *state.tx_histogram_cache.lock().await = None;
+    Json(serde_json::json!({ "pruned": pruned })).into_response()
+}
+
+/// Replay every stored block on top of the balances of the pruned ones.
+/// Pruned stubs carry no transactions, so replaying them changes nothing.
+fn replay_balances(state: &AppState) -> anyhow::Result<BalanceLedger> {
+    let (height, _) = state.chain.tip()?;
+    let mut ledger = state.chain.store().pruned_balances()?;
+    for index in 0..=height {
+        if let Some(block) = state.chain.store().get_block(index)? {
+            ledger.apply_block(&block);
+        }
+    }
+    Ok(ledger)
+}
+
+/// Balance of `account`, rebuilt by replaying every stored block.
+async fn balance(State(state): State<AppState>, Path(account): Path<String>) -> Response {
+    let balance = match replay_balances(&state) {
+        Ok(ledger) => ledger.balance_of(&account),
+        Err(e) => return api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    state
+        .json(&serde_json::json!({ "account": account, "balance": balance }))
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct ExportParams {
+    from: Option<u64>,
+    to: Option<u64>,
+}
+
+/// Blocks `from..=to` (default: the whole chain) as `application/x-ndjson`.
+/// The export runs on the blocking pool and is streamed out as it is
+/// written, so a full-chain export never sits in memory.
+async fn chain_export(State(state): State<AppState>, Query(p): Query<ExportParams>) -> Response {
+    let height = match state.chain.tip() {
+        Ok((height, _)) => height,
+        Err(e) => return ledger_error(e),
+    };
+    let from = p.from.unwrap_or(0);
+    let to = p.to.unwrap_or(height).min(height);
+    let (tx, rx) = tokio::sync::mpsc::channel(EXPORT_CHUNKS_IN_FLIGHT);
+    let chain = state.chain.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut w = std::io::BufWriter::with_capacity(EXPORT_CHUNK_BYTES, ChunkSender(tx.clone()));
+        let result = chain
+            .export_ndjson(&mut w, from, to)
+            .map_err(|e| std::io::Error::other(e.to_string()))
+            .and_then(|_| std::io::Write::flush(&mut w));
+        // an error ends the body early, so the client sees a broken
+        // response rather than a short export
+        if let Err(e) = result {
+            warn!("export failed: {e}");
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response()
+}
+
+/// Bytes per chunk of a streamed `/chain/export` body.
+const EXPORT_CHUNK_BYTES: usize = 64 * 1024;
+/// Chunks buffered ahead of a slow `/chain/export` client before the
+/// export waits for it.
+const EXPORT_CHUNKS_IN_FLIGHT: usize = 4;
+
+/// Forwards each write as one body chunk; fails once the client is gone,
+/// which stops the export.
+struct ChunkSender(tokio::sync::mpsc::Sender<std::io::Result<Bytes>>);
+
+impl std::io::Write for ChunkSender {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Server-Sent Events stream with one `data: {block row}` event per newly mined block.
+async fn chain_subscribe(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let compact = state.compact_json;
+    let stream = BroadcastStream::new(state.new_blocks.subscribe()).filter_map(move |row| {
+        // a lagging client just misses the blocks it fell behind on
+        let mut value = serde_json::to_value(row.ok()?).ok()?;
+        if compact {
+            strip_nulls(&mut value);
+        }
+        Some(Ok(Event::default().data(value.to_string())))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// WebSocket twin of [`chain_subscribe`] for browsers: the current tip as
+/// the first text message, then one block row per newly mined block.
+async fn ws_blocks(State(state): State<AppState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| forward_blocks(socket, state))
+}
+
+async fn forward_blocks(mut socket: WebSocket, state: AppState) {
+    // subscribe before reading the tip so a block mined in between isn't lost
+    let mut blocks = state.new_blocks.subscribe();
+    let (height, hash) = state.chain.tip().unwrap_or((0, None));
+    let tip = state.json(&Tip {
+        height,
+        hash: hash.map(hex::encode),
+    });
+    if socket.send(Message::text(tip.0.to_string())).await.is_err() {
+        return;
+    }
+    loop {
+        tokio::select! {
+            row = blocks.recv() => match row {
+                Ok(row) => {
+                    let value = state.json(&row).0;
+                    if socket.send(Message::text(value.to_string())).await.is_err() {
+                        break;
+                    }
+                }
+                // a lagging client just misses the blocks it fell behind on
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Accept a transaction into the mempool.
+///
+/// The body is taken as raw bytes and parsed leniently so that malformed
+/// input is reported per field rather than with serde's generic rejection.
+async fn submit_tx(State(state): State<AppState>, body: Bytes) -> Result<Response, FieldError> {
+    let tx = TxIn::parse(&body)?.into_transaction_at(state.tx_timestamp());
+    tx.validate()?;
+    // an empty sender is the reserved coinbase; only mining may issue one
+    if tx.is_coinbase() {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "accepted": false, "reason": "coinbase" })),
+        )
+            .into_response());
+    }
+    if !fits_a_block(&state, &tx) {
+        return Ok((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Json(serde_json::json!({ "accepted": false, "reason": "too_large" })),
+        )
+            .into_response());
+    }
+    // the hash covers the server timestamp, so it is only known from here on
+    let (hash, timestamp) = (ledger_core::tx_hash(&tx), tx.timestamp);
+    // The sequence is assigned under the same lock as the push, so concurrent
+    // submissions always observe distinct, contiguous positions.
+    let admitted = {
+        let mut mempool = state.mempool.lock().await;
+        let admitted = mempool.admit(tx.clone());
+        // persist under the lock, so a concurrent /mine can't seal and
+        // forget the tx before it is written
+        if let Ok(a) = &admitted {
+            persist_admitted(&state, &[(tx, a.clone())]).await;
+        }
+        admitted
+    };
+    Ok(match admitted {
+        Ok(Admitted { seq, evicted }) => {
+            state.metrics.tx_accepted(1);
+            Json(serde_json::json!({
+                "accepted": true,
+                "tx_hash": hex::encode(hash),
+                "timestamp": timestamp,
+                "seq": seq,
+                "evicted_seq": evicted.map(|p| p.seq),
+            }))
+            .into_response()
+        }
+        Err(rejection) => (
+            match rejection {
+                Rejection::Full => StatusCode::TOO_MANY_REQUESTS,
+                Rejection::Duplicate => StatusCode::CONFLICT,
+            },
+            Json(serde_json::json!({ "accepted": false, "reason": rejection.reason() })),
+        )
+            .into_response(),
+    })
+}
+
+/// Accept many transactions at once. Every entry is validated on its own and
+/// all that pass are admitted under a single mempool lock; entries that are
+/// malformed, coinbase, too large for any block, duplicates, or don't fit
+/// are reported by position.
+async fn submit_tx_batch(
+    State(state): State<AppState>,
+    body: Bytes,
+) -> Result<Response, FieldError> {
+    let parsed = TxIn::parse_batch(&body)?;
+    let mut accepted = Vec::new();
+    let mut rejected = Vec::new();
+    let mut mempool = state.mempool.lock().await;
+    for (i, tx) in parsed.into_iter().enumerate() {
+        let tx = tx
+            .ok()
+            .map(|tx| tx.into_transaction_at(state.tx_timestamp()))
+            .filter(|tx| tx.validate().is_ok() && !tx.is_coinbase() && fits_a_block(&state, tx));
+        match tx.map(|tx| (mempool.admit(tx.clone()), tx)) {
+            Some((Ok(admitted), tx)) => accepted.push((tx, admitted)),
+            _ => rejected.push(i),
+        }
+    }
+    persist_admitted(&state, &accepted).await;
+    drop(mempool);
+    let accepted = accepted.len();
+    state.metrics.tx_accepted(accepted as u64);
+    Ok(Json(serde_json::json!({ "accepted": accepted, "rejected": rejected })).into_response())
+}
+
+/// Encoded size of a block holding only `data` and the chain's coinbase:
+/// what every block costs before its mempool transactions.
+fn base_block_bytes(chain: &Chain<SledStore>, data: &Option<String>) -> usize {
+    let coinbase = chain
+        .reward()
+        .map(|r| Transaction::coinbase(&r.miner, r.amount));
+    Block {
+        header: BlockHeader::new(0, [0; HASH_SIZE], [0; HASH_SIZE], [0; HASH_SIZE], 0),
+        data: data.clone(),
+        txs: coinbase.into_iter().collect(),
+    }
+    .size_bytes()
+}
+
+/// Whether `tx` fits under `--max-block-bytes` in a block of its own, so it
+/// can ever be mined.
+fn fits_a_block(state: &AppState, tx: &Transaction) -> bool {
+    state
+        .max_block_bytes
+        .is_none_or(|max| base_block_bytes(&state.chain, &None) + tx.size_bytes() <= max)
+}
+
+/// Keep each transaction, in order, that still fits in a block of at most
+/// `max_bytes`, counting the header, `data` and the chain's coinbase. One
+/// that doesn't fit is skipped, not the end of packing; the skipped ones are
+/// returned second, to go back to the mempool.
+fn pack_by_size(
+    chain: &Chain<SledStore>,
+    data: &Option<String>,
+    txs: Vec<PendingTx>,
+    max_bytes: usize,
+) -> (Vec<PendingTx>, Vec<PendingTx>) {
+    let mut size = base_block_bytes(chain, data);
+    txs.into_iter().partition(|p| {
+        let fits = size + p.tx.size_bytes() <= max_bytes;
+        if fits {
+            size += p.tx.size_bytes();
+        }
+        fits
+    })
+}
+
+/// Greedily keep the transactions whose sender stays non-negative, in
+/// mempool order; the rest go back into the mempool.
+async fn affordable_txs(state: &AppState, txs: Vec<PendingTx>) -> anyhow::Result<Vec<PendingTx>> {
+    let mut ledger = match replay_balances(state) {
+        Ok(ledger) => ledger,
+        Err(e) => {
+            state.mempool.lock().await.restore(txs);
+            return Err(e);
+        }
+    };
+    let (affordable, overdrawn): (Vec<_>, Vec<_>) = txs.into_iter().partition(|p| {
+        let ok = ledger.can_afford(&p.tx);
+        if ok {
+            ledger.apply_tx(&p.tx);
+        }
+        ok
+    });
+    if !overdrawn.is_empty() {
+        for PendingTx { tx, .. } in &overdrawn {
+            warn!(
+                "leaving {} -> {} ({}) out of the block: insufficient balance",
+                tx.from, tx.to, tx.amount
+            );
+        }
+        state.mempool.lock().await.restore(overdrawn);
+    }
+    Ok(affordable)
+}
+
+async fn mine(State(state): State<AppState>, Query(params): Query<MineParams>) -> Response {
+    let mut chain = state.chain.clone();
+    let target_zeros = params.target.unwrap_or(20);
+    if target_zeros > state.max_target {
+        let body = serde_json::json!({ "error": "target too high", "max": state.max_target });
+        return (StatusCode::BAD_REQUEST, Json(body)).into_response();
+    }
+    let data = params.data;
+    // highest fees first, so they are the ones that make it in under the
+    // block limit; the rest wait for the next block
+    let room = MAX_TXS_PER_BLOCK - usize::from(chain.reward().is_some());
+    let mut txs = state.mempool.lock().await.take_by_fee(room);
+    if let Some(max_bytes) = state.max_block_bytes {
+        let (fit, rest) = pack_by_size(&chain, &data, txs, max_bytes);
+        txs = fit;
+        state.mempool.lock().await.restore(rest);
+    }
+    // fees decide which txs make it in; the block holds them in a fixed
+    // order, which the balance check has to follow
+    txs.sort_by_cached_key(|p| ledger_core::block_order_key(&p.tx));
+    if state.enforce_balances {
+        txs = match affordable_txs(&state, txs).await {
+            Ok(txs) => txs,
+            Err(e) => return MineResult::failed(&state, e.into()),
+        };
+    }
+    info!(txs = txs.len(), "/mine endpoint called");
+
+    let started = Instant::now();
+    let deadline = state.max_mine.map(|d| started + d);
+    let cancel = Arc::new(AtomicBool::new(false));
+    let block_txs = txs.iter().map(|p| p.tx.clone()).collect();
+    let mined = chain.mine_with_deadline(block_txs, data, target_zeros, deadline, cancel);
+    match &mined {
+        Ok(_) => state.metrics.block_mined(started.elapsed()),
+        _ => state.metrics.mining_failed(started.elapsed()),
+    }
+    match mined {
+        Ok((block, hash)) => {
+            // `Ok` means the block is stored, so its transactions are settled
+            forget_pending(&state, &block.txs);
+            // no subscribers is not an error
+            let _ = state.new_blocks.send(BlockRow::new(&block, hash));
+            state
+                .json(&MineResult {
+                    mined: true,
+                    height: Some(block.header.index),
+                    nonce: Some(block.header.nonce),
+                    hash: Some(hex::encode(hash)),
+                    previous_hash: Some(hex::encode(block.header.previous_hash)),
+                    merkle_root: Some(hex::encode(block.header.merkle_root)),
+                    data_hash: block
+                        .data
+                        .as_ref()
+                        .map(|_| hex::encode(block.header.data_hash)),
+                    tx_count: Some(block.txs.len()),
+                    target: Some(target_zeros),
+                    data: block.data,
+                    ..Default::default()
+                })
+                .into_response()
+        }
+        Err(e) => {
+            // nothing was stored, whether mining stopped or a block received
+            // meanwhile took its place, so hand the transactions back
+            state.mempool.lock().await.restore(txs);
+            MineResult::failed(&state, e)
+        }
+    }
+}
+
+/// Place a block received from elsewhere with [`Chain::accept_block`] at
+/// [`AppState::block_target`]. An orphan is kept in
+/// [`AppState::orphan_pool`]; once a block extends the tip, buffered
+/// descendants are connected after it one by one, each checked at the same
+/// target. Transactions of every block placed leave the mempool.
+async fn submit_block(State(state): State<AppState>, Json(block): Json<Block>) -> Response {
+    let mut chain = state.chain.clone();
+    // held throughout so concurrent submissions connect orphans in order
+    let mut orphans = state.orphan_pool.lock().await;
+    let outcome = match chain.accept_block(block.clone(), state.block_target) {
+        Ok(outcome) => outcome,
+        Err(e) => return ledger_error(e),
+    };
+    let status = match outcome {
+        AcceptOutcome::Extended => "extended",
+        AcceptOutcome::Duplicate => "duplicate",
+        AcceptOutcome::Fork => "fork",
+        AcceptOutcome::Orphan => {
+            let parent = block.header.previous_hash;
+            if orphans.len() >= MAX_ORPHANS && !orphans.contains_key(&parent) {
+                return api_error(StatusCode::SERVICE_UNAVAILABLE, "orphan pool is full");
+            }
+            orphans.insert(parent, block);
+            let body = SubmitBlockResult {
+                status: "orphan",
+                connected: Vec::new(),
+            };
+            return (StatusCode::ACCEPTED, Json(body)).into_response();
+        }
+    };
+    let mut connected = Vec::new();
+    if outcome == AcceptOutcome::Extended {
+        let mut mempool = state.mempool.lock().await;
+        forget_included(&state, &mut mempool, &block);
+        let _ = state.new_blocks.send(state.block_row(&block));
+        let mut tip = chain.block_hash(&block);
+        while let Some(child) = orphans.remove(&tip) {
+            match chain.accept_block(child.clone(), state.block_target) {
+                Ok(AcceptOutcome::Extended) => {
+                    forget_included(&state, &mut mempool, &child);
+                    let _ = state.new_blocks.send(state.block_row(&child));
+                    connected.push(child.header.index);
+                    tip = chain.block_hash(&child);
+                }
+                Ok(other) => {
+                    warn!("dropping buffered block {}: {other:?}", child.header.index);
+                    break;
+                }
+                Err(e) => {
+                    warn!("dropping buffered block {}: {e}", child.header.index);
+                    break;
+                }
+            }
+        }
+    }
+    state
+        .json(&SubmitBlockResult { status, connected })
+        .into_response()
+}
+
+async fn list_blocks(State(state): State<AppState>, Query(p): Query<ListParams>) -> Json<Value> {
+    let (height, _) = state.chain.tip().unwrap_or((0, None));
+    let limit = p
+        .limit
+        .unwrap_or(BLOCKS_PER_BATCH)
+        .min(MAX_BLOCKS_PER_REQUEST);
+    let desc = p.dir.as_deref() != Some("asc");
+    // walk from the newest block down, or from genesis up
+    let start = p.start.unwrap_or(if desc { height } else { 0 });
+
+    let blocks = state
+        .chain
+        .list_blocks_range(start, limit, desc)
+        .unwrap_or_default();
+
+    let touches = |block: &&Block| match &p.account {
+        Some(account) => block
+            .txs
+            .iter()
+            .any(|tx| tx.from == *account || tx.to == *account),
+        None => true,
+    };
+    let rows: Vec<BlockRow> = blocks
+        .iter()
+        .filter(touches)
+        .map(|b| state.block_row(b))
+        .collect();
+    if !p.paged.unwrap_or(false) {
+        return state.json(&rows);
+    }
+    // a short scan means there is nothing further in this direction
+    let next_start = match blocks.last() {
+        Some(last) if blocks.len() == limit as usize => {
+            let index = last.header.index;
+            if desc {
+                index.checked_sub(1)
+            } else {
+                (index < height).then_some(index + 1)
+            }
+        }
+        _ => None,
+    };
+    state.json(&serde_json::json!({ "blocks": rows, "next_start": next_start }))
+}
+
+async fn mempool(State(state): State<AppState>) -> Json<Vec<Transaction>> {
+    Json(state.mempool.lock().await.transactions())
+}
+
+/// Prometheus text exposition of the node's counters, timings and gauges.
+async fn metrics(State(state): State<AppState>) -> Response {
+    let height = state.chain.tip().map(|(height, _)| height).unwrap_or(0);
+    let mempool_size = state.mempool.lock().await.len();
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(height, mempool_size),
+    )
+        .into_response()
+}
+
+async fn mempool_count(State(state): State<AppState>) -> Json<Value> {
+    let count = state.mempool.lock().await.len();
+    Json(serde_json::json!({ "count": count }))
+}
+
+/// Drop every pending transaction.
+async fn clear_mempool(State(state): State<AppState>) -> Json<Value> {
+    let cleared = state.mempool.lock().await.take_all().len();
+    if let Err(e) = state.chain.store().replace_pending_txs(std::iter::empty()) {
+        warn!("failed to persist mempool: {e:#}");
+    }
+    info!("cleared {cleared} txs from the mempool");
+    Json(serde_json::json!({ "cleared": cleared }))
+}
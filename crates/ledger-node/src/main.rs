@@ -1,258 +1,415 @@
-use axum::{
-    extract::Query,
-    routing::{get, post},
-    Json, Router,
+use anyhow::Context;
+use clap::{parser::ValueSource, CommandFactory, FromArgMatches, Parser};
+use ledger_core::{
+    chain::{BlockReward, Chain, ChainStore, GenesisConfig},
+    constants::POW_TARGET_DIFFICULTY,
+    digest::HashAlgo,
+    mine::NonceShard,
+};
+#[cfg(unix)]
+use ledger_node::bind_unix;
+use ledger_node::{
+    log_subscriber,
+    mempool::{FullPolicy, Mempool, DEFAULT_MAX_MEMPOOL},
+    serve, shutdown_signal, AppState, LogFormat, DEFAULT_MAX_TARGET,
 };
-use clap::Parser;
-use ledger_core::{chain::Chain, Transaction};
 use ledger_storage::sled_store::SledStore;
-use serde::{Deserialize, Serialize};
-use std::{net::SocketAddr, sync::Arc};
-use tokio::sync::Mutex;
-use tower_http::trace::TraceLayer;
-use tracing::{info, Level};
-
-use ledger_core::constants::{BLOCKS_PER_BATCH, HASH_HEX_SIZE, MAX_BLOCKS_PER_REQUEST};
+use serde::{Deserialize, Deserializer};
+use std::{
+    ffi::OsString, fmt::Display, net::SocketAddr, path::PathBuf, process::ExitCode, str::FromStr,
+    sync::Arc, time::Duration,
+};
+use tracing::info;
+use tracing_subscriber::util::SubscriberInitExt;
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
 struct Args {
+    /// Read options from this TOML file, keyed like the flags in snake_case
+    /// (`data_dir = "./node-a"`); flags given on the command line win
+    #[arg(long)]
+    #[serde(skip)]
+    config: Option<PathBuf>,
+
     /// Address to listen on, e.g. 127.0.0.1:8080
     #[arg(long, default_value = "127.0.0.1:8080")]
     listen: String,
 
+    /// Write logs as human-readable `text` or one JSON object per line
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Listen on a Unix domain socket at this path instead of TCP; a stale
+    /// socket file from an earlier run is replaced
+    #[cfg(unix)]
+    #[arg(long, conflicts_with = "listen")]
+    bind_unix: Option<PathBuf>,
+
     /// Data directory for sled
     #[arg(long, default_value = "./data")]
     data_dir: String,
-}
 
-#[derive(Clone)]
-struct AppState {
-    chain: Chain<SledStore>,
-    // mempool: Arc<RwLock<Vec<Transaction>>>,
-    mempool: Arc<Mutex<Vec<Transaction>>>,
-}
+    /// How long to wait for `--data-dir` if another process has it open
+    /// before giving up, in milliseconds
+    #[arg(long, default_value_t = 500)]
+    lock_wait_ms: u64,
+
+    /// Omit `null` fields from explorer responses to reduce payload size
+    #[arg(long)]
+    compact_json: bool,
+
+    /// Maximum number of pending transactions held in the mempool
+    #[arg(long, default_value_t = DEFAULT_MAX_MEMPOOL)]
+    max_mempool: usize,
+
+    /// What to do with a new transaction when the mempool is full
+    #[arg(long, value_enum, default_value_t = FullPolicy::Reject)]
+    mempool_full_policy: FullPolicy,
+
+    /// Recompute each block's merkle root when it is read back from disk and
+    /// refuse to serve blocks whose transactions no longer match the header
+    #[arg(long)]
+    verify_merkle_on_read: bool,
+
+    /// JSON array of transactions to load into the mempool at startup
+    #[arg(long)]
+    seed_txs: Option<PathBuf>,
+
+    /// Give up on a `/mine` request after this many seconds (0 = never)
+    #[arg(long, default_value_t = 300)]
+    max_mine_secs: u64,
+
+    /// Largest encoded block, in bytes, `/mine` will seal; transactions that
+    /// don't fit wait for the next block (0 = no byte limit)
+    #[arg(long, default_value_t = 0)]
+    max_block_bytes: usize,
+
+    /// Highest `target` (leading zero bits) `/mine` will attempt; higher
+    /// requests are refused with 400
+    #[arg(long, default_value_t = DEFAULT_MAX_TARGET)]
+    max_target: u32,
+
+    /// Leading zero bits every block received on `POST /block` must meet,
+    /// including buffered orphans when they connect
+    #[arg(long, default_value_t = POW_TARGET_DIFFICULTY)]
+    block_target: u32,
+
+    /// Only mine mempool transactions whose sender can cover the amount;
+    /// the rest stay in the mempool
+    #[arg(long)]
+    enforce_balances: bool,
+
+    /// Account credited with a coinbase reward in every mined block; no
+    /// reward is paid when unset
+    #[arg(long)]
+    miner_address: Option<String>,
+
+    /// Coins issued to `--miner-address` per mined block
+    #[arg(long, default_value_t = 50)]
+    block_reward: u64,
+
+    /// Data sealed into the genesis block of a new chain
+    #[arg(long, default_value = "Genesis Block")]
+    genesis_data: String,
+
+    /// Pre-fund an account in the genesis block of a new chain, as
+    /// `account:amount`; repeat for more accounts
+    #[arg(long, value_parser = parse_alloc)]
+    #[serde(deserialize_with = "de_allocs")]
+    genesis_alloc: Vec<(String, u64)>,
+
+    /// Hash used for proof of work, block links and merkle roots: sha256, or
+    /// blake3 when built with the `blake3` feature. Recorded in the genesis
+    /// block; an existing chain refuses a different setting. Explorer hashes
+    /// and by-hash lookups use it too. Defaults to the one an existing chain
+    /// recorded, else blake3 when built with the feature and sha256 otherwise
+    #[arg(long)]
+    #[serde(deserialize_with = "de_parsed_some")]
+    hash_algo: Option<HashAlgo>,
+
+    /// Which slice of the nonce space `/mine` searches: only nonces `n` with
+    /// `n % shard_count == shard`. Give each cooperating node its own shard
+    #[arg(long, default_value_t = 0)]
+    shard: u64,
+
+    /// How many shards the nonce space is split into for `--shard`
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    shard_count: u64,
 
-#[derive(Serialize)]
-struct Health {
-    status: &'static str,
+    /// Rebuild the hash, transaction and work indexes from the stored
+    /// blocks before serving
+    #[arg(long)]
+    replay: bool,
+
+    /// Serve `POST /chain/prune?below=N`, which drops the transactions and
+    /// data of older blocks for good
+    #[arg(long)]
+    allow_prune: bool,
+
+    /// Let pages from this origin call the API, e.g.
+    /// `http://localhost:3000`; repeat for more, or `*` for any (local dev
+    /// only). Without it only same-origin pages can read responses
+    #[arg(long)]
+    cors_origin: Vec<String>,
+
+    /// Require `Authorization: Bearer <TOKEN>` on the endpoints that write
+    /// (`/tx`, `/tx/batch`, `/mine`, `/block`, `DELETE /mempool`,
+    /// `/chain/prune`); reads stay open
+    #[arg(long)]
+    write_token: Option<String>,
 }
 
-#[derive(Serialize)]
-struct Head {
-    height: u64,
+impl Default for Args {
+    fn default() -> Self {
+        Self::parse_from(["ledger-node"])
+    }
 }
 
-#[derive(Serialize)]
-struct Tip {
-    height: u64,
-    hash: Option<String>,
+impl Args {
+    /// Parse `argv` over the `--config` file, if one is named: a flag on the
+    /// command line beats the file, which beats the built-in default.
+    fn load_from<I, T>(argv: I) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let mut matches = Self::command().get_matches_from(argv);
+        let cli = Self::from_arg_matches(&matches)?;
+        let Some(path) = cli.config else {
+            return Ok(cli);
+        };
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read config {}", path.display()))?;
+        let mut args: Self =
+            toml::from_str(&text).with_context(|| format!("invalid config {}", path.display()))?;
+
+        // forget the defaults so only flags actually given overwrite the file
+        let defaulted: Vec<String> = matches
+            .ids()
+            .filter(|id| matches.value_source(id.as_str()) == Some(ValueSource::DefaultValue))
+            .map(|id| id.to_string())
+            .collect();
+        for id in defaulted {
+            matches.try_clear_id(&id)?;
+        }
+        args.update_from_arg_matches(&matches)?;
+        args.config = Some(path);
+        Ok(args)
+    }
 }
 
-#[derive(Deserialize)]
-struct TxIn {
-    from: String,
-    to: String,
-    amount: u64,
+/// Deserialize a config value through the type's `FromStr`, as its flag does.
+fn de_parsed<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(serde::de::Error::custom)
 }
 
-#[derive(Deserialize)]
-struct MineParams {
-    /// Leading zeros required in the hash, default is 20
-    target: Option<u32>,
-    data: Option<String>,
+/// [`de_parsed`] for a flag without a default value.
+fn de_parsed_some<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    de_parsed(deserializer).map(Some)
 }
-#[derive(Deserialize)]
-struct ListParams {
-    start: Option<u64>,
-    limit: Option<u32>,
-    dir: Option<String>,
+
+/// `genesis_alloc = ["alice:100", "bob:5"]`, in the `--genesis-alloc` format.
+fn de_allocs<'de, D>(deserializer: D) -> Result<Vec<(String, u64)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|s| parse_alloc(s).map_err(serde::de::Error::custom))
+        .collect()
 }
 
-#[derive(Serialize)]
-struct BlockRow {
-    index: u64,
-    ts: u64,
-    tx_count: usize,
-    hash: String,
-    nonce: u64,
-    previous_hash: String,
-    merkle_root: String,
-    data_hash: String,
-    data: String,
+fn parse_alloc(s: &str) -> Result<(String, u64), String> {
+    let (account, amount) = s
+        .rsplit_once(':')
+        .ok_or_else(|| format!("expected account:amount, got {s:?}"))?;
+    let amount = amount
+        .parse()
+        .map_err(|e| format!("invalid amount {amount:?}: {e}"))?;
+    Ok((account.to_string(), amount))
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt()
-        .with_max_level(Level::DEBUG)
-        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-        .init();
-
-    let args = Args::parse();
-    let store = Arc::new(SledStore::open(&args.data_dir)?);
-    let chain = Chain::new(store.clone());
-    chain.ensure_genesis()?;
-
-    let state = AppState {
-        chain,
-        mempool: Arc::new(Mutex::new(Vec::new())),
+async fn main() -> ExitCode {
+    let args = match Args::load_from(std::env::args_os()) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("ledger-node: {e:#}");
+            return ExitCode::FAILURE;
+        }
     };
+    log_subscriber(args.log_format, std::io::stdout).init();
 
-    let app = Router::new()
-        .route("/health", get(|| async { Json(Health { status: "ok" }) }))
-        .route("/healthz", get(|| async { Json(Health { status: "ok" }) }))
-        .route(
-            "/chain/head",
-            get({
-                let state = state.clone();
-                move || async move {
-                    let (height, _hash) = state.chain.tip().unwrap_or((0, None));
-                    Json(Head { height })
-                }
-            }),
-        )
-        .route(
-            "/chain/tip",
-            get({
-                let state = state.clone();
-                move || async move {
-                    let (height, hash) = state.chain.tip().unwrap_or((0, None));
-                    Json(Tip {
-                        height,
-                        hash: hash.map(hex::encode),
-                    })
-                }
-            }),
-        )
-        .route(
-            "/tx",
-            post({
-                let state = state.clone();
-                move |Json(tx): Json<TxIn>| {
-                    let _state = state.clone();
-                    async move {
-                        let tx = Transaction {
-                            from: tx.from,
-                            to: tx.to,
-                            amount: tx.amount,
-                            timestamp: std::time::SystemTime::now()
-                                .duration_since(std::time::UNIX_EPOCH)
-                                .unwrap()
-                                .as_secs(),
-                        };
-                        state.mempool.lock().await.push(tx);
-                        Json(serde_json::json!({ "accepted": true}))
-                    }
-                }
-            }),
-        )
-        .route(
-            "/mine",
-            get({
-                let state = state.clone();
-                move |Query(params): Query<MineParams>| {
-                    let mut state = state.clone();
-                    async move {
-                        let target_zeros = params.target.unwrap_or(20);
-                        let data = params.data;
-                        let txs = {
-                            let mut mp = state.mempool.lock().await;
-                            if mp.is_empty() {
-                                Vec::new()
-                            } else {
-                                std::mem::take(&mut *mp)
-                            }
-                        };
-                        info!(
-                            "/mine endpoint called - mining a new block with {} txs",
-                            txs.len()
-                        );
-
-                        match state.chain.mine_with_txs_parallel(txs, data, target_zeros) {
-                            Ok((block, hash)) => Json(serde_json::json!({
-                                "mined": true,
-                                "height": block.header.index,
-                                "nonce": block.header.nonce,
-                                "hash": hex::encode(hash),
-                                "previous_hash": hex::encode(block.header.previous_hash),
-                                "merkle_root": hex::encode(block.header.merkle_root),
-                                "data_hash": hex::encode(block.header.data_hash),
-                                "tx_count": block.txs.len(),
-                                "target": target_zeros,
-                                "data": block.data.clone().unwrap_or_else(|| "No Data".to_string()),
-                            })),
-                            Err(e) => Json(serde_json::json!({
-                                "mined": false,
-                                "error": e.to_string(),
-                            })),
-                        }
-                    }
-                }
-            }),
-        )
-        .route(
-            "/chain/blocks",
-            get({
-                let state = state.clone();
-                move |Query(p): Query<ListParams>| {
-                    let state = state.clone();
-                    async move {
-                        let (height, _) = state.chain.tip().unwrap_or((0, None));
-                        let limit = p
-                            .limit
-                            .unwrap_or(BLOCKS_PER_BATCH)
-                            .min(MAX_BLOCKS_PER_REQUEST);
-                        let desc = p.dir.as_deref() != Some("asc");
-                        let start = p.start.unwrap_or(height);
-
-                        // call through to storage impl
-                        let blocks = state
-                            .chain
-                            .store() // Arc<SledStore>
-                            .list_blocks_range(start, limit, desc)
-                            .unwrap_or_default();
-
-                        let rows: Vec<BlockRow> = blocks
-                            .into_iter()
-                            .map(|b| BlockRow {
-                                index: b.header.index,
-                                ts: b.header.timestamp,
-                                tx_count: b.txs.len(),
-                                hash: hex::encode(b.hash()),
-                                nonce: b.header.nonce,
-                                previous_hash: hex::encode(b.header.previous_hash),
-                                merkle_root: hex::encode(b.header.merkle_root),
-                                data_hash: if b.data.is_some() {
-                                    hex::encode(b.header.data_hash)
-                                } else {
-                                    "0".repeat(HASH_HEX_SIZE)
-                                },
-                                data: b.data.clone().unwrap_or_else(|| "No Data".to_string()),
-                            })
-                            .collect();
-
-                        Json(rows)
-                    }
-                }
-            }),
-        )
-        .route(
-            "/mempool",
-            get({
-                let state = state.clone();
-                move || {
-                    let state = state.clone();
-                    async move {
-                        let mp = state.mempool.lock().await;
-                        Json(mp.clone())
-                    }
-                }
-            }),
+    // report startup failures such as a locked data dir as one line, not a Debug dump
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("ledger-node: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// `--hash-algo` if given, else the algorithm an existing chain in `store`
+/// recorded in its genesis, else the build's default.
+fn chain_hash_algo(store: &SledStore, flag: Option<HashAlgo>) -> anyhow::Result<HashAlgo> {
+    if let Some(algo) = flag {
+        return Ok(algo);
+    }
+    match store.get_block(0)? {
+        Some(genesis) => HashAlgo::of_genesis(&genesis).map_err(anyhow::Error::msg),
+        None => Ok(HashAlgo::default()),
+    }
+}
+
+async fn run(args: Args) -> anyhow::Result<()> {
+    let lock_wait = Duration::from_millis(args.lock_wait_ms);
+    let store = SledStore::open_with_lock_timeout(&args.data_dir, lock_wait)?
+        .with_verify_merkle_on_read(args.verify_merkle_on_read);
+    let hash_algo = chain_hash_algo(&store, args.hash_algo)?;
+    let store = Arc::new(store.with_hash_algo(hash_algo));
+    if args.replay {
+        let replayed = store.rebuild_indexes()?;
+        info!("replay done: {replayed} blocks");
+    }
+    let shard = NonceShard::new(args.shard, args.shard_count).with_context(|| {
+        format!(
+            "--shard {} must be below --shard-count {}",
+            args.shard, args.shard_count
         )
-        .layer(TraceLayer::new_for_http());
+    })?;
+    let mut chain = Chain::new(store.clone())
+        .with_hash_algo(hash_algo)
+        .with_nonce_shard(shard);
+    if let Some(miner) = args.miner_address.clone() {
+        chain = chain.with_reward(BlockReward {
+            miner,
+            amount: args.block_reward,
+        });
+    }
+    chain.ensure_genesis_with(&GenesisConfig {
+        data: args.genesis_data.clone(),
+        allocations: args.genesis_alloc.clone(),
+    })?;
+
+    let mut mempool = Mempool::with_capacity(args.max_mempool, args.mempool_full_policy);
+    if let Some(path) = &args.seed_txs {
+        let admitted = mempool.seed_from_file(path)?;
+        info!("seeded mempool with {admitted} txs from {}", path.display());
+    }
+    let mut state = AppState::new(chain).with_mempool(mempool);
+    let restored = state.restore_mempool().await?;
+    if restored > 0 {
+        info!("restored {restored} pending txs from the store");
+    }
+    state.compact_json = args.compact_json;
+    state.max_mine = (args.max_mine_secs > 0).then(|| Duration::from_secs(args.max_mine_secs));
+    state.max_block_bytes = (args.max_block_bytes > 0).then_some(args.max_block_bytes);
+    state.max_target = args.max_target;
+    state.block_target = args.block_target;
+    state.enforce_balances = args.enforce_balances;
+    state.allow_prune = args.allow_prune;
+    state.cors_origins = args.cors_origin.clone();
+    state.write_token = args.write_token.clone();
+
+    #[cfg(unix)]
+    if let Some(path) = &args.bind_unix {
+        let listener = bind_unix(path)?;
+        info!("ledger-node listening on unix:{}", path.display());
+        let served = serve(listener, state, shutdown_signal()).await;
+        let _ = std::fs::remove_file(path);
+        return served;
+    }
 
     let addr: SocketAddr = args.listen.parse()?;
     info!("ledger-node listening on http://{addr}");
-    axum::serve(tokio::net::TcpListener::bind(addr).await?, app).await?;
-    Ok(())
+    serve(
+        tokio::net::TcpListener::bind(addr).await?,
+        state,
+        shutdown_signal(),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_line_overrides_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("node.toml");
+        std::fs::write(
+            &path,
+            r#"
+listen = "0.0.0.0:9000"
+data_dir = "/var/lib/ledger"
+max_target = 24
+block_reward = 7
+enforce_balances = true
+mempool_full_policy = "evict-oldest"
+genesis_alloc = ["alice:100"]
+hash_algo = "sha256"
+"#,
+        )
+        .unwrap();
+        let config = path.to_str().unwrap();
+
+        let args = Args::load_from(["ledger-node", "--config", config]).unwrap();
+        assert_eq!(args.listen, "0.0.0.0:9000");
+        assert_eq!(args.data_dir, "/var/lib/ledger");
+        assert_eq!(args.max_target, 24);
+        assert_eq!(args.block_reward, 7);
+        assert!(args.enforce_balances);
+        assert_eq!(args.mempool_full_policy, FullPolicy::EvictOldest);
+        assert_eq!(args.genesis_alloc, vec![("alice".to_string(), 100)]);
+        assert_eq!(args.hash_algo, Some(HashAlgo::Sha256));
+        // keys the file leaves out keep their flag defaults
+        assert_eq!(args.max_mine_secs, 300);
+
+        let args =
+            Args::load_from(["ledger-node", "--config", config, "--max-target", "12"]).unwrap();
+        assert_eq!(args.max_target, 12);
+        assert_eq!(args.data_dir, "/var/lib/ledger");
+    }
+
+    #[test]
+    fn existing_chain_keeps_its_hash_algo() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(SledStore::open(dir.path()).unwrap());
+        assert_eq!(chain_hash_algo(&store, None).unwrap(), HashAlgo::default());
+
+        // an untagged genesis is a SHA-256 chain, whatever the build defaults to
+        Chain::new(store.clone())
+            .with_hash_algo(HashAlgo::Sha256)
+            .ensure_genesis()
+            .unwrap();
+        assert_eq!(chain_hash_algo(&store, None).unwrap(), HashAlgo::Sha256);
+        // the flag still wins, so a mismatch is reported by ensure_genesis
+        #[cfg(feature = "blake3")]
+        assert_eq!(
+            chain_hash_algo(&store, Some(HashAlgo::Blake3)).unwrap(),
+            HashAlgo::Blake3
+        );
+    }
+
+    #[test]
+    fn config_file_rejects_unknown_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("node.toml");
+        std::fs::write(&path, "data-dir = \"./typo\"\n").unwrap();
+        let err = Args::load_from(["ledger-node", "--config", path.to_str().unwrap()]).unwrap_err();
+        assert!(format!("{err:#}").contains("data-dir"), "{err:#}");
+    }
 }
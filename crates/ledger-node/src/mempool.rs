@@ -0,0 +1,336 @@
+//! The node's pending-transaction pool.
+//!
+//! Every admitted transaction is tagged with a sequence number taken while
+//! the pool's lock is held, so admission order is well-defined even when
+//! several `/tx` requests race.
+
+use anyhow::Context;
+use clap::ValueEnum;
+use ledger_core::Transaction;
+use serde_json::Value;
+use std::path::Path;
+use tracing::warn;
+
+use crate::tx_input::TxIn;
+
+/// Default cap on the number of pending transactions.
+pub const DEFAULT_MAX_MEMPOOL: usize = 10_000;
+
+/// What to do with an incoming transaction when the mempool is at capacity.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FullPolicy {
+    /// Refuse the incoming transaction.
+    #[default]
+    Reject,
+    /// Drop the lowest-fee resident if the incoming transaction pays strictly more.
+    EvictLowestFee,
+    /// Drop the longest-waiting resident.
+    EvictOldest,
+}
+
+/// A transaction waiting in the mempool, tagged with its admission sequence.
+#[derive(Clone, Debug)]
+pub struct PendingTx {
+    pub seq: u64,
+    pub tx: Transaction,
+}
+
+impl PendingTx {
+    /// Fee offered by this transaction.
+    pub fn fee(&self) -> u64 {
+        self.tx.fee
+    }
+}
+
+/// Successful admission: the assigned sequence and any resident that was evicted to make room.
+#[derive(Clone, Debug)]
+pub struct Admitted {
+    pub seq: u64,
+    pub evicted: Option<PendingTx>,
+}
+
+/// Why a transaction was not admitted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// At capacity and the full policy refused to make room.
+    Full,
+    /// An identical transaction is already queued.
+    Duplicate,
+}
+
+impl Rejection {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Rejection::Full => "mempool full",
+            Rejection::Duplicate => "duplicate",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Mempool {
+    pending: Vec<PendingTx>,
+    next_seq: u64,
+    capacity: usize,
+    policy: FullPolicy,
+}
+
+impl Default for Mempool {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_MAX_MEMPOOL, FullPolicy::default())
+    }
+}
+
+impl Mempool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize, policy: FullPolicy) -> Self {
+        Self {
+            pending: Vec::new(),
+            next_seq: 0,
+            capacity,
+            policy,
+        }
+    }
+
+    /// Queue a transaction and return its admission sequence number.
+    /// Sequences are monotonically increasing and never reused; a rejected
+    /// transaction does not consume one. A transaction equal to one already
+    /// queued is refused as a duplicate.
+    pub fn admit(&mut self, tx: Transaction) -> Result<Admitted, Rejection> {
+        if self.pending.iter().any(|p| p.tx == tx) {
+            return Err(Rejection::Duplicate);
+        }
+        let incoming = PendingTx {
+            seq: self.next_seq,
+            tx,
+        };
+        let evicted = if self.pending.len() >= self.capacity {
+            Some(self.make_room(&incoming)?)
+        } else {
+            None
+        };
+        self.next_seq += 1;
+        let seq = incoming.seq;
+        self.pending.push(incoming);
+        Ok(Admitted { seq, evicted })
+    }
+
+    /// Evict one resident according to the policy, or refuse.
+    fn make_room(&mut self, incoming: &PendingTx) -> Result<PendingTx, Rejection> {
+        let victim = match self.policy {
+            FullPolicy::Reject => None,
+            // `pending` is kept in admission order, so the oldest is first.
+            FullPolicy::EvictOldest => (!self.pending.is_empty()).then_some(0),
+            FullPolicy::EvictLowestFee => self
+                .pending
+                .iter()
+                .enumerate()
+                // lowest fee first; among equal fees evict the newest resident
+                .min_by_key(|(_, p)| (p.fee(), std::cmp::Reverse(p.seq)))
+                .filter(|(_, p)| p.fee() < incoming.fee())
+                .map(|(i, _)| i),
+        };
+        victim
+            .map(|i| self.pending.remove(i))
+            .ok_or(Rejection::Full)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Pending transactions in admission order.
+    pub fn pending(&self) -> &[PendingTx] {
+        &self.pending
+    }
+
+    /// Clone the queued transactions, in admission order.
+    pub fn transactions(&self) -> Vec<Transaction> {
+        self.pending.iter().map(|p| p.tx.clone()).collect()
+    }
+
+    /// Preload the pool from a JSON array of `{"from", "to", "amount"}`
+    /// objects (`--seed-txs`). Entries are checked with the same rules as
+    /// `/tx`; invalid ones, and any the full policy refuses, are logged and
+    /// skipped. Returns how many were admitted.
+    pub fn seed_from_file(&mut self, path: &Path) -> anyhow::Result<usize> {
+        let raw = std::fs::read(path)
+            .with_context(|| format!("failed to read seed file {}", path.display()))?;
+        let entries: Vec<Value> = serde_json::from_slice(&raw)
+            .with_context(|| format!("seed file {} is not a JSON array", path.display()))?;
+        let mut admitted = 0;
+        for (i, entry) in entries.into_iter().enumerate() {
+            let tx = match TxIn::from_value(entry) {
+                Ok(tx) => tx.into_transaction(),
+                Err(e) => {
+                    warn!("skipping seed tx #{i}: {}: {}", e.field, e.message);
+                    continue;
+                }
+            };
+            match self.admit(tx) {
+                Ok(_) => admitted += 1,
+                Err(rejection) => warn!("skipping seed tx #{i}: {}", rejection.reason()),
+            }
+        }
+        Ok(admitted)
+    }
+
+    /// Remove and return up to `limit` transactions, highest fee first and
+    /// in admission order among equal fees. The rest stay queued. Hand any
+    /// that end up unused back with [`Mempool::restore`].
+    pub fn take_by_fee(&mut self, limit: usize) -> Vec<PendingTx> {
+        let mut chosen = std::mem::take(&mut self.pending);
+        // the sort is stable, so equal fees keep admission order
+        chosen.sort_by_key(|p| std::cmp::Reverse(p.fee()));
+        self.pending = chosen.split_off(limit.min(chosen.len()));
+        self.pending.sort_by_key(|p| p.seq);
+        chosen
+    }
+
+    /// Put back transactions taken with [`Mempool::take_by_fee`], keeping
+    /// their original sequences. They held their place before, so capacity
+    /// and the full policy don't apply; one queued again in the meantime is
+    /// already back and is skipped.
+    pub fn restore(&mut self, txs: Vec<PendingTx>) {
+        for p in txs {
+            if !self.pending.iter().any(|q| q.tx == p.tx) {
+                self.pending.push(p);
+            }
+        }
+        self.pending.sort_by_key(|p| p.seq);
+    }
+
+    /// Remove the queued transactions equal to any in `txs`, e.g. those a
+    /// block from another node already sealed. Returns how many were removed.
+    pub fn remove_included(&mut self, txs: &[Transaction]) -> usize {
+        let before = self.pending.len();
+        self.pending.retain(|p| !txs.contains(&p.tx));
+        before - self.pending.len()
+    }
+
+    /// Drain every pending transaction, in admission order.
+    pub fn take_all(&mut self) -> Vec<Transaction> {
+        std::mem::take(&mut self.pending)
+            .into_iter()
+            .map(|p| p.tx)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicates_are_refused() {
+        let mut mp = Mempool::new();
+        mp.admit(tx(1)).unwrap();
+        assert_eq!(mp.admit(tx(1)).unwrap_err(), Rejection::Duplicate);
+        assert_eq!(mp.len(), 1);
+        // once mined, the same transaction may be queued again
+        mp.take_all();
+        assert_eq!(mp.admit(tx(1)).unwrap().seq, 1);
+    }
+
+    fn tx(amount: u64) -> Transaction {
+        Transaction {
+            from: "alice".into(),
+            to: "bob".into(),
+            amount,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        }
+    }
+
+    #[test]
+    fn sequences_survive_draining() {
+        let mut mp = Mempool::new();
+        assert_eq!(mp.admit(tx(1)).unwrap().seq, 0);
+        assert_eq!(mp.admit(tx(2)).unwrap().seq, 1);
+        assert_eq!(mp.take_all().len(), 2);
+        assert!(mp.is_empty());
+        // sequences are never reused after a mine drains the pool
+        assert_eq!(mp.admit(tx(3)).unwrap().seq, 2);
+    }
+
+    fn full_pool(policy: FullPolicy) -> Mempool {
+        let mut mp = Mempool::with_capacity(2, policy);
+        mp.admit(tx(1)).unwrap();
+        mp.admit(tx(2)).unwrap();
+        mp
+    }
+
+    #[test]
+    fn reject_policy_at_capacity() {
+        let mut mp = full_pool(FullPolicy::Reject);
+        assert_eq!(mp.admit(tx(3)).unwrap_err(), Rejection::Full);
+        assert_eq!(mp.len(), 2);
+        // the rejected tx did not burn a sequence number
+        mp.take_all();
+        assert_eq!(mp.admit(tx(4)).unwrap().seq, 2);
+    }
+
+    #[test]
+    fn evict_oldest_policy_at_capacity() {
+        let mut mp = full_pool(FullPolicy::EvictOldest);
+        let admitted = mp.admit(tx(3)).unwrap();
+        assert_eq!(admitted.seq, 2);
+        assert_eq!(admitted.evicted.unwrap().tx.amount, 1);
+        let amounts: Vec<u64> = mp.pending().iter().map(|p| p.tx.amount).collect();
+        assert_eq!(amounts, vec![2, 3]);
+    }
+
+    #[test]
+    fn evict_lowest_fee_policy_at_capacity() {
+        // a zero-fee tx ties with the residents, so nothing is displaced
+        let mut mp = full_pool(FullPolicy::EvictLowestFee);
+        assert_eq!(mp.admit(tx(3)).unwrap_err(), Rejection::Full);
+        assert_eq!(mp.len(), 2);
+
+        let admitted = mp.admit(with_fee(4, 1)).unwrap();
+        assert_eq!(admitted.evicted.unwrap().tx.amount, 2);
+    }
+
+    fn with_fee(amount: u64, fee: u64) -> Transaction {
+        Transaction { fee, ..tx(amount) }
+    }
+
+    #[test]
+    fn take_by_fee_prefers_higher_fees() {
+        let mut mp = Mempool::new();
+        for (amount, fee) in [(1, 5), (2, 1), (3, 10)] {
+            mp.admit(with_fee(amount, fee)).unwrap();
+        }
+        let fees: Vec<u64> = mp.take_by_fee(2).iter().map(|p| p.fee()).collect();
+        assert_eq!(fees, vec![10, 5]);
+        assert_eq!(mp.len(), 1);
+        assert_eq!(mp.pending()[0].fee(), 1);
+    }
+
+    #[test]
+    fn restore_keeps_sequences_and_ignores_capacity() {
+        let mut mp = full_pool(FullPolicy::Reject);
+        let taken = mp.take_by_fee(2);
+        mp.admit(tx(3)).unwrap();
+        mp.admit(tx(1)).unwrap();
+        mp.restore(taken);
+        // over capacity, in sequence order, and tx(1) only once
+        let seqs: Vec<(u64, u64)> = mp.pending().iter().map(|p| (p.seq, p.tx.amount)).collect();
+        assert_eq!(seqs, vec![(1, 2), (2, 3), (3, 1)]);
+        assert_eq!(mp.admit(tx(4)).unwrap_err(), Rejection::Full);
+    }
+}
@@ -0,0 +1,70 @@
+//! Prometheus metrics for `/metrics`.
+//!
+//! Each [`NodeMetrics`] owns its own recorder rather than installing a global
+//! one, so several nodes (or tests) in one process keep separate counts.
+
+use metrics::{counter, gauge, histogram, with_local_recorder};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusRecorder};
+use std::{sync::Arc, time::Duration};
+
+/// Upper bounds, in seconds, of the `mining_duration_seconds` buckets.
+const MINING_BUCKETS: &[f64] = &[0.01, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0];
+
+/// The node's counters, gauges and histograms. Clones share one recorder.
+#[derive(Clone)]
+pub struct NodeMetrics {
+    recorder: Arc<PrometheusRecorder>,
+}
+
+impl Default for NodeMetrics {
+    fn default() -> Self {
+        let recorder = PrometheusBuilder::new()
+            .set_buckets(MINING_BUCKETS)
+            .expect("mining buckets are not empty")
+            .build_recorder();
+        Self {
+            recorder: Arc::new(recorder),
+        }
+    }
+}
+
+impl NodeMetrics {
+    /// A fresh recorder with every metric at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn record(&self, f: impl FnOnce()) {
+        with_local_recorder(self.recorder.as_ref(), f);
+    }
+
+    /// Count `n` transactions admitted to the mempool.
+    pub fn tx_accepted(&self, n: u64) {
+        self.record(|| counter!("txs_accepted_total").increment(n));
+    }
+
+    /// Count a mined block and record how long the search took.
+    pub fn block_mined(&self, took: Duration) {
+        self.record(|| {
+            counter!("blocks_mined_total").increment(1);
+            histogram!("mining_duration_seconds").record(took.as_secs_f64());
+        });
+    }
+
+    /// Count a mining attempt that found no block, and its duration.
+    pub fn mining_failed(&self, took: Duration) {
+        self.record(|| {
+            counter!("mining_failures_total").increment(1);
+            histogram!("mining_duration_seconds").record(took.as_secs_f64());
+        });
+    }
+
+    /// Prometheus text exposition, with the gauges set to the given values.
+    pub fn render(&self, tip_height: u64, mempool_size: usize) -> String {
+        self.record(|| {
+            gauge!("tip_height").set(tip_height as f64);
+            gauge!("mempool_size").set(mempool_size as f64);
+        });
+        self.recorder.handle().render()
+    }
+}
@@ -0,0 +1,182 @@
+//! Lenient parsing and field-level validation of `/tx` request bodies.
+//!
+//! Bodies are first deserialized into [`RawTxIn`], where every field is an
+//! optional `serde_json::Value`, and then checked one field at a time so the
+//! client is told exactly which field is wrong and why.
+
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use ledger_core::{Transaction, TxError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A single field-level rejection, rendered as
+/// `{"error": {"field": "...", "message": "..."}}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl IntoResponse for FieldError {
+    fn into_response(self) -> axum::response::Response {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": self })),
+        )
+            .into_response()
+    }
+}
+
+impl From<TxError> for FieldError {
+    fn from(e: TxError) -> Self {
+        FieldError::new(e.field(), e.to_string())
+    }
+}
+
+/// A validated transaction submission.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxIn {
+    pub from: String,
+    pub to: String,
+    pub amount: u64,
+    /// Optional in the body; defaults to 0.
+    pub fee: u64,
+}
+
+/// Lenient intermediate: accepts any JSON object, whatever its field types.
+#[derive(Deserialize)]
+struct RawTxIn {
+    from: Option<Value>,
+    to: Option<Value>,
+    amount: Option<Value>,
+    fee: Option<Value>,
+}
+
+impl TxIn {
+    /// Parse a raw request body, reporting the first offending field.
+    pub fn parse(body: &[u8]) -> Result<Self, FieldError> {
+        let value: Value = serde_json::from_slice(body)
+            .map_err(|e| FieldError::new("body", format!("invalid JSON: {e}")))?;
+        Self::from_value(value)
+    }
+
+    /// Parse a `/tx/batch` body: a JSON array whose elements are validated
+    /// independently, so one bad entry doesn't sink the rest.
+    pub fn parse_batch(body: &[u8]) -> Result<Vec<Result<Self, FieldError>>, FieldError> {
+        let value: Value = serde_json::from_slice(body)
+            .map_err(|e| FieldError::new("body", format!("invalid JSON: {e}")))?;
+        let Value::Array(items) = value else {
+            return Err(FieldError::new(
+                "body",
+                "expected a JSON array of transactions",
+            ));
+        };
+        Ok(items.into_iter().map(Self::from_value).collect())
+    }
+
+    /// Validate an already-decoded JSON value, e.g. one element of a seed file.
+    pub fn from_value(value: Value) -> Result<Self, FieldError> {
+        if !value.is_object() {
+            return Err(FieldError::new(
+                "body",
+                format!("expected a JSON object, got {}", type_name(&value)),
+            ));
+        }
+        let raw = RawTxIn::deserialize(value)
+            .map_err(|e| FieldError::new("body", format!("expected a JSON object: {e}")))?;
+        raw.validate()
+    }
+
+    /// Turn the submission into a transaction stamped with the current time.
+    pub fn into_transaction(self) -> Transaction {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        self.into_transaction_at(now)
+    }
+
+    /// Turn the submission into a transaction stamped `timestamp`.
+    pub fn into_transaction_at(self, timestamp: u64) -> Transaction {
+        Transaction {
+            from: self.from,
+            to: self.to,
+            amount: self.amount,
+            fee: self.fee,
+            timestamp,
+            signature: None,
+            pubkey: None,
+        }
+    }
+}
+
+impl RawTxIn {
+    fn validate(self) -> Result<TxIn, FieldError> {
+        Ok(TxIn {
+            from: string_field("from", self.from)?,
+            to: string_field("to", self.to)?,
+            amount: u64_field("amount", self.amount)?,
+            fee: match self.fee {
+                None | Some(Value::Null) => 0,
+                fee => u64_field("fee", fee)?,
+            },
+        })
+    }
+}
+
+fn string_field(field: &str, value: Option<Value>) -> Result<String, FieldError> {
+    match value {
+        None | Some(Value::Null) => Err(FieldError::new(field, "missing field")),
+        Some(Value::String(s)) => Ok(s),
+        Some(other) => Err(FieldError::new(
+            field,
+            format!("must be a string, got {}", type_name(&other)),
+        )),
+    }
+}
+
+fn u64_field(field: &str, value: Option<Value>) -> Result<u64, FieldError> {
+    match value {
+        None | Some(Value::Null) => Err(FieldError::new(field, "missing field")),
+        Some(Value::Number(n)) => {
+            if let Some(v) = n.as_u64() {
+                Ok(v)
+            } else if n.as_i64().is_some_and(|v| v < 0) || n.as_f64().is_some_and(|v| v < 0.0) {
+                Err(FieldError::new(field, "must not be negative"))
+            } else {
+                Err(FieldError::new(
+                    field,
+                    format!("must be an unsigned 64-bit integer, got {n}"),
+                ))
+            }
+        }
+        Some(other) => Err(FieldError::new(
+            field,
+            format!(
+                "must be an unsigned 64-bit integer, got {}",
+                type_name(&other)
+            ),
+        )),
+    }
+}
+
+fn type_name(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
@@ -0,0 +1,1615 @@
+pub mod helpers;
+
+use axum::http::StatusCode;
+use ledger_core::{
+    chain::{BlockReward, ChainStore},
+    constants::MAX_TXS_PER_BLOCK,
+    mine::{mine_block_parallel, mine_block_parallel_at},
+    Block, BlockHeader, Transaction,
+};
+use ledger_node::{
+    log_subscriber,
+    mempool::{FullPolicy, Mempool},
+    router, serve, AppState, LogFormat,
+};
+use std::sync::Arc;
+
+use crate::helpers::{delete, get, open_chain, post, post_json, preflight, send, test_state};
+
+async fn assert_field_error(body: &str, field: &str) -> String {
+    let (_dir, state) = test_state();
+    let app = router(state);
+    let (status, json) = send(&app, post_json("/tx", body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "body: {body}");
+    assert_eq!(json["error"]["field"], field, "body: {body}");
+    json["error"]["message"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_submit_tx_accepted() {
+    let (_dir, state) = test_state();
+    let app = router(state.clone());
+    let (status, json) = send(
+        &app,
+        post_json("/tx", r#"{"from":"alice","to":"bob","amount":10}"#),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["accepted"], true);
+    assert_eq!(state.mempool.lock().await.len(), 1);
+
+    let (_, mempool) = send(&app, get("/mempool")).await;
+    assert_eq!(mempool[0]["from"], "alice");
+}
+
+#[tokio::test]
+async fn test_submit_tx_returns_hash() {
+    let (_dir, state) = test_state();
+    let app = router(state.clone());
+    let (_, json) = send(
+        &app,
+        post_json("/tx", r#"{"from":"alice","to":"bob","amount":10}"#),
+    )
+    .await;
+    let hash = json["tx_hash"].as_str().unwrap().to_string();
+
+    let pending = state.mempool.lock().await.transactions();
+    assert_eq!(hash, hex::encode(ledger_core::tx_hash(&pending[0])));
+    assert_eq!(json["timestamp"], pending[0].timestamp);
+
+    // the same hash finds the transaction once it is mined
+    send(&app, get("/mine?target=1")).await;
+    let (status, found) = send(&app, get(&format!("/tx/{hash}"))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(found["block"], 1);
+}
+
+#[tokio::test]
+async fn test_orphan_connects_when_parent_arrives() {
+    let (_dir, mut state) = test_state();
+    state.block_target = 1;
+    let app = router(state.clone());
+    let genesis = state.chain.store().get_block(0).unwrap().unwrap();
+    let ts = genesis.header.timestamp;
+    let (parent, parent_hash) =
+        mine_block_parallel_at(1, genesis.hash(), vec![], Some("a".into()), 1, ts + 1).unwrap();
+    let (child, _) =
+        mine_block_parallel_at(2, parent_hash, vec![], Some("b".into()), 1, ts + 2).unwrap();
+
+    let body = serde_json::to_string(&child).unwrap();
+    let (status, json) = send(&app, post_json("/block", &body)).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(json["status"], "orphan");
+    assert_eq!(state.chain.height().unwrap(), 0);
+
+    let body = serde_json::to_string(&parent).unwrap();
+    let (status, json) = send(&app, post_json("/block", &body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["status"], "extended");
+    assert_eq!(json["connected"], serde_json::json!([2]));
+    assert_eq!(state.chain.height().unwrap(), 2);
+    assert!(state.orphan_pool.lock().await.is_empty());
+
+    let (_, json) = send(&app, post_json("/block", &body)).await;
+    assert_eq!(json["status"], "duplicate");
+}
+
+/// A block on `parent` whose hash has no leading zero bits at all.
+fn zero_work_block(index: u64, parent: ledger_core::Hash, txs: Vec<Transaction>) -> Block {
+    let (mut block, _) = mine_block_parallel(index, parent, txs, None, 0).unwrap();
+    while ledger_core::pow::count_leading_zero_bits(&block.hash()) != 0 {
+        block.header.nonce += 1;
+    }
+    block
+}
+
+#[tokio::test]
+async fn test_submitted_blocks_meet_the_node_target() {
+    let (_dir, mut state) = test_state();
+    state.block_target = 4;
+    let app = router(state.clone());
+    let (_, genesis_hash) = state.chain.tip().unwrap();
+    let genesis_hash = genesis_hash.unwrap();
+
+    // the submitter can't lower the bar, not even with the old query parameter
+    let weak = serde_json::to_string(&zero_work_block(1, genesis_hash, vec![])).unwrap();
+    let (status, _) = send(&app, post_json("/block?target=0", &weak)).await;
+    assert!(status.is_client_error(), "{status}");
+    assert_eq!(state.chain.height().unwrap(), 0);
+
+    // nor park a weak block as an orphan to connect later
+    let (_, parent_hash) = mine_block_parallel(1, genesis_hash, vec![], None, 4).unwrap();
+    let child = serde_json::to_string(&zero_work_block(2, parent_hash, vec![])).unwrap();
+    let (status, _) = send(&app, post_json("/block", &child)).await;
+    assert!(status.is_client_error(), "{status}");
+    assert!(state.orphan_pool.lock().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_submitted_block_clears_its_txs_from_the_mempool() {
+    let (_dir, mut state) = test_state();
+    state.block_target = 1;
+    let app = router(state.clone());
+    for amount in [1, 2] {
+        let body = format!(r#"{{"from":"alice","to":"bob","amount":{amount}}}"#);
+        send(&app, post_json("/tx", &body)).await;
+    }
+    let queued = state.mempool.lock().await.transactions();
+
+    // another node seals the first tx and we receive its block
+    let genesis = state.chain.store().get_block(0).unwrap().unwrap();
+    let ts = genesis.header.timestamp + 1;
+    let (block, _) =
+        mine_block_parallel_at(1, genesis.hash(), vec![queued[0].clone()], None, 1, ts).unwrap();
+    let (status, _) = send(
+        &app,
+        post_json("/block", &serde_json::to_string(&block).unwrap()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        state.mempool.lock().await.transactions(),
+        vec![queued[1].clone()]
+    );
+    let persisted = state.chain.store().pending_txs().unwrap();
+    assert_eq!(persisted.len(), 1);
+
+    // so the next local block only seals what is left
+    send(&app, get("/mine?target=1")).await;
+    assert_eq!(block_txs(&app, 2).await, vec![queued[1].clone()]);
+}
+
+/// A block received while `/mine` searches must not cost the mempool its
+/// transactions: each one ends up either in a stored block or back in the
+/// pool, and a block `/mine` reports is one the chain holds.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_block_received_while_mining_keeps_txs() {
+    let (_dir, mut state) = test_state();
+    state.block_target = 1;
+    let app = router(state.clone());
+    for round in 1..=5u64 {
+        let body = format!(r#"{{"from":"alice","to":"bob","amount":{round}}}"#);
+        let (_, json) = send(&app, post_json("/tx", &body)).await;
+        let tx_hash = json["tx_hash"].as_str().unwrap().to_string();
+
+        let tip = state.chain.store().get_block(state.chain.height().unwrap());
+        let tip = tip.unwrap().unwrap();
+        let (rival, _) = mine_block_parallel_at(
+            tip.header.index + 1,
+            tip.hash(),
+            vec![],
+            Some(format!("rival {round}")),
+            1,
+            tip.header.timestamp + 1,
+        )
+        .unwrap();
+        let miner = app.clone();
+        let mining = tokio::spawn(async move { send(&miner, get("/mine?target=16")).await });
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let rival = serde_json::to_string(&rival).unwrap();
+        send(&app, post_json("/block", &rival)).await;
+        let (status, mined) = mining.await.unwrap();
+
+        if mined["mined"] == true {
+            let height = mined["height"].as_u64().unwrap();
+            let stored = state.chain.store().get_block(height).unwrap().unwrap();
+            assert_eq!(mined["hash"], hex::encode(stored.hash()), "round {round}");
+        } else {
+            assert_eq!(status, StatusCode::CONFLICT, "{mined}");
+            assert_eq!(mined["error_code"], "stale_parent");
+        }
+        let (status, _) = send(&app, get(&format!("/tx/{tx_hash}"))).await;
+        let in_block = status == StatusCode::OK;
+        let queued = state.mempool.lock().await.len() == 1;
+        assert!(
+            in_block != queued,
+            "round {round}: in block {in_block}, queued {queued}"
+        );
+        // start the next round with an empty pool
+        if queued {
+            send(&app, get("/mine?target=1")).await;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_simultaneous_txs_seal_in_a_stable_order() {
+    let (_dir, state) = test_state();
+    let app = router(state.clone());
+    let handles: Vec<_> = (1..=3)
+        .map(|amount| {
+            let app = app.clone();
+            tokio::spawn(async move {
+                let body = format!(r#"{{"from":"alice","to":"bob","amount":{amount}}}"#);
+                send(&app, post_json("/tx", &body)).await
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.await.unwrap().0, StatusCode::OK);
+    }
+    let queued = state.mempool.lock().await.transactions();
+    send(&app, get("/mine?target=1")).await;
+
+    // whatever order they were queued in, the block holds them by
+    // (timestamp, tx_hash)
+    let mut expected = queued;
+    expected.sort_by_key(|tx| (tx.timestamp, ledger_core::tx_hash(tx)));
+    let sealed = block_txs(&app, 1).await;
+    assert_eq!(
+        sealed.iter().map(ledger_core::tx_hash).collect::<Vec<_>>(),
+        expected
+            .iter()
+            .map(ledger_core::tx_hash)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn test_mempool_survives_restart() {
+    let (_dir, state) = test_state();
+    let app = router(state.clone());
+    // the first tx is sealed by a mine, so only the later two stay pending
+    for amount in 1..=3 {
+        let body = format!(r#"{{"from":"alice","to":"bob","amount":{amount}}}"#);
+        send(&app, post_json("/tx", &body)).await;
+        if amount == 1 {
+            send(&app, get("/mine?target=1")).await;
+        }
+    }
+
+    // a fresh AppState over the same store, as after a restart
+    let restarted = AppState::new(state.chain.clone());
+    assert_eq!(restarted.restore_mempool().await.unwrap(), 2);
+    let (_, pending) = send(&router(restarted), get("/mempool")).await;
+    let amounts: Vec<u64> = pending
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tx| tx["amount"].as_u64().unwrap())
+        .collect();
+    assert_eq!(amounts, vec![2, 3]);
+}
+
+#[tokio::test]
+async fn test_mine_forgets_persisted_txs() {
+    let (_dir, state) = test_state();
+    let app = router(state.clone());
+    send(
+        &app,
+        post_json("/tx", r#"{"from":"alice","to":"bob","amount":1}"#),
+    )
+    .await;
+    assert_eq!(state.chain.store().pending_txs().unwrap().len(), 1);
+    send(&app, get("/mine?target=1")).await;
+    assert!(state.chain.store().pending_txs().unwrap().is_empty());
+
+    send(
+        &app,
+        post_json("/tx", r#"{"from":"alice","to":"bob","amount":2}"#),
+    )
+    .await;
+    send(&app, delete("/mempool")).await;
+    assert!(state.chain.store().pending_txs().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_submit_tx_missing_from() {
+    let msg = assert_field_error(r#"{"to":"bob","amount":10}"#, "from").await;
+    assert!(msg.contains("missing"));
+}
+
+#[tokio::test]
+async fn test_submit_tx_missing_to() {
+    let msg = assert_field_error(r#"{"from":"alice","amount":10}"#, "to").await;
+    assert!(msg.contains("missing"));
+}
+
+#[tokio::test]
+async fn test_submit_tx_negative_amount() {
+    let msg = assert_field_error(r#"{"from":"alice","to":"bob","amount":-5}"#, "amount").await;
+    assert!(msg.contains("negative"));
+}
+
+#[tokio::test]
+async fn test_submit_tx_non_integer_amount() {
+    assert_field_error(r#"{"from":"alice","to":"bob","amount":1.5}"#, "amount").await;
+    assert_field_error(r#"{"from":"alice","to":"bob","amount":"10"}"#, "amount").await;
+    assert_field_error(r#"{"from":"alice","to":"bob"}"#, "amount").await;
+}
+
+#[tokio::test]
+async fn test_submit_tx_rejected_by_validate() {
+    let msg = assert_field_error(r#"{"from":"alice","to":"alice","amount":1}"#, "to").await;
+    assert!(msg.contains("differ"), "{msg}");
+    let msg = assert_field_error(r#"{"from":"alice","to":"bob","amount":0}"#, "amount").await;
+    assert!(msg.contains("greater than zero"), "{msg}");
+    assert_field_error(r#"{"from":"alice","to":"","amount":1}"#, "to").await;
+}
+
+#[tokio::test]
+async fn test_submit_tx_wrong_field_type() {
+    let msg = assert_field_error(r#"{"from":42,"to":"bob","amount":1}"#, "from").await;
+    assert!(msg.contains("string"));
+}
+
+#[tokio::test]
+async fn test_submit_tx_malformed_body() {
+    assert_field_error("not json", "body").await;
+    assert_field_error(r#"[1,2,3]"#, "body").await;
+}
+
+#[tokio::test]
+async fn test_blocks_omit_absent_data() {
+    let (_dir, state) = test_state();
+    let app = router(state);
+    let (status, mined) = send(&app, get("/mine?target=1")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(mined["mined"], true);
+    assert!(mined.get("data").is_none());
+    assert!(mined.get("error").is_none());
+
+    let (_, rows) = send(&app, get("/chain/blocks")).await;
+    let rows = rows.as_array().unwrap();
+    assert_eq!(rows.len(), 2);
+    // block 1 has no data: no sentinel, no zeroed data_hash
+    assert_eq!(rows[0]["index"], 1);
+    assert!(rows[0].get("data").is_none());
+    assert!(rows[0].get("data_hash").is_none());
+    // genesis carries data, so both fields are present
+    assert_eq!(rows[1]["data"], "Genesis Block");
+    assert!(rows[1]["data_hash"].is_string());
+}
+
+#[tokio::test]
+async fn test_compact_json_strips_nulls() {
+    let (_dir, mut state) = test_state();
+    let app = router(state.clone());
+    let (_, tip) = send(&app, get("/chain/tip")).await;
+    assert!(tip["hash"].is_string());
+
+    state.chain.store().clear().unwrap();
+    let (_, tip) = send(&app, get("/chain/tip")).await;
+    assert!(tip.get("hash").unwrap().is_null());
+
+    state.compact_json = true;
+    let app = router(state);
+    let (_, tip) = send(&app, get("/chain/tip")).await;
+    assert!(tip.get("hash").is_none());
+    assert_eq!(tip["height"], 0);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_admission_sequences_are_contiguous() {
+    const TASKS: u64 = 8;
+    const PER_TASK: u64 = 25;
+    let (_dir, state) = test_state();
+    let app = router(state.clone());
+
+    let mut handles = Vec::new();
+    for t in 0..TASKS {
+        let app = app.clone();
+        handles.push(tokio::spawn(async move {
+            let mut seqs = Vec::new();
+            for i in 0..PER_TASK {
+                let body = format!(r#"{{"from":"user{t}","to":"bob","amount":{}}}"#, i + 1);
+                let (status, json) = send(&app, post_json("/tx", &body)).await;
+                assert_eq!(status, StatusCode::OK);
+                seqs.push(json["seq"].as_u64().unwrap());
+            }
+            seqs
+        }));
+    }
+
+    let mut all = Vec::new();
+    for h in handles {
+        let seqs = h.await.unwrap();
+        // each task observes its own submissions in increasing order
+        assert!(seqs.windows(2).all(|w| w[0] < w[1]));
+        all.extend(seqs);
+    }
+    all.sort_unstable();
+    assert_eq!(all, (0..TASKS * PER_TASK).collect::<Vec<_>>());
+
+    let mp = state.mempool.lock().await;
+    assert_eq!(mp.len() as u64, TASKS * PER_TASK);
+    let pending_seqs: Vec<u64> = mp.pending().iter().map(|p| p.seq).collect();
+    assert_eq!(pending_seqs, (0..TASKS * PER_TASK).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_chain_genesis() {
+    let (_dir, state) = test_state();
+    let app = router(state.clone());
+    let (status, genesis) = send(&app, get("/chain/genesis")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(genesis["index"], 0);
+    assert_eq!(genesis["data"], "Genesis Block");
+    assert!(genesis["difficulty"].as_u64().unwrap() >= 20);
+
+    // identical to the first row of an ascending listing
+    let (_, rows) = send(&app, get("/chain/blocks?dir=asc&start=0&limit=1")).await;
+    assert_eq!(rows[0]["hash"], genesis["hash"]);
+
+    state.chain.store().clear().unwrap();
+    let (status, body) = send(&app, get("/chain/genesis")).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(body["error"].is_string());
+}
+
+#[tokio::test]
+async fn test_chain_block_detail() {
+    let (_dir, state) = test_state();
+    let app = router(state);
+    send(
+        &app,
+        post_json("/tx", r#"{"from":"alice","to":"bob","amount":3}"#),
+    )
+    .await;
+    send(&app, get("/mine?target=1")).await;
+
+    let (status, block) = send(&app, get("/chain/block/1")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(block["index"], 1);
+    assert_eq!(block["confirmations"], 1);
+    assert_eq!(block["txs"][0]["amount"], 3);
+
+    let (_, genesis) = send(&app, get("/chain/block/0")).await;
+    assert_eq!(genesis["confirmations"], 2);
+
+    let (status, _) = send(&app, get("/chain/block/9")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_mempool_full_policies() {
+    let tx = |amount: u64| format!(r#"{{"from":"alice","to":"bob","amount":{amount}}}"#);
+
+    let (_dir, state) = test_state();
+    let state = state.with_mempool(Mempool::with_capacity(2, FullPolicy::Reject));
+    let app = router(state.clone());
+    send(&app, post_json("/tx", &tx(1))).await;
+    send(&app, post_json("/tx", &tx(2))).await;
+    let (status, json) = send(&app, post_json("/tx", &tx(3))).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(json["accepted"], false);
+    assert_eq!(json["reason"], "mempool full");
+    assert_eq!(state.mempool.lock().await.len(), 2);
+
+    let (_dir, state) = test_state();
+    let state = state.with_mempool(Mempool::with_capacity(2, FullPolicy::EvictOldest));
+    let app = router(state.clone());
+    send(&app, post_json("/tx", &tx(1))).await;
+    send(&app, post_json("/tx", &tx(2))).await;
+    let (status, json) = send(&app, post_json("/tx", &tx(3))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["evicted_seq"], 0);
+    let (_, mempool) = send(&app, get("/mempool")).await;
+    assert_eq!(mempool[0]["amount"], 2);
+    assert_eq!(mempool[1]["amount"], 3);
+}
+
+#[tokio::test]
+async fn test_chain_status() {
+    let (_dir, state) = test_state();
+    let app = router(state);
+    send(
+        &app,
+        post_json("/tx", r#"{"from":"alice","to":"bob","amount":1}"#),
+    )
+    .await;
+    let (status, json) = send(&app, get("/chain/status")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["height"], 0);
+    assert_eq!(json["tip_hash"].as_str().unwrap().len(), 64);
+    assert_eq!(json["mempool_size"], 1);
+    // genesis was just mined, so it cannot be old
+    assert!(json["last_block_age_secs"].as_u64().unwrap() < 60);
+    assert!(json["uptime_secs"].as_u64().unwrap() < 60);
+
+    send(&app, get("/mine?target=1")).await;
+    let (_, json) = send(&app, get("/chain/status")).await;
+    assert_eq!(json["height"], 1);
+    assert_eq!(json["mempool_size"], 0);
+}
+
+#[tokio::test]
+async fn test_chain_stats() {
+    let (_dir, state) = test_state();
+    let app = router(state);
+    let (status, json) = send(&app, get("/chain/stats")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["total_blocks"], 1);
+    assert!(json["avg_block_time_secs"].is_null());
+    assert!(json["estimated_hashrate"].is_null());
+
+    // genesis holds 0 txs, then blocks of 1 and 3
+    for n in [1, 3] {
+        for amount in 1..=n {
+            let body = format!(r#"{{"from":"alice","to":"bob","amount":{amount}}}"#);
+            send(&app, post_json("/tx", &body)).await;
+        }
+        send(&app, get("/mine?target=2")).await;
+    }
+    let (_, json) = send(&app, get("/chain/stats")).await;
+    assert_eq!(json["total_blocks"], 3);
+    assert_eq!(json["total_txs"], 4);
+    assert!((json["avg_txs_per_block"].as_f64().unwrap() - 4.0 / 3.0).abs() < 1e-9);
+    assert!(json["avg_block_time_secs"].as_f64().unwrap() >= 0.0);
+    assert!(json["tip_target"].as_u64().unwrap() >= 2);
+    let recent = json["recent_difficulty"].as_array().unwrap();
+    assert_eq!(recent.len(), 3);
+    assert_eq!(recent[2], json["tip_target"]);
+    // scored with the chain's own block hash, as `/chain/status` reports it
+    let (_, status) = send(&app, get("/chain/status")).await;
+    let tip_hash: ledger_core::Hash = hex::decode(status["tip_hash"].as_str().unwrap())
+        .unwrap()
+        .try_into()
+        .unwrap();
+    assert_eq!(
+        json["tip_target"],
+        ledger_core::pow::count_leading_zero_bits(&tip_hash)
+    );
+    // blocks mined within one second leave no time to divide by
+    let rate = &json["estimated_hashrate"];
+    assert!(rate.is_null() || rate.as_f64().unwrap() > 0.0, "{rate}");
+}
+
+#[tokio::test]
+async fn test_tx_histogram() {
+    let (_dir, state) = test_state();
+    let app = router(state.clone());
+    // genesis holds 0 txs, then blocks of 2 and 4
+    for n in [2, 4] {
+        for amount in 1..=n {
+            let body = format!(r#"{{"from":"alice","to":"bob","amount":{amount}}}"#);
+            send(&app, post_json("/tx", &body)).await;
+        }
+        send(&app, get("/mine?target=1")).await;
+    }
+
+    let (status, json) = send(&app, get("/chain/tx-histogram?buckets=3")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["blocks"], 3);
+    assert_eq!(json["total_txs"], 6);
+    assert_eq!(json["min"], 0);
+    assert_eq!(json["max"], 4);
+    assert_eq!(json["mean"], 2.0);
+    let counts: Vec<u64> = json["buckets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|b| b["count"].as_u64().unwrap())
+        .collect();
+    assert_eq!(counts, vec![1, 1, 1]);
+    assert!(state.tx_histogram_cache.lock().await.is_some());
+
+    // a new block invalidates the cached result
+    send(&app, get("/mine?target=1")).await;
+    let (_, json) = send(&app, get("/chain/tx-histogram?buckets=3")).await;
+    assert_eq!(json["blocks"], 4);
+}
+
+#[tokio::test]
+async fn test_seed_txs_preload_mempool() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("seed.json");
+    std::fs::write(
+        &path,
+        r#"[
+            {"from": "alice", "to": "bob", "amount": 5},
+            {"from": "bob", "to": "carol", "amount": 2},
+            {"from": "carol", "amount": 1},
+            {"from": "carol", "to": "alice", "amount": -3}
+        ]"#,
+    )
+    .unwrap();
+
+    let mut mempool = Mempool::new();
+    // the two invalid entries are skipped rather than failing the load
+    assert_eq!(mempool.seed_from_file(&path).unwrap(), 2);
+
+    let (_dir, state) = test_state();
+    let app = router(state.with_mempool(mempool));
+    let (_, json) = send(&app, get("/mempool")).await;
+    assert_eq!(json.as_array().unwrap().len(), 2);
+    assert_eq!(json[1]["to"], "carol");
+}
+
+#[tokio::test]
+async fn test_chain_block_by_hash() {
+    let (_dir, state) = test_state();
+    let app = router(state);
+    let (_, mined) = send(&app, get("/mine?target=1")).await;
+    let hash = mined["hash"].as_str().unwrap();
+
+    let (status, block) = send(&app, get(&format!("/chain/block/by-hash/{hash}"))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(block["index"], 1);
+    assert_eq!(block["hash"], hash);
+    assert_eq!(block["confirmations"], 1);
+
+    let missing = "ab".repeat(32);
+    let (status, _) = send(&app, get(&format!("/chain/block/by-hash/{missing}"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(&app, get("/chain/block/by-hash/xyz")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[cfg(feature = "blake3")]
+#[tokio::test]
+async fn test_explorer_hashes_follow_the_chain_algo() {
+    use ledger_core::{chain::Chain, digest::HashAlgo};
+    use ledger_storage::sled_store::SledStore;
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = SledStore::open(temp_dir.path())
+        .unwrap()
+        .with_hash_algo(HashAlgo::Blake3);
+    let chain = Chain::new(Arc::new(store)).with_hash_algo(HashAlgo::Blake3);
+    chain.ensure_genesis().unwrap();
+    let app = router(AppState::new(chain.clone()));
+
+    let (_, mined) = send(&app, get("/mine?target=4")).await;
+    let hash = mined["hash"].as_str().unwrap();
+    let block = chain.store().get_block(1).unwrap().unwrap();
+    assert_eq!(
+        hash,
+        hex::encode(HashAlgo::Blake3.header_hash(block.header))
+    );
+
+    let (_, tip) = send(&app, get("/chain/tip")).await;
+    assert_eq!(tip["hash"], hash);
+    let (_, status) = send(&app, get("/chain/status")).await;
+    assert_eq!(status["tip_hash"], hash);
+    let (_, rows) = send(&app, get("/chain/blocks")).await;
+    assert_eq!(rows[0]["hash"], hash);
+    assert!(rows[0]["difficulty"].as_u64().unwrap() >= 4);
+
+    let (status, found) = send(&app, get(&format!("/chain/block/by-hash/{hash}"))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(found["index"], 1);
+    // the store's SHA-256 key is not this chain's block hash
+    let sha = hex::encode(block.hash());
+    let (status, _) = send(&app, get(&format!("/chain/block/by-hash/{sha}"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_get_tx_by_hash() {
+    let (_dir, state) = test_state();
+    let app = router(state);
+    send(
+        &app,
+        post_json("/tx", r#"{"from":"alice","to":"bob","amount":7}"#),
+    )
+    .await;
+    let (_, mined) = send(&app, get("/mine?target=1")).await;
+    assert_eq!(mined["mined"], true);
+
+    let (_, block) = send(&app, get("/chain/block/1")).await;
+    let tx: ledger_core::Transaction = serde_json::from_value(block["txs"][0].clone()).unwrap();
+    let hash = hex::encode(ledger_core::tx_hash(&tx));
+    let (status, json) = send(&app, get(&format!("/tx/{hash}"))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["block"], 1);
+    assert_eq!(json["tx_hash"], hash);
+    assert_eq!(json["tx"]["amount"], 7);
+
+    let (status, _) = send(&app, get(&format!("/tx/{}", "ab".repeat(32)))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(&app, get("/tx/xyz")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_mine_times_out() {
+    let (_dir, mut state) = test_state();
+    state.max_mine = Some(std::time::Duration::from_millis(100));
+    state.max_target = 200;
+    let app = router(state);
+    send(
+        &app,
+        post_json("/tx", r#"{"from":"alice","to":"bob","amount":1}"#),
+    )
+    .await;
+
+    let (status, json) = send(&app, get("/mine?target=200")).await;
+    assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(json["mined"], false);
+    assert_eq!(json["error_code"], "mining_timeout");
+
+    // the drained transaction went back into the pool and no block was added
+    let (_, mempool) = send(&app, get("/mempool")).await;
+    assert_eq!(mempool.as_array().unwrap().len(), 1);
+    let (_, head) = send(&app, get("/chain/head")).await;
+    assert_eq!(head["height"], 0);
+}
+
+#[tokio::test]
+async fn test_mine_rejects_target_above_max() {
+    let (_dir, state) = test_state();
+    let app = router(state);
+    let (status, json) = send(&app, get("/mine?target=29")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"], "target too high");
+    assert_eq!(json["max"], 28);
+    let (_, head) = send(&app, get("/chain/head")).await;
+    assert_eq!(head["height"], 0);
+}
+
+#[tokio::test]
+async fn test_mine_accepts_target_at_max() {
+    let (_dir, mut state) = test_state();
+    state.max_target = 4;
+    let app = router(state);
+    let (status, json) = send(&app, get("/mine?target=4")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["mined"], true);
+    let (status, json) = send(&app, get("/mine?target=5")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["max"], 4);
+}
+
+#[tokio::test]
+async fn test_resubmitted_tx_gets_its_own_stamp() {
+    let (_dir, state) = test_state();
+    let app = router(state.clone());
+    // every submission is stamped uniquely, so an identical body sent twice
+    // in the same second is two transactions rather than a duplicate
+    let body = r#"{"from":"alice","to":"bob","amount":1}"#;
+    let (status, first) = send(&app, post_json("/tx", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, second) = send(&app, post_json("/tx", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(second["timestamp"].as_u64() > first["timestamp"].as_u64());
+    assert_ne!(first["tx_hash"], second["tx_hash"]);
+    assert_eq!(state.mempool.lock().await.len(), 2);
+}
+
+#[tokio::test]
+async fn test_chain_subscribe_streams_mined_blocks() {
+    use tokio_stream::StreamExt;
+    use tower::ServiceExt;
+
+    let (_dir, state) = test_state();
+    let app = router(state);
+    let res = app.clone().oneshot(get("/chain/subscribe")).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["content-type"], "text/event-stream");
+    let mut body = res.into_body().into_data_stream();
+
+    let (_, mined) = send(&app, get("/mine?target=1")).await;
+
+    let event = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        let mut buf = String::new();
+        while !buf.contains("\n\n") {
+            let chunk = body.next().await.expect("stream ended").unwrap();
+            buf.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+        buf
+    })
+    .await
+    .expect("no block event within 5s");
+
+    let json = event
+        .lines()
+        .find_map(|l| l.strip_prefix("data: "))
+        .expect("event has a data line");
+    let row: serde_json::Value = serde_json::from_str(json).unwrap();
+    assert_eq!(row["index"], 1);
+    assert_eq!(row["hash"], mined["hash"]);
+}
+
+#[tokio::test]
+async fn test_chain_block_present_absent_genesis() {
+    let (_dir, state) = test_state();
+    let app = router(state);
+
+    let (status, genesis) = send(&app, get("/chain/block/0")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(genesis["index"], 0);
+    assert_eq!(genesis["data"], "Genesis Block");
+    assert_eq!(genesis["txs"], serde_json::json!([]));
+
+    for amount in [4, 5] {
+        let body = format!(r#"{{"from":"alice","to":"carol","amount":{amount}}}"#);
+        send(&app, post_json("/tx", &body)).await;
+    }
+    send(&app, get("/mine?target=1")).await;
+    let (status, block) = send(&app, get("/chain/block/1")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(block["previous_hash"], genesis["hash"]);
+    assert_eq!(block["tx_count"], 2);
+    let mut amounts: Vec<u64> = block["txs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tx| tx["amount"].as_u64().unwrap())
+        .collect();
+    // txs submitted in the same second are ordered by hash
+    amounts.sort();
+    assert_eq!(amounts, vec![4, 5]);
+
+    let (status, json) = send(&app, get("/chain/block/2")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["error"], "block 2 not found");
+}
+
+#[tokio::test]
+async fn test_balance() {
+    let (_dir, state) = test_state();
+    let app = router(state);
+    for body in [
+        r#"{"from":"alice","to":"bob","amount":30}"#,
+        r#"{"from":"bob","to":"carol","amount":10}"#,
+    ] {
+        send(&app, post_json("/tx", body)).await;
+    }
+    send(&app, get("/mine?target=1")).await;
+
+    let (status, json) = send(&app, get("/balance/bob")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["account"], "bob");
+    assert_eq!(json["balance"], 20);
+    let (_, json) = send(&app, get("/balance/alice")).await;
+    assert_eq!(json["balance"], -30);
+    let (_, json) = send(&app, get("/balance/nobody")).await;
+    assert_eq!(json["balance"], 0);
+}
+
+/// The transactions of stored block `index`, in block order.
+async fn block_txs(app: &axum::Router, index: u64) -> Vec<Transaction> {
+    let (_, block) = send(app, get(&format!("/chain/block/{index}"))).await;
+    serde_json::from_value(block["txs"].clone()).unwrap()
+}
+
+#[tokio::test]
+async fn test_mine_searches_only_its_nonce_shard() {
+    let (_dir, mut state) = test_state();
+    let shard = ledger_core::mine::NonceShard::new(2, 3).unwrap();
+    state.chain = state.chain.clone().with_nonce_shard(shard);
+    let app = router(state.clone());
+    for _ in 0..3 {
+        let (status, _) = send(&app, get("/mine?target=4")).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    for index in 1..=3 {
+        let block = state.chain.store().get_block(index).unwrap().unwrap();
+        assert_eq!(block.header.nonce % 3, 2, "block {index}");
+    }
+}
+
+#[tokio::test]
+async fn test_mine_orders_by_time_not_fee() {
+    let (_dir, state) = test_state();
+    let app = router(state);
+    for (amount, fee) in [(1, 5), (2, 1), (3, 10)] {
+        let body = format!(r#"{{"from":"alice","to":"bob","amount":{amount},"fee":{fee}}}"#);
+        let (status, _) = send(&app, post_json("/tx", &body)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    send(&app, get("/mine?target=1")).await;
+
+    // fees only decide what gets in when the block is full
+    let txs = block_txs(&app, 1).await;
+    let mut sorted = txs.clone();
+    ledger_core::sort_block_txs(&mut sorted);
+    assert_eq!(
+        txs.iter().map(ledger_core::tx_hash).collect::<Vec<_>>(),
+        sorted.iter().map(ledger_core::tx_hash).collect::<Vec<_>>()
+    );
+    let mut fees: Vec<u64> = txs.iter().map(|tx| tx.fee).collect();
+    fees.sort();
+    assert_eq!(fees, vec![1, 5, 10]);
+
+    let msg = assert_field_error(r#"{"from":"alice","to":"bob","amount":1,"fee":-1}"#, "fee").await;
+    assert!(msg.contains("negative"));
+}
+
+#[tokio::test]
+async fn test_mine_caps_block_size() {
+    let (_dir, state) = test_state();
+    let app = router(state.clone());
+    {
+        let mut mempool = state.mempool.lock().await;
+        for amount in 1..=(MAX_TXS_PER_BLOCK + 10) as u64 {
+            mempool
+                .admit(Transaction {
+                    from: "alice".into(),
+                    to: "bob".into(),
+                    amount,
+                    timestamp: 1_600_000_000,
+                    fee: 0,
+                    signature: None,
+                    pubkey: None,
+                })
+                .unwrap();
+        }
+    }
+
+    let (_, first) = send(&app, get("/mine?target=1")).await;
+    assert_eq!(first["tx_count"], MAX_TXS_PER_BLOCK);
+    assert_eq!(state.mempool.lock().await.len(), 10);
+    let (_, second) = send(&app, get("/mine?target=1")).await;
+    assert_eq!(second["tx_count"], 10);
+    assert!(state.mempool.lock().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_mine_packs_by_block_bytes() {
+    let (_dir, mut state) = test_state();
+    let tx = |to: String, amount| Transaction {
+        from: "alice".into(),
+        to,
+        amount,
+        timestamp: 1_600_000_000,
+        fee: 0,
+        signature: None,
+        pubkey: None,
+    };
+    let small = |amount| tx("bob".into(), amount);
+    let big = tx("b".repeat(1024), 3);
+    let empty = Block {
+        header: BlockHeader::new(1, [0; 32], [0; 32], [0; 32], 0),
+        data: None,
+        txs: vec![],
+    };
+    // room for two small txs and the big one, less a byte
+    let cap = empty.size_bytes() + 2 * small(1).size_bytes() + big.size_bytes() - 1;
+    state.max_block_bytes = Some(cap);
+    {
+        let mut mempool = state.mempool.lock().await;
+        for tx in [small(1), small(2), big, small(4)] {
+            mempool.admit(tx).unwrap();
+        }
+    }
+    let app = router(state.clone());
+
+    let (_, mined) = send(&app, get("/mine?target=1")).await;
+    assert_eq!(mined["tx_count"], 3);
+    let (_, block) = send(&app, get("/chain/block/1")).await;
+    let mut amounts: Vec<u64> = (0..3)
+        .map(|i| block["txs"][i]["amount"].as_u64().unwrap())
+        .collect();
+    amounts.sort();
+    assert_eq!(amounts, vec![1, 2, 4]);
+    // the big tx is skipped, not the end of packing, and waits for a block of its own
+    let left: Vec<u64> = state
+        .mempool
+        .lock()
+        .await
+        .pending()
+        .iter()
+        .map(|p| p.tx.amount)
+        .collect();
+    assert_eq!(left, vec![3]);
+    let (_, mined) = send(&app, get("/mine?target=1")).await;
+    assert_eq!(mined["tx_count"], 1);
+}
+
+#[tokio::test]
+async fn test_tx_too_large_for_any_block_is_refused() {
+    let (_dir, mut state) = test_state();
+    let empty = Block {
+        header: BlockHeader::new(1, [0; 32], [0; 32], [0; 32], 0),
+        data: None,
+        txs: vec![],
+    };
+    let bob = Transaction {
+        from: "alice".into(),
+        to: "bob".into(),
+        amount: 2,
+        timestamp: 1_600_000_000,
+        fee: 0,
+        signature: None,
+        pubkey: None,
+    };
+    // room for a tx to "bob" but not to a longer name
+    state.max_block_bytes = Some(empty.size_bytes() + bob.size_bytes());
+    let app = router(state.clone());
+    let body = format!(r#"{{"from":"alice","to":"{}","amount":1}}"#, "b".repeat(8));
+
+    let (status, json) = send(&app, post_json("/tx", &body)).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(json["reason"], "too_large");
+    let batch = format!(r#"[{body},{{"from":"alice","to":"bob","amount":2}}]"#);
+    let (_, json) = send(&app, post_json("/tx/batch", &batch)).await;
+    assert_eq!(json["accepted"], 1);
+    assert_eq!(json["rejected"], serde_json::json!([0]));
+    assert_eq!(state.mempool.lock().await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_mine_pays_coinbase_reward() {
+    let (_dir, mut state) = test_state();
+    state.chain = state.chain.with_reward(BlockReward {
+        miner: "miner".into(),
+        amount: 50,
+    });
+    let app = router(state);
+    send(
+        &app,
+        post_json("/tx", r#"{"from":"alice","to":"bob","amount":5}"#),
+    )
+    .await;
+    let (_, mined) = send(&app, get("/mine?target=1")).await;
+    assert_eq!(mined["tx_count"], 2);
+
+    let (_, block) = send(&app, get("/chain/block/1")).await;
+    assert_eq!(block["txs"][0]["from"], "");
+    assert_eq!(block["txs"][0]["to"], "miner");
+    assert_eq!(block["txs"][0]["amount"], 50);
+    assert_eq!(block["txs"][1]["from"], "alice");
+
+    send(&app, get("/mine?target=1")).await;
+    let (_, json) = send(&app, get("/balance/miner")).await;
+    assert_eq!(json["balance"], 100);
+}
+
+#[tokio::test]
+async fn test_enforce_balances_excludes_overdraft() {
+    let (_dir, mut state) = test_state();
+    state.enforce_balances = true;
+    // fund alice with one block's reward, mined outside the node
+    let mut funding = state.chain.clone().with_reward(BlockReward {
+        miner: "alice".into(),
+        amount: 50,
+    });
+    funding.mine_with_txs_parallel(vec![], None, 1).unwrap();
+    let app = router(state.clone());
+    for body in [
+        r#"{"from":"alice","to":"bob","amount":30}"#,
+        r#"{"from":"alice","to":"carol","amount":30}"#,
+    ] {
+        send(&app, post_json("/tx", body)).await;
+    }
+    let (_, mined) = send(&app, get("/mine?target=1")).await;
+    assert_eq!(mined["mined"], true);
+    assert_eq!(mined["tx_count"], 1);
+
+    let (_, block) = send(&app, get("/chain/block/2")).await;
+    let recipients: Vec<_> = block["txs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tx| tx["to"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(recipients, ["bob"]);
+
+    let pending = state.mempool.lock().await.transactions();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].to, "carol");
+}
+
+#[tokio::test]
+async fn test_submit_tx_batch() {
+    let (_dir, state) = test_state();
+    let state = state.with_mempool(Mempool::with_capacity(3, FullPolicy::Reject));
+    let app = router(state.clone());
+    let body = r#"[
+        {"from":"alice","to":"bob","amount":1},
+        {"from":"alice","to":"bob","amount":-2},
+        {"from":"alice","to":"bob","amount":3},
+        {"from":"alice","to":"bob","amount":4},
+        {"from":"alice","to":"bob","amount":5}
+    ]"#;
+    let (status, json) = send(&app, post_json("/tx/batch", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    // index 1 is malformed and index 4 no longer fits
+    assert_eq!(json["accepted"], 3);
+    assert_eq!(json["rejected"], serde_json::json!([1, 4]));
+    assert_eq!(state.mempool.lock().await.len(), 3);
+
+    let (status, json) = send(&app, post_json("/tx/batch", r#"{"from":"a"}"#)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"]["field"], "body");
+}
+
+#[tokio::test]
+async fn test_coinbase_cannot_be_submitted() {
+    let (_dir, state) = test_state();
+    let app = router(state.clone());
+    let body = r#"{"from":"","to":"mallory","amount":1000000}"#;
+    let (status, json) = send(&app, post_json("/tx", body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["accepted"], false);
+    assert_eq!(json["reason"], "coinbase");
+
+    let batch = format!(r#"[{{"from":"alice","to":"bob","amount":1}}, {body}]"#);
+    let (status, json) = send(&app, post_json("/tx/batch", &batch)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["accepted"], 1);
+    assert_eq!(json["rejected"], serde_json::json!([1]));
+
+    assert_eq!(state.mempool.lock().await.len(), 1);
+    send(&app, get("/mine?target=1")).await;
+    let (_, json) = send(&app, get("/balance/mallory")).await;
+    assert_eq!(json["balance"], 0);
+}
+
+#[tokio::test]
+async fn test_chain_export() {
+    let (_dir, state) = test_state();
+    let app = router(state);
+    send(&app, get("/mine?target=1&data=one")).await;
+    send(&app, get("/mine?target=1&data=two")).await;
+
+    let (status, body) = send(&app, get("/chain/export")).await;
+    assert_eq!(status, StatusCode::OK);
+    let blocks: Vec<ledger_core::Block> = body
+        .as_str()
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let indices: Vec<u64> = blocks.iter().map(|b| b.header.index).collect();
+    assert_eq!(indices, [0, 1, 2]);
+    assert_eq!(blocks[2].data.as_deref(), Some("two"));
+
+    // a single line is itself valid JSON, so `send` decodes it as the block
+    let (_, body) = send(&app, get("/chain/export?from=1&to=1")).await;
+    assert_eq!(body["header"]["index"], 1);
+}
+
+#[tokio::test]
+async fn test_mempool_count_and_clear() {
+    let (_dir, state) = test_state();
+    let app = router(state);
+    for amount in 1..=3 {
+        let body = format!(r#"{{"from":"alice","to":"bob","amount":{amount}}}"#);
+        send(&app, post_json("/tx", &body)).await;
+    }
+    let (status, json) = send(&app, get("/mempool/count")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["count"], 3);
+
+    let (status, json) = send(&app, delete("/mempool")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["cleared"], 3);
+
+    let (_, json) = send(&app, get("/mempool/count")).await;
+    assert_eq!(json["count"], 0);
+}
+
+#[tokio::test]
+async fn test_list_blocks_paged() {
+    let (_dir, state) = test_state();
+    let mut chain = state.chain.clone();
+    for _ in 1..60 {
+        chain.mine_with_txs_parallel(vec![], None, 1).unwrap();
+    }
+    let app = router(state);
+
+    for (dir, first, expected) in [("desc", 59, vec![59, 34, 9]), ("asc", 0, vec![0, 25, 50])] {
+        let mut start = Some(first);
+        let mut starts = Vec::new();
+        let mut seen = Vec::new();
+        while let Some(s) = start {
+            starts.push(s);
+            let uri = format!("/chain/blocks?paged=true&limit=25&dir={dir}&start={s}");
+            let (status, page) = send(&app, get(&uri)).await;
+            assert_eq!(status, StatusCode::OK);
+            seen.extend(
+                page["blocks"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|b| b["index"].as_u64().unwrap()),
+            );
+            start = page["next_start"].as_u64();
+        }
+        assert_eq!(starts, expected, "dir={dir}");
+        assert_eq!(seen.len(), 60, "dir={dir}");
+    }
+
+    // unpaged requests still get the bare array
+    let (_, rows) = send(&app, get("/chain/blocks?limit=5")).await;
+    assert_eq!(rows.as_array().unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn test_list_blocks_default_start() {
+    let (_dir, state) = test_state();
+    let mut chain = state.chain.clone();
+    for _ in 0..3 {
+        chain.mine_with_txs_parallel(vec![], None, 1).unwrap();
+    }
+    let app = router(state);
+    let indices = |rows: serde_json::Value| -> Vec<u64> {
+        rows.as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["index"].as_u64().unwrap())
+            .collect()
+    };
+
+    let (_, rows) = send(&app, get("/chain/blocks?dir=asc")).await;
+    assert_eq!(indices(rows), vec![0, 1, 2, 3]);
+    let (_, rows) = send(&app, get("/chain/blocks?dir=desc")).await;
+    assert_eq!(indices(rows), vec![3, 2, 1, 0]);
+    // an explicit start still wins in both directions
+    let (_, rows) = send(&app, get("/chain/blocks?dir=asc&start=2")).await;
+    assert_eq!(indices(rows), vec![2, 3]);
+    let (_, rows) = send(&app, get("/chain/blocks?dir=desc&start=1")).await;
+    assert_eq!(indices(rows), vec![1, 0]);
+}
+
+#[tokio::test]
+async fn test_list_blocks_by_account() {
+    let (_dir, state) = test_state();
+    let app = router(state);
+    // blocks 1..=4: alice->bob, carol->dave, nothing, dave->bob
+    for pair in [
+        Some(("alice", "bob")),
+        Some(("carol", "dave")),
+        None,
+        Some(("dave", "bob")),
+    ] {
+        if let Some((from, to)) = pair {
+            let body = format!(r#"{{"from":"{from}","to":"{to}","amount":1}}"#);
+            send(&app, post_json("/tx", &body)).await;
+        }
+        send(&app, get("/mine?target=1")).await;
+    }
+    let indices = |rows: &serde_json::Value| -> Vec<u64> {
+        rows.as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["index"].as_u64().unwrap())
+            .collect()
+    };
+
+    let (_, rows) = send(&app, get("/chain/blocks?account=bob")).await;
+    assert_eq!(indices(&rows), vec![4, 1]);
+    let (_, rows) = send(&app, get("/chain/blocks?account=dave&dir=asc")).await;
+    assert_eq!(indices(&rows), vec![2, 4]);
+    let (_, rows) = send(&app, get("/chain/blocks?account=erin")).await;
+    assert!(indices(&rows).is_empty());
+
+    // the limit counts scanned blocks, so paging carries on past a thin page
+    let uri = "/chain/blocks?account=bob&dir=asc&start=0&limit=2&paged=true";
+    let (_, page) = send(&app, get(uri)).await;
+    assert_eq!(indices(&page["blocks"]), vec![1]);
+    assert_eq!(page["next_start"], 2);
+}
+
+#[tokio::test]
+async fn test_list_blocks_default_limit() {
+    use ledger_core::constants::BLOCKS_PER_BATCH;
+
+    let (_dir, state) = test_state();
+    let mut chain = state.chain.clone();
+    for _ in 0..BLOCKS_PER_BATCH + 10 {
+        chain.mine_with_txs_parallel(vec![], None, 1).unwrap();
+    }
+    let app = router(state);
+    let (_, rows) = send(&app, get("/chain/blocks")).await;
+    assert_eq!(rows.as_array().unwrap().len(), BLOCKS_PER_BATCH as usize);
+}
+
+#[tokio::test]
+async fn test_metrics() {
+    let (_dir, state) = test_state();
+    let app = router(state);
+    send(
+        &app,
+        post_json("/tx", r#"{"from":"alice","to":"bob","amount":1}"#),
+    )
+    .await;
+    send(&app, get("/mine?target=1")).await;
+
+    let (status, body) = send(&app, get("/metrics")).await;
+    assert_eq!(status, StatusCode::OK);
+    let text = body.as_str().unwrap();
+    assert!(text.contains("blocks_mined_total 1"), "{text}");
+    assert!(text.contains("txs_accepted_total 1"), "{text}");
+    assert!(text.contains("tip_height 1"), "{text}");
+    assert!(text.contains("mempool_size 0"), "{text}");
+    assert!(text.contains("mining_duration_seconds_bucket"), "{text}");
+}
+
+#[tokio::test]
+async fn test_validate_chain() {
+    let (dir, state) = test_state();
+    let app = router(state.clone());
+    for _ in 0..3 {
+        let (status, _) = send(&app, get("/mine?target=1")).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, json) = send(&app, post("/chain/validate")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json, serde_json::json!({ "valid": true }));
+    let (_, json) = send(&app, post_json("/chain/validate", r#"{"from":2,"to":3}"#)).await;
+    assert_eq!(json["valid"], true);
+    let (status, _) = send(&app, post_json("/chain/validate", r#"{"from":3,"to":2}"#)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // swap blocks 2 and 3 on disk, behind the store's back
+    state.chain.store().close().unwrap();
+    drop((app, state));
+    {
+        let db = sled::open(dir.path()).unwrap();
+        let blocks = db.open_tree("blocks").unwrap();
+        let (two, three) = (2u64.to_be_bytes(), 3u64.to_be_bytes());
+        let (a, b) = (blocks.get(two).unwrap(), blocks.get(three).unwrap());
+        blocks.insert(two, b.unwrap()).unwrap();
+        blocks.insert(three, a.unwrap()).unwrap();
+        db.flush().unwrap();
+    }
+    let app = router(AppState::new(open_chain(dir.path())));
+    let (status, json) = send(&app, post("/chain/validate")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["valid"], false);
+    assert_eq!(json["bad_index"], 2);
+    assert!(
+        json["reason"].as_str().unwrap().contains("previous_hash"),
+        "{json}"
+    );
+}
+
+#[tokio::test]
+async fn test_prune_chain() {
+    let (_dir, mut state) = test_state();
+    let mut chain = state.chain.clone();
+    for amount in 1..=4 {
+        let tx = Transaction {
+            from: "alice".into(),
+            to: "bob".into(),
+            amount,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        };
+        chain.mine_with_txs_parallel(vec![tx], None, 1).unwrap();
+    }
+    let (status, _) = send(&router(state.clone()), post("/chain/prune?below=3")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    state.allow_prune = true;
+    let app = router(state);
+    let (_, before) = send(&app, get("/balance/bob")).await;
+    assert_eq!(before["balance"], 10);
+    let (status, json) = send(&app, post("/chain/prune?below=3")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["pruned"], 3);
+    // pruned transfers still count towards balances
+    let (_, after) = send(&app, get("/balance/bob")).await;
+    assert_eq!(after, before);
+
+    let (_, block) = send(&app, get("/chain/block/2")).await;
+    assert_eq!(block["pruned"], true);
+    assert_eq!(block["txs"], serde_json::json!([]));
+    assert_eq!(block["tx_count"], 0);
+    let (_, block) = send(&app, get("/chain/block/3")).await;
+    assert!(block.get("pruned").is_none());
+    assert_eq!(block["tx_count"], 1);
+
+    let (_, json) = send(&app, post("/chain/validate")).await;
+    assert_eq!(json["valid"], true);
+}
+
+#[tokio::test]
+async fn test_chain_head_etag() {
+    use tower::ServiceExt;
+
+    let (_dir, state) = test_state();
+    let app = router(state.clone());
+    let head = |etag: Option<&str>| {
+        let mut req = axum::http::Request::builder().uri("/chain/head");
+        if let Some(etag) = etag {
+            req = req.header("if-none-match", etag);
+        }
+        req.body(axum::body::Body::empty()).unwrap()
+    };
+
+    let resp = app.clone().oneshot(head(None)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let etag = resp.headers()["etag"].to_str().unwrap().to_string();
+    let (_, tip) = state.chain.tip().unwrap();
+    assert_eq!(etag, format!("\"{}\"", hex::encode(tip.unwrap())));
+
+    let resp = app.clone().oneshot(head(Some(&etag))).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(body.is_empty());
+
+    send(&app, get("/mine?target=1")).await;
+    let resp = app.clone().oneshot(head(Some(&etag))).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_ne!(resp.headers()["etag"], etag.as_str());
+}
+
+#[tokio::test]
+async fn test_cors_preflight() {
+    use tower::ServiceExt;
+
+    let (_dir, mut state) = test_state();
+    state.cors_origins = vec!["http://dash.local".into()];
+    let app = router(state.clone());
+    let resp = app
+        .clone()
+        .oneshot(preflight("/tx", "http://dash.local"))
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+    assert_eq!(
+        resp.headers()["access-control-allow-origin"],
+        "http://dash.local"
+    );
+    // an origin not on the list gets no grant
+    let resp = app
+        .oneshot(preflight("/tx", "http://evil.local"))
+        .await
+        .unwrap();
+    assert!(resp.headers().get("access-control-allow-origin").is_none());
+
+    // by default there are no CORS headers at all
+    state.cors_origins.clear();
+    let resp = router(state)
+        .oneshot(preflight("/tx", "http://dash.local"))
+        .await
+        .unwrap();
+    assert!(resp.headers().get("access-control-allow-origin").is_none());
+}
+
+#[tokio::test]
+async fn test_write_token() {
+    let (_dir, mut state) = test_state();
+    state.write_token = Some("s3cret".into());
+    let app = router(state);
+    let body = r#"{"from":"alice","to":"bob","amount":10}"#;
+    let with_auth = |auth: &str| {
+        let mut req = post_json("/tx", body);
+        req.headers_mut()
+            .insert("authorization", auth.parse().unwrap());
+        req
+    };
+
+    let (status, json) = send(&app, post_json("/tx", body)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(json["error"], "missing or invalid bearer token");
+    let (status, _) = send(&app, with_auth("Bearer wrong")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(&app, with_auth("s3cret")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(&app, with_auth("Bearer s3cret")).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // reads stay open, including GET on a path whose DELETE is guarded
+    let (status, txs) = send(&app, get("/mempool")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(txs.as_array().unwrap().len(), 1);
+    let (status, _) = send(&app, get("/mine?target=1")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_ws_streams_tip_then_new_blocks() {
+    use tokio_stream::StreamExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let (_dir, state) = test_state();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(serve(listener, state.clone(), std::future::pending()));
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .unwrap();
+    let mut next_json = async || {
+        let msg = tokio::time::timeout(std::time::Duration::from_secs(5), ws.next())
+            .await
+            .expect("no message within 5s")
+            .unwrap()
+            .unwrap();
+        let Message::Text(text) = msg else {
+            panic!("expected a text message, got {msg:?}");
+        };
+        serde_json::from_str::<serde_json::Value>(&text).unwrap()
+    };
+
+    let tip = next_json().await;
+    assert_eq!(tip["height"], 0);
+    assert_eq!(tip["hash"].as_str().unwrap().len(), 64);
+
+    let (_, mined) = send(&router(state), get("/mine?target=1")).await;
+    let block = next_json().await;
+    assert_eq!(block["index"], 1);
+    assert_eq!(block["hash"], mined["hash"]);
+}
+
+#[tokio::test]
+async fn test_serve_shuts_down_gracefully() {
+    let (_dir, state) = test_state();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(serve(listener, state, async {
+        let _ = stopped.await;
+    }));
+
+    let response = tokio::task::spawn_blocking(move || {
+        use std::io::{Read, Write};
+        let mut conn = std::net::TcpStream::connect(addr).unwrap();
+        conn.write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        conn.read_to_string(&mut response).unwrap();
+        response
+    })
+    .await
+    .unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.contains(r#"{"status":"ok"}"#), "{response}");
+
+    stop.send(()).unwrap();
+    let served = tokio::time::timeout(std::time::Duration::from_secs(5), server)
+        .await
+        .expect("server did not shut down")
+        .unwrap();
+    assert!(served.is_ok(), "{served:?}");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_serve_on_unix_socket() {
+    let (dir, state) = test_state();
+    let path = dir.path().join("node.sock");
+    // a socket file left over from a node that died is replaced
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+    let listener = ledger_node::bind_unix(&path).unwrap();
+    tokio::spawn(serve(listener, state, std::future::pending()));
+
+    let (path, response) = tokio::task::spawn_blocking(move || {
+        use std::io::{Read, Write};
+        let mut conn = std::os::unix::net::UnixStream::connect(&path).unwrap();
+        conn.write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        conn.read_to_string(&mut response).unwrap();
+        (path, response)
+    })
+    .await
+    .unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.contains(r#"{"status":"ok"}"#), "{response}");
+
+    // a live socket is left alone
+    let err = ledger_node::bind_unix(&path).unwrap_err();
+    assert!(err.to_string().contains("already in use"), "{err}");
+}
+
+#[test]
+fn test_json_log_format_emits_objects() {
+    #[derive(Clone, Default)]
+    struct Captured(Arc<std::sync::Mutex<Vec<u8>>>);
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let out = Captured::default();
+    let writer = out.clone();
+    let subscriber = log_subscriber(LogFormat::Json, move || writer.clone());
+    // with RUST_LOG unset the env filter still lets errors through
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::error!(height = 7u64, "chain store unavailable");
+    });
+
+    let bytes = out.0.lock().unwrap().clone();
+    let line = String::from_utf8(bytes).unwrap();
+    let record: serde_json::Value = serde_json::from_str(line.lines().next().unwrap()).unwrap();
+    assert!(record.is_object(), "record: {record}");
+    assert_eq!(record["level"], "ERROR");
+    assert_eq!(record["fields"]["message"], "chain store unavailable");
+    assert_eq!(record["fields"]["height"], 7);
+    assert!(record["timestamp"].is_string());
+}
@@ -0,0 +1,85 @@
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+    Router,
+};
+use ledger_core::{chain::Chain, digest::HashAlgo};
+use ledger_node::AppState;
+use ledger_storage::sled_store::SledStore;
+use serde_json::Value;
+use std::{path::Path, sync::Arc};
+use tempfile::{tempdir, TempDir};
+use tower::ServiceExt;
+
+/// Open a fresh sled store in a temp dir and wrap it in an `AppState` with genesis mined.
+pub fn test_state() -> (TempDir, AppState) {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let chain = open_chain(temp_dir.path());
+    chain.ensure_genesis().expect("Failed to create genesis");
+    (temp_dir, AppState::new(chain))
+}
+
+/// Open the sled store at `path` as a SHA-256 chain whatever the `blake3`
+/// feature makes the default, since the tests build blocks with the SHA-256
+/// helpers.
+pub fn open_chain(path: &Path) -> Chain<SledStore> {
+    let store = SledStore::open(path)
+        .expect("Failed to open SledStore")
+        .with_hash_algo(HashAlgo::Sha256);
+    Chain::new(Arc::new(store)).with_hash_algo(HashAlgo::Sha256)
+}
+
+pub fn get(uri: &str) -> Request<Body> {
+    Request::builder().uri(uri).body(Body::empty()).unwrap()
+}
+
+pub fn post(uri: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .body(Body::empty())
+        .unwrap()
+}
+
+pub fn delete(uri: &str) -> Request<Body> {
+    Request::builder()
+        .method("DELETE")
+        .uri(uri)
+        .body(Body::empty())
+        .unwrap()
+}
+
+pub fn post_json(uri: &str, body: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+/// A CORS preflight from `origin` for a JSON `POST` to `uri`.
+pub fn preflight(uri: &str, origin: &str) -> Request<Body> {
+    Request::builder()
+        .method("OPTIONS")
+        .uri(uri)
+        .header("origin", origin)
+        .header("access-control-request-method", "POST")
+        .header("access-control-request-headers", "content-type")
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// Drive a single request through the router and decode the JSON body (Null when empty).
+pub async fn send(app: &Router, req: Request<Body>) -> (StatusCode, Value) {
+    let resp = app.clone().oneshot(req).await.expect("request failed");
+    let status = resp.status();
+    let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let body = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()))
+    };
+    (status, body)
+}
@@ -0,0 +1,26 @@
+//! Typed errors surfaced by the storage backends.
+//!
+//! Store methods still return `anyhow::Result`; callers that care about a
+//! particular failure can `downcast_ref::<StorageError>()`.
+
+use ledger_core::Hash;
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum StorageError {
+    /// A block's transactions no longer hash to the merkle root in its header.
+    #[error(
+        "merkle root mismatch in block {index}: header has {}, txs hash to {}",
+        hex::encode(expected),
+        hex::encode(actual)
+    )]
+    MerkleMismatch {
+        index: u64,
+        expected: Hash,
+        actual: Hash,
+    },
+    /// Another process (or another store in this one) has the data dir open.
+    #[error("data dir is locked by another process: {}", .0.display())]
+    Locked(PathBuf),
+}
@@ -1,9 +1,18 @@
+pub mod error;
+pub mod mem_store;
 pub mod sled_store;
 
+pub use error::StorageError;
+
 use anyhow::Result;
-use ledger_core::{Block, Hash};
+use ledger_core::{chain::ChainStore, Block, Hash};
 
-/// Legacy trait kept for internal use; Chain uses the core's ChainStore trait.
+/// Legacy subset of [`ChainStore`]. Every `ChainStore` is a `Storage`, so
+/// backends implement `ChainStore` alone and old `Storage` bounds still work.
+#[deprecated(
+    since = "0.3.3",
+    note = "implement and bound on `ledger_core::chain::ChainStore`"
+)]
 pub trait Storage: Send + Sync {
     fn put_block(&self, block: &Block) -> Result<()>;
     fn get_block(&self, index: u64) -> Result<Option<Block>>;
@@ -11,3 +20,22 @@ pub trait Storage: Send + Sync {
     fn tip_hash(&self) -> Result<Option<Hash>>;
     fn close(&self) -> Result<()>;
 }
+
+#[allow(deprecated)]
+impl<T: ChainStore + ?Sized> Storage for T {
+    fn put_block(&self, block: &Block) -> Result<()> {
+        ChainStore::put_block(self, block)
+    }
+    fn get_block(&self, index: u64) -> Result<Option<Block>> {
+        ChainStore::get_block(self, index)
+    }
+    fn tip_height(&self) -> Result<u64> {
+        ChainStore::tip_height(self)
+    }
+    fn tip_hash(&self) -> Result<Option<Hash>> {
+        ChainStore::tip_hash(self)
+    }
+    fn close(&self) -> Result<()> {
+        ChainStore::close(self)
+    }
+}
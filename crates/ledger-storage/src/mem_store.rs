@@ -0,0 +1,161 @@
+//! In-memory backend with the same semantics as [`SledStore`](crate::sled_store::SledStore).
+//!
+//! Nothing touches disk, which makes it the natural store for tests and for
+//! embedders that do not need persistence.
+
+use anyhow::Result;
+use ledger_core::{chain::ChainStore, Block, Hash};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+#[derive(Debug, Default)]
+struct Inner {
+    blocks: BTreeMap<u64, Block>,
+    by_hash: HashMap<Hash, u64>,
+    /// Height and hash of the tip, cached so reads don't walk the map.
+    tip: Option<(u64, Hash)>,
+}
+
+#[derive(Debug, Default)]
+pub struct MemStore {
+    inner: RwLock<Inner>,
+}
+
+impl MemStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop every block and reset the tip.
+    pub fn clear(&self) -> Result<()> {
+        *self.inner.write().unwrap() = Inner::default();
+        Ok(())
+    }
+
+    /// Up to `limit` blocks starting at `start`, walking down when `desc` is
+    /// set and up otherwise. `start` itself is included if present.
+    pub fn list_blocks_range(&self, start: u64, limit: u32, desc: bool) -> Result<Vec<Block>> {
+        let inner = self.inner.read().unwrap();
+        let blocks = &inner.blocks;
+        let out = if desc {
+            blocks
+                .range(..=start)
+                .rev()
+                .take(limit as usize)
+                .map(|(_, b)| b.clone())
+                .collect()
+        } else {
+            blocks
+                .range(start..)
+                .take(limit as usize)
+                .map(|(_, b)| b.clone())
+                .collect()
+        };
+        Ok(out)
+    }
+
+    pub fn get_block_by_hash(&self, hash: &Hash) -> Result<Option<Block>> {
+        let inner = self.inner.read().unwrap();
+        Ok(inner
+            .by_hash
+            .get(hash)
+            .and_then(|index| inner.blocks.get(index))
+            .cloned())
+    }
+
+    /// Remove the block at `index`; if it was the tip, the tip moves to the
+    /// highest remaining block.
+    pub fn delete_block(&self, index: u64) -> Result<()> {
+        let mut inner = self.inner.write().unwrap();
+        let Some(block) = inner.blocks.remove(&index) else {
+            return Ok(());
+        };
+        inner.by_hash.remove(&block.hash());
+        if inner.tip.is_some_and(|(height, _)| height == index) {
+            inner.tip = inner
+                .blocks
+                .iter()
+                .next_back()
+                .map(|(height, b)| (*height, b.hash()));
+        }
+        Ok(())
+    }
+}
+
+impl Inner {
+    /// Store `block` unless its index is taken.
+    fn insert(&mut self, block: &Block) {
+        let index = block.header.index;
+        if self.blocks.contains_key(&index) {
+            // Block already exists, no-op
+            return;
+        }
+        let hash = block.hash();
+        self.blocks.insert(index, block.clone());
+        self.by_hash.insert(hash, index);
+        // only advance the tip when the block extends the chain
+        if self.tip.is_none_or(|(height, _)| index > height) {
+            self.tip = Some((index, hash));
+        }
+    }
+}
+
+impl ChainStore for MemStore {
+    fn put_block(&self, block: &Block) -> anyhow::Result<()> {
+        self.inner.write().unwrap().insert(block);
+        Ok(())
+    }
+    fn put_block_after(&self, block: &Block, parent: Option<Hash>) -> anyhow::Result<bool> {
+        let mut inner = self.inner.write().unwrap();
+        let index = block.header.index;
+        if let Some(below) = index.checked_sub(1) {
+            if inner.blocks.get(&below).map(Block::hash) != parent {
+                return Ok(false);
+            }
+        }
+        if inner.blocks.contains_key(&index) {
+            return Ok(false);
+        }
+        inner.insert(block);
+        Ok(true)
+    }
+    fn replace_block(&self, block: &Block) -> anyhow::Result<()> {
+        let mut inner = self.inner.write().unwrap();
+        let index = block.header.index;
+        let hash = block.hash();
+        if let Some(old) = inner.blocks.insert(index, block.clone()) {
+            inner.by_hash.remove(&old.hash());
+        }
+        inner.by_hash.insert(hash, index);
+        if inner.tip.is_none_or(|(height, _)| index >= height) {
+            inner.tip = Some((index, hash));
+        }
+        Ok(())
+    }
+    fn get_block(&self, index: u64) -> anyhow::Result<Option<Block>> {
+        Ok(self.inner.read().unwrap().blocks.get(&index).cloned())
+    }
+    fn get_block_by_hash(&self, hash: &Hash) -> anyhow::Result<Option<Block>> {
+        MemStore::get_block_by_hash(self, hash)
+    }
+    fn delete_block(&self, index: u64) -> anyhow::Result<()> {
+        MemStore::delete_block(self, index)
+    }
+    fn list_blocks_range(&self, start: u64, limit: u32, desc: bool) -> anyhow::Result<Vec<Block>> {
+        MemStore::list_blocks_range(self, start, limit, desc)
+    }
+    fn tip_height(&self) -> anyhow::Result<u64> {
+        Ok(self
+            .inner
+            .read()
+            .unwrap()
+            .tip
+            .map_or(0, |(height, _)| height))
+    }
+    fn tip_hash(&self) -> anyhow::Result<Option<Hash>> {
+        Ok(self.inner.read().unwrap().tip.map(|(_, hash)| hash))
+    }
+    fn close(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
@@ -1,40 +1,945 @@
-use crate::Storage;
-use anyhow::{Ok, Result};
+use crate::StorageError;
+use anyhow::Result;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use ledger_core::constants::HASH_SIZE;
-use ledger_core::{Block, Hash};
-use sled::{Db, IVec};
+use ledger_core::{
+    chain::ChainStore, digest::HashAlgo, ledger::BalanceLedger, pow::block_work, tx_hash, Block,
+    BlockHeader, Hash, Transaction,
+};
+use serde::{Deserialize, Serialize};
+use sled::{
+    transaction::{ConflictableTransactionError, TransactionError, TransactionResult},
+    Db, IVec, Transactional,
+};
+use std::io::{Read, Write};
+use std::ops::Bound;
 use std::path::Path;
+use std::time::{Duration, Instant};
 use tracing::info;
 
 const TREE_BLOCKS: &str = "blocks";
+/// Block hash -> big-endian index key into `TREE_BLOCKS`.
+const TREE_HASH_INDEX: &str = "hash_index";
+/// Big-endian index -> big-endian `u128` total work of the chain up to it.
+const TREE_WORK: &str = "work";
+/// Transaction hash -> big-endian index of the block holding it.
+const TREE_TX_INDEX: &str = "tx_index";
+/// Big-endian index -> empty, for blocks whose body was dropped by
+/// [`SledStore::prune_bodies_below`].
+const TREE_PRUNED: &str = "pruned";
+/// Account -> big-endian `i64` balance over every pruned block, so balances
+/// survive the pruned transactions.
+const TREE_PRUNED_BALANCES: &str = "pruned_balances";
+/// Transaction hash -> bincode `(seq, Transaction)` for the node's mempool,
+/// so pending transactions survive a restart.
+const TREE_MEMPOOL: &str = "mempool";
 const KEY_TIP_HEIGHT: &[u8] = b"tip_height";
 const KEY_TIP_HASH: &[u8] = b"tip_hash";
+/// Set once `TREE_TX_INDEX` covers every stored block. Blocks without
+/// transactions add no entries, so an empty index alone proves nothing.
+const KEY_TX_INDEX_BUILT: &[u8] = b"tx_index_built";
+/// Set once every value in `TREE_BLOCKS` starts with a format tag.
+const KEY_BLOCKS_TAGGED: &[u8] = b"blocks_tagged";
+/// Last key tagged by an unfinished format-tag backfill.
+const KEY_BLOCKS_TAGGING_AT: &[u8] = b"blocks_tagging_at";
+/// Values the format-tag backfill rewrites per transaction.
+const TAG_BATCH: usize = 1024;
+
+/// First byte of each value in `TREE_BLOCKS`: how the bincode after it is
+/// stored. Tagging per value lets one store hold every kind, so turning
+/// compression on or off never needs a rewrite.
+///
+/// Blocks written before transactions had fees and signatures, tagged by
+/// the format-tag backfill; decoded through [`LegacyBlock`].
+const FORMAT_LEGACY: u8 = 0;
+const FORMAT_GZIP: u8 = 1;
+const FORMAT_RAW: u8 = 2;
+
+/// How long [`SledStore::open`] waits for a held data dir lock. A store
+/// dropped moments ago may still hold it while sled shuts down.
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// sled reports a held lock as a generic I/O error; only its message
+/// tells it apart.
+fn is_lock_error(e: &sled::Error) -> bool {
+    matches!(e, sled::Error::Io(io) if io.to_string().starts_with("could not acquire lock"))
+}
+
+fn decode_height(v: &[u8]) -> u64 {
+    let mut arr = [0u8; 8];
+    arr.copy_from_slice(v);
+    u64::from_be_bytes(arr)
+}
+
+fn decode_work(v: &[u8]) -> u128 {
+    let mut arr = [0u8; 16];
+    arr.copy_from_slice(v);
+    u128::from_be_bytes(arr)
+}
+
+fn decode_balance(v: &[u8]) -> i64 {
+    let mut arr = [0u8; 8];
+    arr.copy_from_slice(v);
+    i64::from_be_bytes(arr)
+}
+
+fn encode_block(block: &Block, compress: bool) -> Result<Vec<u8>> {
+    let bytes = bincode::serialize(block)?;
+    if !compress {
+        let mut out = Vec::with_capacity(bytes.len() + 1);
+        out.push(FORMAT_RAW);
+        out.extend_from_slice(&bytes);
+        return Ok(out);
+    }
+    let mut gz = GzEncoder::new(vec![FORMAT_GZIP], Compression::default());
+    gz.write_all(&bytes)?;
+    Ok(gz.finish()?)
+}
+
+/// The bincode layout of a [`Block`] before transactions carried a fee,
+/// signature and public key. bincode is not self-describing, so these
+/// blocks cannot be read as the current struct.
+#[derive(Serialize, Deserialize)]
+struct LegacyBlock {
+    header: BlockHeader,
+    data: Option<String>,
+    txs: Vec<LegacyTransaction>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LegacyTransaction {
+    from: String,
+    to: String,
+    amount: u64,
+    timestamp: u64,
+}
+
+impl From<LegacyBlock> for Block {
+    fn from(block: LegacyBlock) -> Self {
+        let txs = block
+            .txs
+            .into_iter()
+            .map(|tx| Transaction {
+                from: tx.from,
+                to: tx.to,
+                amount: tx.amount,
+                timestamp: tx.timestamp,
+                fee: 0,
+                signature: None,
+                pubkey: None,
+            })
+            .collect();
+        Block {
+            header: block.header,
+            data: block.data,
+            txs,
+        }
+    }
+}
+
+fn decode_block(v: &[u8]) -> Result<Block> {
+    match v.split_first() {
+        Some((&FORMAT_RAW, rest)) => Ok(bincode::deserialize(rest)?),
+        Some((&FORMAT_LEGACY, rest)) => Ok(bincode::deserialize::<LegacyBlock>(rest)?.into()),
+        Some((&FORMAT_GZIP, rest)) => {
+            let mut bytes = Vec::new();
+            GzDecoder::new(rest).read_to_end(&mut bytes)?;
+            Ok(bincode::deserialize(&bytes)?)
+        }
+        Some((tag, _)) => Err(anyhow::anyhow!("unknown block format tag {tag}")),
+        None => Err(anyhow::anyhow!("empty block value")),
+    }
+}
+
+/// Tuning for [`SledStore::open_with`]. The default matches
+/// [`SledStore::open`]: sled's own settings and a flush after every write.
+#[derive(Clone, Debug)]
+pub struct SledConfig {
+    /// Interval of sled's background flush; `None` turns it off.
+    pub flush_every_ms: Option<u64>,
+    /// Bytes of page cache sled may keep in memory.
+    pub cache_capacity_bytes: u64,
+    pub mode: sled::Mode,
+    /// Skip the flush after each `put_block` and `delete_block`. Writes then
+    /// reach disk on the background flush or at `close`, so a crash can lose
+    /// the most recent blocks. Meant for bulk loads.
+    pub defer_flush: bool,
+    /// How long to wait for a data dir locked by another store.
+    pub lock_timeout: Duration,
+    /// Gzip blocks as they are written. Blocks already stored are read
+    /// either way, so this can change between opens.
+    pub compress: bool,
+}
+
+impl Default for SledConfig {
+    fn default() -> Self {
+        Self {
+            flush_every_ms: Some(500),
+            cache_capacity_bytes: 1024 * 1024 * 1024,
+            mode: sled::Mode::LowSpace,
+            defer_flush: false,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            compress: false,
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct SledStore {
     db: Db,
+    verify_merkle_on_read: bool,
+    hash_algo: HashAlgo,
+    defer_flush: bool,
+    compress: bool,
 }
 
 impl SledStore {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let db = sled::open(path)?;
+        Self::open_with(path, SledConfig::default())
+    }
+
+    /// Like [`SledStore::open`], retrying for up to `timeout` while the data
+    /// dir is locked, then failing with [`StorageError::Locked`].
+    pub fn open_with_lock_timeout<P: AsRef<Path>>(path: P, timeout: Duration) -> Result<Self> {
+        Self::open_with(
+            path,
+            SledConfig {
+                lock_timeout: timeout,
+                ..SledConfig::default()
+            },
+        )
+    }
+
+    /// Open with explicit sled tuning; see [`SledConfig`].
+    pub fn open_with<P: AsRef<Path>>(path: P, config: SledConfig) -> Result<Self> {
+        let path = path.as_ref();
+        let sled_config = sled::Config::new()
+            .path(path)
+            .cache_capacity(config.cache_capacity_bytes)
+            .mode(config.mode)
+            .flush_every_ms(config.flush_every_ms);
+        let started = Instant::now();
+        let db = loop {
+            match sled_config.open() {
+                Ok(db) => break db,
+                Err(e) if is_lock_error(&e) => {
+                    if started.elapsed() >= config.lock_timeout {
+                        return Err(StorageError::Locked(path.to_path_buf()).into());
+                    }
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        };
         info!("sled store opened");
-        Ok(Self { db })
+        let store = Self {
+            db,
+            verify_merkle_on_read: false,
+            hash_algo: HashAlgo::default(),
+            defer_flush: config.defer_flush,
+            compress: config.compress,
+        };
+        store.backfill_format_tags()?;
+        store.backfill_hash_index()?;
+        store.backfill_work()?;
+        store.backfill_tx_index()?;
+        Ok(store)
+    }
+
+    /// Databases written before blocks carried a format tag hold bare
+    /// bincode in the legacy layout; tag every value as
+    /// [`FORMAT_LEGACY`] once. Values are retagged
+    /// [`TAG_BATCH`] at a time, each batch in one transaction with a cursor
+    /// at its last key, so a crash resumes where it stopped and never
+    /// tags a value twice.
+    fn backfill_format_tags(&self) -> Result<()> {
+        if self.db.contains_key(KEY_BLOCKS_TAGGED)? {
+            return Ok(());
+        }
+        let tree = self.blocks();
+        let mut total = 0u64;
+        loop {
+            let cursor = self.db.get(KEY_BLOCKS_TAGGING_AT)?;
+            let range = match &cursor {
+                Some(last) => {
+                    tree.range::<&[u8], _>((Bound::Excluded(&last[..]), Bound::Unbounded))
+                }
+                None => tree.iter(),
+            };
+            let mut rows = Vec::with_capacity(TAG_BATCH);
+            for kv in range.take(TAG_BATCH) {
+                let (key, v) = kv?;
+                let mut tagged = Vec::with_capacity(v.len() + 1);
+                tagged.push(FORMAT_LEGACY);
+                tagged.extend_from_slice(&v);
+                rows.push((key, tagged));
+            }
+            let Some((last, _)) = rows.last() else {
+                break;
+            };
+            let last = last.clone();
+            let result: TransactionResult<()> = (&tree, &*self.db).transaction(|(blocks, meta)| {
+                for (key, tagged) in &rows {
+                    blocks.insert(key, tagged.as_slice())?;
+                }
+                meta.insert(KEY_BLOCKS_TAGGING_AT, &last)?;
+                Ok(())
+            });
+            if let Err(e) = result {
+                return Err(anyhow::anyhow!("failed to tag stored blocks: {e:?}"));
+            }
+            total += rows.len() as u64;
+            self.db.flush()?;
+        }
+        if total > 0 {
+            info!("tagged {total} stored blocks with their format");
+        }
+        self.db.insert(KEY_BLOCKS_TAGGED, &[])?;
+        self.db.remove(KEY_BLOCKS_TAGGING_AT)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Databases written before the hash index existed have blocks but an
+    /// empty index; rebuild it once so lookups by hash see every block.
+    fn backfill_hash_index(&self) -> Result<()> {
+        let index = self.hash_index();
+        if !index.is_empty() {
+            return Ok(());
+        }
+        let mut count = 0u64;
+        for kv in self.blocks().iter() {
+            let (key, v) = kv?;
+            let block = decode_block(&v)?;
+            index.insert(block.hash(), key)?;
+            count += 1;
+        }
+        if count > 0 {
+            info!("rebuilt hash index for {count} blocks");
+            index.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Databases written before work was tracked have no running totals;
+    /// compute them once in index order.
+    fn backfill_work(&self) -> Result<()> {
+        let work = self.work();
+        if !work.is_empty() {
+            return Ok(());
+        }
+        let mut total = 0u128;
+        let mut count = 0u64;
+        for kv in self.blocks().iter() {
+            let (key, v) = kv?;
+            let block = decode_block(&v)?;
+            total = total.saturating_add(block_work(&block.hash()));
+            work.insert(key, &total.to_be_bytes())?;
+            count += 1;
+        }
+        if count > 0 {
+            info!("rebuilt total work for {count} blocks");
+            work.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Same as [`Self::backfill_hash_index`], for the transaction index.
+    fn backfill_tx_index(&self) -> Result<()> {
+        if self.db.contains_key(KEY_TX_INDEX_BUILT)? {
+            return Ok(());
+        }
+        let index = self.tx_index();
+        let mut count = 0u64;
+        for kv in self.blocks().iter() {
+            let (key, v) = kv?;
+            let block = decode_block(&v)?;
+            for tx in &block.txs {
+                index.insert(tx_hash(tx), &key)?;
+                count += 1;
+            }
+        }
+        if count > 0 {
+            info!("rebuilt transaction index for {count} txs");
+        }
+        self.db.insert(KEY_TX_INDEX_BUILT, &[])?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Drop the hash, transaction and work indexes and rebuild them from the
+    /// stored blocks, for chains whose indexes are missing or suspect. The
+    /// on-open backfills only fill empty indexes; this always starts over.
+    /// Returns the number of blocks replayed.
+    pub fn rebuild_indexes(&self) -> Result<u64> {
+        let hash_index = self.hash_index();
+        let work = self.work();
+        let tx_index = self.tx_index();
+        // the marker goes first, so a crash mid-rebuild leaves the
+        // transaction index to be rebuilt on the next open
+        self.db.remove(KEY_TX_INDEX_BUILT)?;
+        self.db.flush()?;
+        hash_index.clear()?;
+        work.clear()?;
+        tx_index.clear()?;
+
+        let mut total = 0u128;
+        let mut count = 0u64;
+        for block in self.iter_blocks(0, false) {
+            let block = block?;
+            let key = block.header.index.to_be_bytes();
+            hash_index.insert(block.hash(), &key)?;
+            total = total.saturating_add(self.work_of(&block));
+            work.insert(key, &total.to_be_bytes())?;
+            for tx in &block.txs {
+                tx_index.insert(tx_hash(tx), &key)?;
+            }
+            count += 1;
+            if count.is_multiple_of(1000) {
+                info!("replayed {count} blocks");
+            }
+        }
+        self.db.flush()?;
+        self.db.insert(KEY_TX_INDEX_BUILT, &[])?;
+        self.db.flush()?;
+        info!("rebuilt indexes for {count} blocks");
+        Ok(count)
+    }
+
+    /// Recompute the merkle root of every block read through `get_block` and
+    /// fail with [`StorageError::MerkleMismatch`] if it disagrees with the
+    /// header. Off by default since it hashes every transaction on each read.
+    pub fn with_verify_merkle_on_read(mut self, verify: bool) -> Self {
+        self.verify_merkle_on_read = verify;
+        self
+    }
+
+    /// The algorithm the chain in this store hashes merkle trees with, for
+    /// [`SledStore::with_verify_merkle_on_read`]. It must match the chain's
+    /// [`Chain::with_hash_algo`](ledger_core::chain::Chain::with_hash_algo).
+    pub fn with_hash_algo(mut self, algo: HashAlgo) -> Self {
+        self.hash_algo = algo;
+        self
+    }
+
+    /// Fail with [`StorageError::MerkleMismatch`] when merkle-on-read is on
+    /// and `block` no longer matches its header. Pruned stubs have no
+    /// transactions left to check and pass.
+    fn check_merkle(&self, block: &Block) -> Result<()> {
+        let index = block.header.index;
+        if !self.verify_merkle_on_read
+            || block.merkle_root_matches_with(self.hash_algo)
+            || self.is_pruned(index)?
+        {
+            return Ok(());
+        }
+        Err(StorageError::MerkleMismatch {
+            index,
+            expected: block.header.merkle_root,
+            actual: self.hash_algo.merkle_root(&block.txs),
+        }
+        .into())
+    }
+
+    /// Decode a stored block for a reader, applying [`Self::check_merkle`].
+    fn read_block(&self, bytes: &[u8]) -> Result<Block> {
+        let block = decode_block(bytes)?;
+        self.check_merkle(&block)?;
+        Ok(block)
     }
 
     fn blocks(&self) -> sled::Tree {
         self.db.open_tree(TREE_BLOCKS).expect("open tree")
     }
 
+    fn hash_index(&self) -> sled::Tree {
+        self.db.open_tree(TREE_HASH_INDEX).expect("open tree")
+    }
+
+    fn work(&self) -> sled::Tree {
+        self.db.open_tree(TREE_WORK).expect("open tree")
+    }
+
+    fn tx_index(&self) -> sled::Tree {
+        self.db.open_tree(TREE_TX_INDEX).expect("open tree")
+    }
+
+    fn pruned(&self) -> sled::Tree {
+        self.db.open_tree(TREE_PRUNED).expect("open tree")
+    }
+
+    fn pruned_balances_tree(&self) -> sled::Tree {
+        self.db.open_tree(TREE_PRUNED_BALANCES).expect("open tree")
+    }
+
+    /// Replace the blocks below `below` with header-only stubs: no
+    /// transactions and no data. Headers, and so hashes, links and work,
+    /// are untouched, so the chain still validates; the pruned
+    /// transactions leave the transaction index, and their effect on
+    /// balances is folded into [`Self::pruned_balances`]. The tip always
+    /// keeps its body. Returns how many blocks were newly pruned.
+    pub fn prune_bodies_below(&self, below: u64) -> Result<u64> {
+        if <Self as ChainStore>::tip_hash(self)?.is_none() {
+            return Ok(0);
+        }
+        let below = below.min(<Self as ChainStore>::tip_height(self)?);
+        let tree = self.blocks();
+        let pruned = self.pruned();
+        let tx_index = self.tx_index();
+        let balances = self.pruned_balances_tree();
+        let keys = tree
+            .range(..below.to_be_bytes())
+            .keys()
+            .collect::<sled::Result<Vec<IVec>>>()?;
+
+        let mut count = 0u64;
+        for key in keys {
+            if pruned.contains_key(&key)? {
+                continue;
+            }
+            let Some(bytes) = tree.get(&key)? else {
+                continue;
+            };
+            let block = decode_block(&bytes)?;
+            let tx_hashes: Vec<Hash> = block.txs.iter().map(tx_hash).collect();
+            let mut delta = BalanceLedger::new();
+            delta.apply_block(&block);
+            let stub = encode_block(
+                &Block {
+                    header: block.header,
+                    txs: vec![],
+                    data: None,
+                },
+                self.compress,
+            )?;
+            let result: TransactionResult<()> = (&tree, &pruned, &tx_index, &balances).transaction(
+                |(blocks, pruned, by_tx, balances)| {
+                    blocks.insert(&key, stub.as_slice())?;
+                    pruned.insert(&key, &[])?;
+                    for h in &tx_hashes {
+                        if by_tx.get(h)?.is_some_and(|k| k == key) {
+                            by_tx.remove(h)?;
+                        }
+                    }
+                    for (account, change) in delta.balances() {
+                        let balance = balances.get(account)?.map_or(0, |v| decode_balance(&v));
+                        balances.insert(account, &balance.saturating_add(change).to_be_bytes())?;
+                    }
+                    Ok(())
+                },
+            );
+            if let Err(e) = result {
+                let index = decode_height(&key);
+                return Err(anyhow::anyhow!("failed to prune block {index}: {e:?}"));
+            }
+            count += 1;
+        }
+        if count > 0 {
+            info!("pruned the bodies of {count} blocks below {below}");
+        }
+        if !self.defer_flush {
+            self.db.flush()?;
+        }
+        Ok(count)
+    }
+
+    fn mempool(&self) -> sled::Tree {
+        self.db.open_tree(TREE_MEMPOOL).expect("open tree")
+    }
+
+    /// Persisted pending transactions with their admission sequence, in
+    /// sequence order.
+    pub fn pending_txs(&self) -> Result<Vec<(u64, Transaction)>> {
+        let mut out = self
+            .mempool()
+            .iter()
+            .values()
+            .map(|v| Ok(bincode::deserialize::<(u64, Transaction)>(&v?)?))
+            .collect::<Result<Vec<_>>>()?;
+        out.sort_by_key(|(seq, _)| *seq);
+        Ok(out)
+    }
+
+    /// Persist pending transactions in one batch; identical ones already
+    /// stored are overwritten.
+    pub fn put_pending_txs<'a>(
+        &self,
+        pending: impl IntoIterator<Item = (u64, &'a Transaction)>,
+    ) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for (seq, tx) in pending {
+            batch.insert(&tx_hash(tx), bincode::serialize(&(seq, tx))?);
+        }
+        self.mempool().apply_batch(batch)?;
+        if !self.defer_flush {
+            self.db.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Forget persisted pending transactions, e.g. once they are mined.
+    /// Transactions that aren't persisted are ignored.
+    pub fn remove_pending_txs(&self, txs: &[Transaction]) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for tx in txs {
+            batch.remove(&tx_hash(tx));
+        }
+        self.mempool().apply_batch(batch)?;
+        if !self.defer_flush {
+            self.db.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Replace every persisted pending transaction with `pending`.
+    pub fn replace_pending_txs<'a>(
+        &self,
+        pending: impl IntoIterator<Item = (u64, &'a Transaction)>,
+    ) -> Result<()> {
+        let tree = self.mempool();
+        let mut batch = sled::Batch::default();
+        for key in tree.iter().keys() {
+            batch.remove(key?);
+        }
+        for (seq, tx) in pending {
+            batch.insert(&tx_hash(tx), bincode::serialize(&(seq, tx))?);
+        }
+        tree.apply_batch(batch)?;
+        if !self.defer_flush {
+            self.db.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Whether the body of the block at `index` was dropped by
+    /// [`Self::prune_bodies_below`].
+    pub fn is_pruned(&self, index: u64) -> Result<bool> {
+        Ok(self.pruned().contains_key(index.to_be_bytes())?)
+    }
+
+    /// Balances as of the pruned blocks alone; empty if nothing was pruned.
+    /// Replaying the remaining blocks on top of these gives the balances
+    /// the full chain would.
+    pub fn pruned_balances(&self) -> Result<BalanceLedger> {
+        let balances = self
+            .pruned_balances_tree()
+            .iter()
+            .map(|entry| {
+                let (k, v) = entry?;
+                Ok((String::from_utf8(k.to_vec())?, decode_balance(&v)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(BalanceLedger::from_balances(balances))
+    }
+
+    /// Index of the block holding the transaction with this hash, via the
+    /// transaction index.
+    pub fn tx_block_index(&self, tx_hash: &Hash) -> Result<Option<u64>> {
+        Ok(self.tx_index().get(tx_hash)?.map(|v| decode_height(&v)))
+    }
+
+    /// Total work of the chain up to and including the tip, kept as a
+    /// running sum alongside each block by `put_block`. `0` while a gap
+    /// below the tip leaves it without a total.
+    pub fn tip_work(&self) -> Result<u128> {
+        if <Self as ChainStore>::tip_hash(self)?.is_none() {
+            return Ok(0);
+        }
+        let tip = <Self as ChainStore>::tip_height(self)?;
+        Ok(self
+            .work()
+            .get(tip.to_be_bytes())?
+            .map(|v| decode_work(&v))
+            .unwrap_or(0))
+    }
+
+    /// Look a block up by its header hash via the hash index.
+    pub fn get_block_by_hash(&self, hash: &Hash) -> Result<Option<Block>> {
+        match self.hash_index().get(hash)? {
+            Some(key) => <Self as ChainStore>::get_block(self, decode_height(&key)),
+            None => Ok(None),
+        }
+    }
+
+    /// Remove the block at `index` and its hash index entry. When the tip is
+    /// removed the tip moves to the highest remaining block, or is cleared if
+    /// none remain.
+    pub fn delete_block(&self, index: u64) -> Result<()> {
+        let tree = self.blocks();
+        let hash_index = self.hash_index();
+        let work = self.work();
+        let tx_index = self.tx_index();
+        let pruned = self.pruned();
+        let key = index.to_be_bytes();
+        let Some(bytes) = tree.get(key)? else {
+            return Ok(());
+        };
+        let block = decode_block(&bytes)?;
+        let hash = block.hash();
+        let tx_hashes: Vec<Hash> = block.txs.iter().map(tx_hash).collect();
+        let was_tip = <Self as ChainStore>::tip_hash(self)? == Some(hash);
+        let new_tip = match tree.range(..key).next_back() {
+            Some(kv) => {
+                let (k, v) = kv?;
+                Some((k, decode_block(&v)?.hash()))
+            }
+            None => None,
+        };
+
+        let result: TransactionResult<()> =
+            (&tree, &hash_index, &work, &tx_index, &pruned, &*self.db).transaction(
+                |(blocks, by_hash, work, by_tx, pruned, meta)| {
+                    blocks.remove(&key)?;
+                    by_hash.remove(&hash)?;
+                    work.remove(&key)?;
+                    pruned.remove(&key)?;
+                    for h in &tx_hashes {
+                        // an identical tx in another block keeps that entry
+                        if by_tx.get(h)?.is_some_and(|k| k == key) {
+                            by_tx.remove(h)?;
+                        }
+                    }
+                    if was_tip {
+                        match &new_tip {
+                            Some((k, h)) => {
+                                meta.insert(KEY_TIP_HEIGHT, k)?;
+                                meta.insert(KEY_TIP_HASH, h)?;
+                            }
+                            None => {
+                                meta.remove(KEY_TIP_HEIGHT)?;
+                                meta.remove(KEY_TIP_HASH)?;
+                            }
+                        }
+                    }
+                    Ok(())
+                },
+            );
+        if let Err(e) = result {
+            return Err(anyhow::anyhow!("failed to delete block {index}: {e:?}"));
+        }
+        if !self.defer_flush {
+            self.db.flush()?;
+        }
+        Ok(())
+    }
+
     // Additional method to clear the database (for testing purposes)
     pub fn clear(&self) -> Result<()> {
         self.db.drop_tree(TREE_BLOCKS)?;
+        self.db.drop_tree(TREE_HASH_INDEX)?;
+        self.db.drop_tree(TREE_WORK)?;
+        self.db.drop_tree(TREE_TX_INDEX)?;
+        self.db.drop_tree(TREE_PRUNED)?;
+        self.db.drop_tree(TREE_MEMPOOL)?;
         self.db.remove(KEY_TIP_HEIGHT)?;
         self.db.remove(KEY_TIP_HASH)?;
         self.db.flush()?;
         Ok(())
     }
 
+    /// [`block_work`] of `block`, hashed with the chain's algorithm.
+    fn work_of(&self, block: &Block) -> u128 {
+        block_work(&self.hash_algo.header_hash(block.header))
+    }
+
+    /// Write the running work of stored blocks from `from` up that are
+    /// still waiting on their parent's total, stopping at the first block
+    /// that is missing, already has one, or still can't get one.
+    fn fill_deferred_work(&self, from: u64) -> Result<()> {
+        let (tree, work) = (self.blocks(), self.work());
+        for kv in tree.range(from.to_be_bytes()..) {
+            let (key, v) = kv?;
+            let index = decode_height(&key);
+            if work.contains_key(&key)? {
+                break;
+            }
+            let Some(parent) = work.get((index - 1).to_be_bytes())? else {
+                break;
+            };
+            let total = decode_work(&parent).saturating_add(self.work_of(&decode_block(&v)?));
+            work.insert(&key, &total.to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Store `blocks` in order in a single transaction and flush once.
+    /// Indices already stored are skipped, and the tip moves to the highest
+    /// index written if that extends the chain.
+    /// A block whose parent isn't stored gets no work entry until the gap
+    /// is filled.
+    pub fn put_blocks(&self, blocks: &[Block]) -> Result<()> {
+        self.write_blocks(blocks, None).map(|_| ())
+    }
+
+    /// Store `block` only if its index is free and the block below it still
+    /// has hash `parent`, checked in the same transaction as the write.
+    /// Returns whether it was stored.
+    pub fn put_block_after(&self, block: &Block, parent: Option<Hash>) -> Result<bool> {
+        self.write_blocks(std::slice::from_ref(block), Some(parent))
+    }
+
+    /// Put `block` in place of the one stored at its index, in one
+    /// transaction: the old block's hash, transaction and pruned entries go,
+    /// and the new block gets its own and a work total. The tip follows if
+    /// the old block was the tip. Blocks above are left alone, so their work
+    /// totals still count the old block.
+    pub fn replace_block(&self, block: &Block) -> Result<()> {
+        let tree = self.blocks();
+        let hash_index = self.hash_index();
+        let work = self.work();
+        let tx_index = self.tx_index();
+        let pruned = self.pruned();
+        let index = block.header.index;
+        let key = index.to_be_bytes();
+        let old = tree.get(key)?.map(|v| decode_block(&v)).transpose()?;
+        let old_hash = old.as_ref().map(Block::hash);
+        let old_tx_hashes: Vec<Hash> = old.iter().flat_map(|b| b.txs.iter().map(tx_hash)).collect();
+        let bytes = encode_block(block, self.compress)?;
+        let hash = block.hash();
+        let tx_hashes: Vec<Hash> = block.txs.iter().map(tx_hash).collect();
+        let block_work = self.work_of(block);
+
+        let result: TransactionResult<()> =
+            (&tree, &hash_index, &work, &tx_index, &pruned, &*self.db).transaction(
+                |(blocks, by_hash, work, by_tx, pruned, meta)| {
+                    if let Some(old_hash) = &old_hash {
+                        by_hash.remove(old_hash)?;
+                    }
+                    for h in &old_tx_hashes {
+                        // an identical tx in another block keeps that entry
+                        if by_tx.get(h)?.is_some_and(|k| k == key) {
+                            by_tx.remove(h)?;
+                        }
+                    }
+                    pruned.remove(&key)?;
+                    blocks.insert(&key, bytes.as_slice())?;
+                    by_hash.insert(&hash, &key)?;
+                    let parent_work = match index.checked_sub(1) {
+                        Some(below) => work.get(below.to_be_bytes())?.map(|v| decode_work(&v)),
+                        None => Some(0),
+                    };
+                    match parent_work {
+                        Some(parent_work) => {
+                            let total = parent_work.saturating_add(block_work);
+                            work.insert(&key, &total.to_be_bytes())?
+                        }
+                        None => work.remove(&key)?,
+                    };
+                    for h in &tx_hashes {
+                        by_tx.insert(h, &key)?;
+                    }
+                    let current = meta.get(KEY_TIP_HEIGHT)?.map(|v| decode_height(&v));
+                    if current.is_none_or(|h| index >= h) {
+                        meta.insert(KEY_TIP_HEIGHT, &key)?;
+                        meta.insert(KEY_TIP_HASH, &hash)?;
+                    }
+                    Ok(())
+                },
+            );
+        if let Err(e) = result {
+            return Err(anyhow::anyhow!("failed to replace block {index}: {e:?}"));
+        }
+        if !self.defer_flush {
+            self.db.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write `blocks`, skipping indices already taken. With `expect_parent`
+    /// set, the first block must instead sit on top of that parent at a
+    /// free index, or nothing is written and this returns `false`.
+    fn write_blocks(&self, blocks: &[Block], expect_parent: Option<Option<Hash>>) -> Result<bool> {
+        let (Some(first), Some(last)) = (blocks.first(), blocks.last()) else {
+            return Ok(true);
+        };
+        let tree = self.blocks();
+        let hash_index = self.hash_index();
+        let work = self.work();
+        let tx_index = self.tx_index();
+        let mut rows = Vec::with_capacity(blocks.len());
+        for block in blocks {
+            let tx_hashes: Vec<Hash> = block.txs.iter().map(tx_hash).collect();
+            rows.push((
+                block.header.index,
+                encode_block(block, self.compress)?,
+                block.hash(),
+                self.work_of(block),
+                tx_hashes,
+            ));
+        }
+
+        // Each block, its index and work entries and both tip keys are written
+        // in one transaction so a crash can never leave them disagreeing.
+        let result: TransactionResult<()> = (&tree, &hash_index, &work, &tx_index, &*self.db)
+            .transaction(|(blocks, by_hash, work, by_tx, meta)| {
+                if let (Some(parent), Some((index, ..))) = (expect_parent, rows.first()) {
+                    let below = index.checked_sub(1).map(u64::to_be_bytes);
+                    let parent_holds = match (below, parent) {
+                        (Some(below), Some(parent)) => {
+                            by_hash.get(parent)?.is_some_and(|key| key == below)
+                        }
+                        (Some(below), None) => blocks.get(below)?.is_none(),
+                        (None, _) => true,
+                    };
+                    if !parent_holds || blocks.get(index.to_be_bytes())?.is_some() {
+                        return Err(ConflictableTransactionError::Abort(()));
+                    }
+                }
+                for (index, bytes, hash, block_work, tx_hashes) in &rows {
+                    let key = index.to_be_bytes();
+                    if blocks.get(key)?.is_some() {
+                        // Block already exists, no-op
+                        tracing::debug!("Block {:?} already exists, skipping insert", hash);
+                        continue;
+                    }
+                    blocks.insert(&key, bytes.as_slice())?;
+                    by_hash.insert(hash, &key)?;
+                    // without the parent's total there is nothing to add to;
+                    // the entry waits for `fill_deferred_work`
+                    let parent_work = match index.checked_sub(1) {
+                        Some(below) => work.get(below.to_be_bytes())?.map(|v| decode_work(&v)),
+                        None => Some(0),
+                    };
+                    if let Some(parent_work) = parent_work {
+                        let total = parent_work.saturating_add(*block_work);
+                        work.insert(&key, &total.to_be_bytes())?;
+                    }
+                    for h in tx_hashes {
+                        by_tx.insert(h, &key)?;
+                    }
+
+                    // only advance the tip when the block extends the chain
+                    let current = meta.get(KEY_TIP_HEIGHT)?.map(|v| decode_height(&v));
+                    if current.is_none_or(|h| *index > h) {
+                        meta.insert(KEY_TIP_HEIGHT, &key)?;
+                        meta.insert(KEY_TIP_HASH, hash)?;
+                    }
+                }
+                Ok(())
+            });
+        if let Err(TransactionError::Abort(())) = result {
+            return Ok(false);
+        }
+        if let Err(e) = result {
+            let (first, last) = (first.header.index, last.header.index);
+            tracing::error!("Error inserting blocks {first}..={last}: {e:?}");
+            return Err(if first == last {
+                anyhow::anyhow!("failed to insert block {first}: {e:?}")
+            } else {
+                anyhow::anyhow!("failed to insert blocks {first}..={last}: {e:?}")
+            });
+        }
+        let highest = blocks.iter().map(|b| b.header.index).max().unwrap_or(0);
+        if let Some(next) = highest.checked_add(1) {
+            self.fill_deferred_work(next)?;
+        }
+
+        if !self.defer_flush {
+            self.db.flush()?;
+        }
+        Ok(true)
+    }
+
     pub fn list_blocks_range(
         &self,
         start: u64,
@@ -42,101 +947,108 @@ impl SledStore {
         desc: bool,
     ) -> anyhow::Result<Vec<ledger_core::Block>> {
         let tree = self.blocks();
-        let mut out = Vec::with_capacity(limit as usize);
+        // `limit` is caller-controlled, so grow with what is actually read
+        let mut out = Vec::new();
         if desc {
             // iterate downwards from `start`
             let start_key = start.to_be_bytes();
             for kv in tree.range(..=start_key).rev().take(limit as usize) {
                 let (_, v) = kv?;
-                out.push(bincode::deserialize(&v)?);
+                out.push(self.read_block(&v)?);
             }
         } else {
             let start_key = start.to_be_bytes();
             for kv in tree.range(start_key..).take(limit as usize) {
                 let (_, v) = kv?;
-                out.push(bincode::deserialize(&v)?);
+                out.push(self.read_block(&v)?);
             }
         }
         Ok(out)
     }
-}
-
-impl Storage for SledStore {
-    fn put_block(&self, block: &Block) -> Result<()> {
-        if self.get_block(block.header.index)?.is_some() {
-            // Block already exists, no-op
-            tracing::debug!("Block {:?} already exists, skipping insert", block.hash());
-            return Ok(());
-        }
 
+    /// Stream blocks from `from` (inclusive) upwards, or downwards when
+    /// `desc` is set, decoding and checking each only as the caller advances.
+    pub fn iter_blocks(
+        &self,
+        from: u64,
+        desc: bool,
+    ) -> impl Iterator<Item = anyhow::Result<Block>> + '_ {
         let tree = self.blocks();
-        let key = block.header.index.to_be_bytes();
-        let bytes = bincode::serialize(block)?;
-
-        if let Err(e) = tree.insert(key, bytes) {
-            tracing::error!("Error inserting Block {:?}: {:?}", key, e);
-            return Err(e.into());
-        }
-
-        // update tip
-        self.db
-            .insert(KEY_TIP_HEIGHT, &block.header.index.to_be_bytes())?;
-        self.db.insert(KEY_TIP_HASH, &block.hash())?;
-
-        self.db.flush()?;
-        Ok(())
+        let key = from.to_be_bytes();
+        let range: Box<dyn Iterator<Item = sled::Result<(IVec, IVec)>>> = if desc {
+            Box::new(tree.range(..=key).rev())
+        } else {
+            Box::new(tree.range(key..))
+        };
+        range.map(move |kv| {
+            let (_, v) = kv?;
+            self.read_block(&v)
+        })
     }
+}
 
-    fn get_block(&self, index: u64) -> Result<Option<Block>> {
+/// Implement the core ChainStore trait for our SledStore backend.
+impl ChainStore for SledStore {
+    fn put_block(&self, block: &Block) -> anyhow::Result<()> {
+        SledStore::put_blocks(self, std::slice::from_ref(block))
+    }
+    fn get_block(&self, index: u64) -> anyhow::Result<Option<Block>> {
         let tree = self.blocks();
         let key = index.to_be_bytes();
-        let opt = tree.get(key)?;
-        Ok(opt.map(|ivec: IVec| bincode::deserialize(&ivec).unwrap()))
+        tree.get(key)?.map(|v| self.read_block(&v)).transpose()
     }
-
-    fn tip_height(&self) -> Result<u64> {
+    fn put_blocks(&self, blocks: &[Block]) -> anyhow::Result<()> {
+        SledStore::put_blocks(self, blocks)
+    }
+    fn put_block_after(&self, block: &Block, parent: Option<Hash>) -> anyhow::Result<bool> {
+        SledStore::put_block_after(self, block, parent)
+    }
+    fn replace_block(&self, block: &Block) -> anyhow::Result<()> {
+        SledStore::replace_block(self, block)
+    }
+    fn get_block_by_hash(&self, hash: &Hash) -> anyhow::Result<Option<Block>> {
+        SledStore::get_block_by_hash(self, hash)
+    }
+    fn delete_block(&self, index: u64) -> anyhow::Result<()> {
+        SledStore::delete_block(self, index)
+    }
+    fn list_blocks_range(&self, start: u64, limit: u32, desc: bool) -> anyhow::Result<Vec<Block>> {
+        SledStore::list_blocks_range(self, start, limit, desc)
+    }
+    fn iter_blocks(
+        &self,
+        from: u64,
+        desc: bool,
+    ) -> Box<dyn Iterator<Item = anyhow::Result<Block>> + '_> {
+        Box::new(SledStore::iter_blocks(self, from, desc))
+    }
+    fn tip_height(&self) -> anyhow::Result<u64> {
         Ok(self
             .db
             .get(KEY_TIP_HEIGHT)?
-            .map(|v| {
-                let mut arr = [0u8; 8];
-                arr.copy_from_slice(&v);
-                u64::from_be_bytes(arr)
-            })
+            .map(|v| decode_height(&v))
             .unwrap_or(0))
     }
-
-    fn tip_hash(&self) -> Result<Option<Hash>> {
+    fn tip_hash(&self) -> anyhow::Result<Option<Hash>> {
         Ok(self.db.get(KEY_TIP_HASH)?.map(|v| {
             let mut arr = [0u8; HASH_SIZE];
             arr.copy_from_slice(&v);
             arr
         }))
     }
-
-    // Additional method to close the database
-    fn close(&self) -> Result<()> {
-        self.db.flush()?;
-        Ok(())
-    }
-}
-
-/// Implement the core ChainStore trait for our SledStore backend.
-impl ledger_core::chain::ChainStore for SledStore {
-    fn put_block(&self, block: &Block) -> anyhow::Result<()> {
-        <Self as crate::Storage>::put_block(self, block)
-    }
-    fn get_block(&self, index: u64) -> anyhow::Result<Option<Block>> {
-        <Self as crate::Storage>::get_block(self, index)
+    fn tip_work(&self) -> anyhow::Result<u128> {
+        SledStore::tip_work(self)
     }
-    fn tip_height(&self) -> anyhow::Result<u64> {
-        <Self as crate::Storage>::tip_height(self)
+    fn tx_block_index(&self, tx_hash: &Hash) -> anyhow::Result<Option<u64>> {
+        SledStore::tx_block_index(self, tx_hash)
     }
-    fn tip_hash(&self) -> anyhow::Result<Option<Hash>> {
-        <Self as crate::Storage>::tip_hash(self)
+    fn is_pruned(&self, index: u64) -> anyhow::Result<bool> {
+        SledStore::is_pruned(self, index)
     }
+    // Additional method to close the database
     fn close(&self) -> anyhow::Result<()> {
-        <Self as crate::Storage>::close(self)
+        self.db.flush()?;
+        Ok(())
     }
 }
 
@@ -156,6 +1068,60 @@ mod tests {
         assert_eq!(store2.tip_height().unwrap(), 0);
     }
 
+    /// bulk insert with and without a flush per block; deferred must persist too
+    #[test]
+    fn test_deferred_flush_bulk_insert() {
+        let insert_all = |config: SledConfig| {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let store = SledStore::open_with(temp_dir.path(), config).unwrap();
+            let mut prev = [0u8; HASH_SIZE];
+            for index in 0..1000 {
+                let block = Block {
+                    header: ledger_core::BlockHeader::new(
+                        index,
+                        prev,
+                        [0u8; HASH_SIZE],
+                        [0u8; HASH_SIZE],
+                        0,
+                    ),
+                    txs: vec![],
+                    data: None,
+                };
+                prev = block.hash();
+                store.put_block(&block).unwrap();
+            }
+            store.close().unwrap();
+            drop(store);
+            let reopened = SledStore::open(temp_dir.path()).unwrap();
+            assert_eq!(reopened.tip_height().unwrap(), 999);
+            assert_eq!(reopened.tip_hash().unwrap(), Some(prev));
+        };
+        insert_all(SledConfig::default());
+        insert_all(SledConfig {
+            defer_flush: true,
+            ..SledConfig::default()
+        });
+    }
+
+    /// a second open of a data dir in use fails with a clear error
+    #[test]
+    fn test_open_locked_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let _store = SledStore::open(temp_dir.path()).unwrap();
+        let err = SledStore::open_with_lock_timeout(temp_dir.path(), Duration::ZERO).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<StorageError>(),
+            Some(&StorageError::Locked(temp_dir.path().to_path_buf()))
+        );
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "data dir is locked by another process: {}",
+                temp_dir.path().display()
+            )
+        );
+    }
+
     /// test put/get block
     #[test]
     fn test_put_get_block() {
@@ -183,6 +1149,46 @@ mod tests {
         assert!(none.is_none());
     }
 
+    #[test]
+    fn test_iter_blocks_streams_in_order() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = SledStore::open(temp_dir.path()).unwrap();
+        for i in 0..100 {
+            let block = Block {
+                header: ledger_core::BlockHeader {
+                    index: i,
+                    previous_hash: store.tip_hash().unwrap().unwrap_or([0u8; HASH_SIZE]),
+                    data_hash: [0u8; HASH_SIZE],
+                    merkle_root: [0u8; HASH_SIZE],
+                    timestamp: 0,
+                    nonce: 0,
+                },
+                txs: vec![],
+                data: None,
+            };
+            store.put_block(&block).unwrap();
+        }
+
+        // consume one block at a time; nothing is collected up front
+        let mut expected = 0;
+        for block in store.iter_blocks(0, false) {
+            assert_eq!(block.unwrap().header.index, expected);
+            expected += 1;
+        }
+        assert_eq!(expected, 100);
+
+        let mut iter = store.iter_blocks(41, true);
+        assert_eq!(iter.next().unwrap().unwrap().header.index, 41);
+        assert_eq!(iter.next().unwrap().unwrap().header.index, 40);
+        assert_eq!(iter.count(), 40);
+
+        let from_middle: Vec<u64> = store
+            .iter_blocks(97, false)
+            .map(|b| b.unwrap().header.index)
+            .collect();
+        assert_eq!(from_middle, vec![97, 98, 99]);
+    }
+
     /// test genesis block handling
     #[test]
     fn test_genesis_block() {
@@ -367,32 +1373,38 @@ mod tests {
         assert_eq!(fetched.hash(), block.hash());
     }
 
-    /// test deleting a block (not supported, should be handled by caller)
+    /// test deleting the tip block and its index entries
     #[test]
     fn test_deleting_block() {
         let temp_dir = tempfile::tempdir().unwrap();
         let store = SledStore::open(temp_dir.path()).unwrap();
-        let block = Block {
-            header: ledger_core::BlockHeader {
-                index: 1,
-                previous_hash: [0u8; HASH_SIZE],
-                data_hash: [0u8; HASH_SIZE],
-                merkle_root: [0u8; HASH_SIZE],
-                timestamp: 0,
-                nonce: 0,
-            },
-            txs: vec![],
-            data: None,
+        let genesis = bare_block(0);
+        let tx = ledger_core::Transaction {
+            from: "Alice".to_string(),
+            to: "Bob".to_string(),
+            amount: 10,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
-        store.put_block(&block).unwrap();
+        let block = Block::new(1, genesis.hash(), vec![tx.clone()], None, 0);
+        store.put_blocks(&[genesis.clone(), block.clone()]).unwrap();
         assert_eq!(store.tip_height().unwrap(), 1);
-        // Deleting is not supported in this implementation
-        // But we can simulate by removing directly from sled (not recommended)
-        let tree = store.blocks();
-        tree.remove(1u64.to_be_bytes()).unwrap();
+        assert_eq!(store.tx_block_index(&tx_hash(&tx)).unwrap(), Some(1));
+
+        store.delete_block(1).unwrap();
         assert!(store.get_block(1).unwrap().is_none());
-        // Tip height and hash remain unchanged; application logic should handle consistency
-        assert_eq!(store.tip_height().unwrap(), 1);
+        // the tip moves back to the block below
+        assert_eq!(store.tip_height().unwrap(), 0);
+        assert_eq!(store.tip_hash().unwrap(), Some(genesis.hash()));
+        // and the block's hash and transaction index entries go with it
+        assert!(store.hash_index().get(block.hash()).unwrap().is_none());
+        assert!(store.get_block_by_hash(&block.hash()).unwrap().is_none());
+        assert_eq!(store.tx_block_index(&tx_hash(&tx)).unwrap(), None);
+        // deleting it again is a no-op
+        store.delete_block(1).unwrap();
+        assert_eq!(store.tip_hash().unwrap(), Some(genesis.hash()));
     }
 
     /// test storing and retrieving a block with transactions
@@ -405,12 +1417,18 @@ mod tests {
             to: "Bob".to_string(),
             amount: 10,
             timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         let tx2 = ledger_core::Transaction {
             from: "Bob".to_string(),
             to: "Charlie".to_string(),
             amount: 5,
             timestamp: 1_600_000_100,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         let block = Block {
             header: ledger_core::BlockHeader {
@@ -487,6 +1505,9 @@ mod tests {
                 to: format!("User{}", i + 1),
                 amount: i as u64,
                 timestamp: 1_600_000_000 + i as u64,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             };
             txs.push(tx);
         }
@@ -601,12 +1622,18 @@ mod tests {
             to: "Боб".to_string(),     // "Bob" in Russian
             amount: 10,
             timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         let tx2 = ledger_core::Transaction {
             from: "ボブ".to_string(),     // "Bob" in Japanese
             to: "チャーリー".to_string(), // "Charlie" in Japanese
             amount: 5,
             timestamp: 1_600_000_100,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         let block = Block {
             header: ledger_core::BlockHeader {
@@ -668,6 +1695,9 @@ mod tests {
                 to: format!("User{}", i + 1),
                 amount: i as u64,
                 timestamp: 1_600_000_000 + i as u64,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             };
             txs.push(tx);
         }
@@ -694,6 +1724,259 @@ mod tests {
         }
     }
 
+    /// a compressed store returns the same block and takes less disk
+    #[test]
+    fn test_compressed_round_trip() {
+        let txs: Vec<ledger_core::Transaction> = (0..10_000)
+            .map(|i| ledger_core::Transaction {
+                from: format!("{:a<1024}", i),
+                to: format!("{:b<1024}", i),
+                amount: i as u64,
+                timestamp: 1_600_000_000 + i as u64,
+                fee: 0,
+                signature: None,
+                pubkey: None,
+            })
+            .collect();
+        let mut block = bare_block(0);
+        block.header.merkle_root = ledger_core::merkle_root(&txs);
+        block.txs = txs;
+
+        let size_with = |compress: bool| {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let store = SledStore::open_with(
+                temp_dir.path(),
+                SledConfig {
+                    compress,
+                    ..SledConfig::default()
+                },
+            )
+            .unwrap();
+            store.put_block(&block).unwrap();
+            let fetched = store.get_block(0).unwrap().unwrap();
+            assert_eq!(fetched.hash(), block.hash());
+            assert_eq!(fetched.txs, block.txs);
+            let listed = store.list_blocks_range(0, 1, false).unwrap();
+            assert_eq!(listed[0].txs, block.txs);
+            store.close().unwrap();
+            store.db.size_on_disk().unwrap()
+        };
+        let (plain, compressed) = (size_with(false), size_with(true));
+        assert!(
+            compressed < plain,
+            "compressed {compressed} >= plain {plain}"
+        );
+    }
+
+    /// an interrupted format-tag backfill resumes past its cursor, over several batches
+    #[test]
+    fn test_format_tag_backfill_resumes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let count = TAG_BATCH as u64 + 5;
+        {
+            let db = sled::open(temp_dir.path()).unwrap();
+            let tree = db.open_tree(TREE_BLOCKS).unwrap();
+            for index in 0..count {
+                let mut bytes = legacy_bytes(&bare_block(index));
+                // the first three were tagged before the crash
+                if index < 3 {
+                    bytes.insert(0, FORMAT_LEGACY);
+                }
+                tree.insert(index.to_be_bytes(), bytes).unwrap();
+            }
+            db.insert(KEY_BLOCKS_TAGGING_AT, &2u64.to_be_bytes())
+                .unwrap();
+            db.flush().unwrap();
+        }
+        let store = SledStore::open(temp_dir.path()).unwrap();
+        let blocks = store.list_blocks_range(0, u32::MAX, false).unwrap();
+        assert_eq!(blocks.len() as u64, count);
+        assert!(blocks
+            .iter()
+            .all(|b| b.hash() == bare_block(b.header.index).hash()));
+        assert!(store.db.contains_key(KEY_BLOCKS_TAGGED).unwrap());
+        assert!(!store.db.contains_key(KEY_BLOCKS_TAGGING_AT).unwrap());
+    }
+
+    /// blocks written before format tags, and by either setting, all read back
+    #[test]
+    fn test_mixed_block_formats() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        {
+            let db = sled::open(temp_dir.path()).unwrap();
+            db.open_tree(TREE_BLOCKS)
+                .unwrap()
+                .insert(0u64.to_be_bytes(), legacy_bytes(&bare_block(0)))
+                .unwrap();
+            db.flush().unwrap();
+        }
+        let compressed = SledConfig {
+            compress: true,
+            ..SledConfig::default()
+        };
+        let store = SledStore::open_with(temp_dir.path(), compressed.clone()).unwrap();
+        store.put_block(&bare_block(1)).unwrap();
+        store.close().unwrap();
+        drop(store);
+
+        let store = SledStore::open(temp_dir.path()).unwrap();
+        store.put_block(&bare_block(2)).unwrap();
+        store.close().unwrap();
+        drop(store);
+
+        let store = SledStore::open_with(temp_dir.path(), compressed).unwrap();
+        let hashes: Vec<Hash> = store
+            .list_blocks_range(0, 10, false)
+            .unwrap()
+            .iter()
+            .map(Block::hash)
+            .collect();
+        assert_eq!(
+            hashes,
+            (0..3).map(|i| bare_block(i).hash()).collect::<Vec<_>>()
+        );
+        let first = store.get_block_by_hash(&hashes[0]).unwrap().unwrap();
+        assert_eq!(first.header.index, 0);
+    }
+
+    /// a rebuild that stops partway doesn't leave the index marked as built
+    #[test]
+    fn test_failed_rebuild_clears_tx_index_marker() {
+        let (_dir, store) = tampered_store(true);
+        assert!(store.db.contains_key(KEY_TX_INDEX_BUILT).unwrap());
+        assert!(store.rebuild_indexes().is_err());
+        assert!(!store.db.contains_key(KEY_TX_INDEX_BUILT).unwrap());
+    }
+
+    /// indexes wiped behind the store's back come back after a replay
+    #[test]
+    fn test_rebuild_indexes() {
+        use ledger_core::chain::Chain;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = SledStore::open(temp_dir.path()).unwrap();
+        let tx = ledger_core::Transaction {
+            from: "alice".into(),
+            to: "bob".into(),
+            amount: 5,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        };
+        let mut blocks = vec![bare_block(0), bare_block(1), bare_block(2)];
+        blocks[1].txs = vec![tx.clone()];
+        store.put_blocks(&blocks).unwrap();
+        let work = store.tip_work().unwrap();
+        // as a store from before the indexes existed would look
+        store.hash_index().clear().unwrap();
+        store.work().clear().unwrap();
+        store.tx_index().clear().unwrap();
+        assert!(store
+            .get_block_by_hash(&blocks[1].hash())
+            .unwrap()
+            .is_none());
+
+        assert_eq!(store.rebuild_indexes().unwrap(), 3);
+        let by_hash = store.get_block_by_hash(&blocks[1].hash()).unwrap().unwrap();
+        assert_eq!(by_hash.header.index, 1);
+        assert_eq!(store.tip_work().unwrap(), work);
+        let chain = Chain::new(std::sync::Arc::new(store));
+        let (index, found) = chain.get_transaction(&tx_hash(&tx)).unwrap().unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(found, tx);
+    }
+
+    /// pruned blocks lose their bodies but the chain still links up
+    #[test]
+    fn test_prune_bodies_below() {
+        use ledger_core::chain::Chain;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = std::sync::Arc::new(
+            SledStore::open(temp_dir.path())
+                .unwrap()
+                .with_verify_merkle_on_read(true),
+        );
+        let mut chain = Chain::new(store.clone());
+        chain.ensure_genesis().unwrap();
+        let txs: Vec<ledger_core::Transaction> = (1..=4)
+            .map(|amount| ledger_core::Transaction {
+                from: "alice".into(),
+                to: "bob".into(),
+                amount,
+                timestamp: 1_600_000_000,
+                fee: 0,
+                signature: None,
+                pubkey: None,
+            })
+            .collect();
+        for tx in &txs {
+            chain
+                .mine_with_txs_parallel(vec![tx.clone()], Some("body".into()), 1)
+                .unwrap();
+        }
+        let hashes: Vec<Hash> = (0..=4)
+            .map(|i| store.get_block(i).unwrap().unwrap().hash())
+            .collect();
+
+        assert_eq!(store.prune_bodies_below(3).unwrap(), 3);
+        for i in 0..3 {
+            let stub = store.get_block(i).unwrap().unwrap();
+            assert!(stub.txs.is_empty() && stub.data.is_none());
+            assert_eq!(stub.hash(), hashes[i as usize]);
+            assert!(store.is_pruned(i).unwrap());
+        }
+        assert_eq!(store.get_block(3).unwrap().unwrap().txs.len(), 1);
+        assert!(!store.is_pruned(3).unwrap());
+        assert_eq!(chain.validate_range(0, 4, 1), Ok(()));
+        // pruned txs leave the index; pruning again is a no-op
+        assert_eq!(chain.get_transaction(&tx_hash(&txs[0])).unwrap(), None);
+        let (index, _) = chain.get_transaction(&tx_hash(&txs[2])).unwrap().unwrap();
+        assert_eq!(index, 3);
+        assert_eq!(store.prune_bodies_below(3).unwrap(), 0);
+        // the pruned transfers (1 and 2) are kept as balances
+        let balances = store.pruned_balances().unwrap();
+        assert_eq!(balances.balance_of("alice"), -3);
+        assert_eq!(balances.balance_of("bob"), 3);
+        // stubs are neither exported nor reported as corrupt
+        let mut out = Vec::new();
+        assert_eq!(chain.export_ndjson(&mut out, 0, 4).unwrap(), 2);
+        let report = ledger_core::audit::full_audit(&chain, 1).unwrap();
+        assert!(report.is_clean(), "{:?}", report.issues);
+        assert_eq!(report.blocks_pruned, 3);
+        // the tip keeps its body whatever `below` says
+        assert_eq!(store.prune_bodies_below(100).unwrap(), 1);
+        assert_eq!(store.get_block(4).unwrap().unwrap().txs.len(), 1);
+        assert_eq!(store.pruned_balances().unwrap().balance_of("bob"), 6);
+    }
+
+    /// pending txs round-trip in sequence order and can be forgotten
+    #[test]
+    fn test_pending_txs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = SledStore::open(temp_dir.path()).unwrap();
+        let tx = |amount| ledger_core::Transaction {
+            from: "alice".into(),
+            to: "bob".into(),
+            amount,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        };
+        store.put_pending_txs([(7, &tx(1)), (3, &tx(2))]).unwrap();
+        store.put_pending_txs([(5, &tx(3))]).unwrap();
+        store.remove_pending_txs(&[tx(3), tx(99)]).unwrap();
+        store.close().unwrap();
+        drop(store);
+
+        let store = SledStore::open(temp_dir.path()).unwrap();
+        assert_eq!(store.pending_txs().unwrap(), vec![(3, tx(2)), (7, tx(1))]);
+        store.replace_pending_txs([(0, &tx(4))]).unwrap();
+        assert_eq!(store.pending_txs().unwrap(), vec![(0, tx(4))]);
+    }
+
     /// test storing and retrieving blocks with duplicate transactions
     #[test]
     fn test_blocks_with_duplicate_transactions() {
@@ -704,6 +1987,9 @@ mod tests {
             to: "Bob".to_string(),
             amount: 10,
             timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         };
         let block = Block {
             header: ledger_core::BlockHeader {
@@ -832,6 +2118,9 @@ mod tests {
                 to: format!("User{}", i + 1),
                 amount: i as u64,
                 timestamp: 1_600_000_000 + i as u64,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             };
             txs.push(tx);
         }
@@ -870,6 +2159,9 @@ mod tests {
                 to: format!("User{}", i + 1),
                 amount: i as u64,
                 timestamp: 1_600_000_000 + i as u64,
+                fee: 0,
+                signature: None,
+                pubkey: None,
             };
             txs.push(tx);
         }
@@ -943,4 +2235,275 @@ mod tests {
         assert_eq!(store.tip_height().unwrap(), 2);
         assert_eq!(store.tip_hash().unwrap().unwrap(), block2.hash());
     }
+
+    fn tampered_store(verify: bool) -> (tempfile::TempDir, SledStore) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = SledStore::open(temp_dir.path())
+            .unwrap()
+            .with_verify_merkle_on_read(verify);
+        let tx = ledger_core::Transaction {
+            from: "alice".to_string(),
+            to: "bob".to_string(),
+            amount: 10,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        };
+        let mut block = Block {
+            header: ledger_core::BlockHeader {
+                index: 1,
+                previous_hash: [0u8; HASH_SIZE],
+                data_hash: [0u8; HASH_SIZE],
+                merkle_root: ledger_core::merkle_root(std::slice::from_ref(&tx)),
+                timestamp: 0,
+                nonce: 0,
+            },
+            txs: vec![tx],
+            data: None,
+        };
+        store.put_block(&block).unwrap();
+        // rewrite the stored txs behind the header's back
+        block.txs[0].amount = 1_000_000;
+        store
+            .blocks()
+            .insert(1u64.to_be_bytes(), encode_block(&block, false).unwrap())
+            .unwrap();
+        (temp_dir, store)
+    }
+
+    #[test]
+    fn test_verify_merkle_on_read_rejects_tampered_txs() {
+        let (_dir, store) = tampered_store(true);
+        let err = store.get_block(1).unwrap_err();
+        match err.downcast_ref::<StorageError>() {
+            Some(StorageError::MerkleMismatch { index, .. }) => assert_eq!(*index, 1),
+            other => panic!("expected MerkleMismatch, got {other:?}"),
+        }
+        // range reads check each block too
+        assert!(store.list_blocks_range(0, 10, false).is_err());
+        assert!(store.iter_blocks(0, false).any(|b| b.is_err()));
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_verify_merkle_on_read_uses_the_chain_algo() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = SledStore::open(temp_dir.path())
+            .unwrap()
+            .with_verify_merkle_on_read(true)
+            .with_hash_algo(HashAlgo::Blake3);
+        let tx = ledger_core::Transaction {
+            from: "alice".to_string(),
+            to: "bob".to_string(),
+            amount: 10,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        };
+        let mut block = Block::new(1, [0u8; HASH_SIZE], vec![tx], None, 0);
+        block.header.merkle_root = HashAlgo::Blake3.merkle_root(&block.txs);
+        store.put_block(&block).unwrap();
+        assert!(store.get_block(1).unwrap().is_some());
+
+        block.txs[0].amount = 1_000_000;
+        store
+            .blocks()
+            .insert(1u64.to_be_bytes(), encode_block(&block, false).unwrap())
+            .unwrap();
+        let err = store.get_block(1).unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<StorageError>(),
+                Some(StorageError::MerkleMismatch { index: 1, .. })
+            ),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_unverified_read_returns_tampered_block() {
+        let (_dir, store) = tampered_store(false);
+        let block = store.get_block(1).unwrap().unwrap();
+        assert_eq!(block.txs[0].amount, 1_000_000);
+    }
+
+    /// `block` as the bare bincode stores held before format tags and
+    /// transaction fees, dropping any fee or signature.
+    fn legacy_bytes(block: &Block) -> Vec<u8> {
+        let legacy = LegacyBlock {
+            header: block.header,
+            data: block.data.clone(),
+            txs: block
+                .txs
+                .iter()
+                .map(|tx| LegacyTransaction {
+                    from: tx.from.clone(),
+                    to: tx.to.clone(),
+                    amount: tx.amount,
+                    timestamp: tx.timestamp,
+                })
+                .collect(),
+        };
+        bincode::serialize(&legacy).unwrap()
+    }
+
+    /// a store written before transactions had fees and signatures opens,
+    /// and its transactions read back with none
+    #[test]
+    fn test_opens_store_with_legacy_transactions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let tx = |to: &str, amount| Transaction {
+            from: "alice".into(),
+            to: to.into(),
+            amount,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        };
+        let block = |index, previous_hash, txs| {
+            let mut block = Block::new(index, previous_hash, txs, None, 0);
+            block.header.timestamp = 1_600_000_000 + index;
+            block
+        };
+        let genesis = block(0, [0u8; HASH_SIZE], vec![]);
+        let next = block(1, genesis.hash(), vec![tx("bob", 5), tx("carol", 7)]);
+        {
+            // the layout and keys of the original store
+            let db = sled::open(temp_dir.path()).unwrap();
+            let tree = db.open_tree(TREE_BLOCKS).unwrap();
+            for block in [&genesis, &next] {
+                tree.insert(block.header.index.to_be_bytes(), legacy_bytes(block))
+                    .unwrap();
+            }
+            db.insert(KEY_TIP_HEIGHT, &1u64.to_be_bytes()).unwrap();
+            db.insert(KEY_TIP_HASH, &next.hash()).unwrap();
+            db.flush().unwrap();
+        }
+        let store = SledStore::open(temp_dir.path()).unwrap();
+        let got = store.get_block(1).unwrap().unwrap();
+        assert_eq!(got.hash(), next.hash());
+        assert_eq!(got.txs, next.txs);
+        assert_eq!(
+            store.tx_block_index(&tx_hash(&next.txs[1])).unwrap(),
+            Some(1)
+        );
+        // new blocks are written in the current layout next to the old ones
+        let paid = Transaction {
+            fee: 3,
+            ..tx("dave", 1)
+        };
+        let current = block(2, next.hash(), vec![paid]);
+        store.put_block(&current).unwrap();
+        assert_eq!(
+            store.blocks().get(2u64.to_be_bytes()).unwrap().unwrap()[0],
+            FORMAT_RAW
+        );
+        assert_eq!(store.get_block(2).unwrap().unwrap().txs, current.txs);
+    }
+
+    fn bare_block(index: u64) -> Block {
+        Block {
+            header: ledger_core::BlockHeader {
+                index,
+                previous_hash: [0u8; HASH_SIZE],
+                data_hash: [0u8; HASH_SIZE],
+                merkle_root: [0u8; HASH_SIZE],
+                timestamp: 0,
+                nonce: 0,
+            },
+            txs: vec![],
+            data: None,
+        }
+    }
+
+    /// work past a gap waits for the gap to be filled rather than restarting at 0
+    #[test]
+    fn test_work_waits_for_missing_parent() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = SledStore::open(temp_dir.path())
+            .unwrap()
+            .with_hash_algo(HashAlgo::Sha256);
+        let blocks = [bare_block(0), bare_block(1), bare_block(2)];
+        store
+            .put_blocks(&[blocks[0].clone(), blocks[2].clone()])
+            .unwrap();
+        assert_eq!(store.tip_work().unwrap(), 0);
+
+        store.put_block(&blocks[1]).unwrap();
+        let total: u128 = blocks.iter().map(|b| block_work(&b.hash())).sum();
+        assert_eq!(store.tip_work().unwrap(), total);
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_work_uses_the_chain_algo() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = SledStore::open(temp_dir.path())
+            .unwrap()
+            .with_hash_algo(HashAlgo::Blake3);
+        let block = bare_block(0);
+        store.put_block(&block).unwrap();
+        let work = block_work(&HashAlgo::Blake3.header_hash(block.header));
+        assert_eq!(store.tip_work().unwrap(), work);
+        assert_eq!(store.rebuild_indexes().unwrap(), 1);
+        assert_eq!(store.tip_work().unwrap(), work);
+    }
+
+    /// a block below the tip is stored but does not move the tip back
+    #[test]
+    fn test_lower_index_does_not_move_tip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = SledStore::open(temp_dir.path()).unwrap();
+        let block5 = bare_block(5);
+        store.put_block(&block5).unwrap();
+        store.put_block(&bare_block(3)).unwrap();
+        assert_eq!(store.tip_height().unwrap(), 5);
+        assert_eq!(store.tip_hash().unwrap().unwrap(), block5.hash());
+        assert!(store.get_block(3).unwrap().is_some());
+    }
+
+    /// stores written before the hash index existed are backfilled on open
+    #[test]
+    fn test_hash_index_backfilled_on_open() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let block = bare_block(1);
+        {
+            let store = SledStore::open(temp_dir.path()).unwrap();
+            store.put_block(&block).unwrap();
+            store.db.drop_tree(TREE_HASH_INDEX).unwrap();
+            assert!(store.get_block_by_hash(&block.hash()).unwrap().is_none());
+        }
+        let store = SledStore::open(temp_dir.path()).unwrap();
+        let found = store.get_block_by_hash(&block.hash()).unwrap().unwrap();
+        assert_eq!(found.header.index, 1);
+    }
+
+    /// deleting the tip moves it down to the highest remaining block
+    #[test]
+    fn test_delete_block_recomputes_tip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = SledStore::open(temp_dir.path()).unwrap();
+        let (b1, b2, b3) = (bare_block(1), bare_block(2), bare_block(3));
+        for b in [&b1, &b2, &b3] {
+            store.put_block(b).unwrap();
+        }
+
+        // a non-tip delete leaves the tip alone
+        store.delete_block(2).unwrap();
+        assert_eq!(store.tip_height().unwrap(), 3);
+        assert!(store.get_block(2).unwrap().is_none());
+
+        store.delete_block(3).unwrap();
+        assert_eq!(store.tip_height().unwrap(), 1);
+        assert_eq!(store.tip_hash().unwrap(), Some(b1.hash()));
+        assert!(store.get_block_by_hash(&b3.hash()).unwrap().is_none());
+
+        store.delete_block(1).unwrap();
+        assert_eq!(store.tip_hash().unwrap(), None);
+        // deleting a missing block is a no-op
+        store.delete_block(7).unwrap();
+    }
 }
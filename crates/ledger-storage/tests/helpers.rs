@@ -1,5 +1,6 @@
 use std::fs;
 
+use ledger_core::digest::HashAlgo;
 use ledger_storage::sled_store::SledStore;
 use tempfile::{tempdir, TempDir};
 
@@ -25,7 +26,10 @@ pub fn create_temp_store() -> (TempDir, SledStore) {
     // Initialize the SledStore
     (
         temp_dir,
-        SledStore::open(db_path.to_str().unwrap()).expect("Failed to open SledStore"),
+        // the tests write SHA-256 blocks, whatever the `blake3` feature defaults to
+        SledStore::open(db_path.to_str().unwrap())
+            .expect("Failed to open SledStore")
+            .with_hash_algo(HashAlgo::Sha256),
     )
 }
 
@@ -1,16 +1,37 @@
+//! Storage behaviour checks. Most are written once, generic over the store,
+//! and `backend_tests!` runs them against both `SledStore` and `MemStore`;
+//! the rest reopen or corrupt a sled database and stay sled-only.
+
 pub mod helpers;
-use ledger_core::{block_data_hash, constants::HASH_SIZE, Block, Transaction};
+use ledger_core::{
+    block_data_hash, chain::ChainStore, constants::HASH_SIZE, Block, Hash, Transaction,
+};
 use ledger_storage::sled_store::SledStore;
-use ledger_storage::Storage;
 use rand::Rng;
 use std::any::Any;
+use std::sync::Arc;
 
-use crate::helpers::{create_temp_dir, create_temp_store, remove_temp_dir, teardown_store};
+use crate::helpers::{create_temp_dir, remove_temp_dir};
 
-#[tokio::test]
-async fn test_storage_integration() -> anyhow::Result<()> {
-    // create a temporary sled database
-    let (temp_dir, store) = create_temp_store();
+fn block(index: u64, previous_hash: [u8; HASH_SIZE], txs: Vec<Transaction>) -> Block {
+    let mut block = Block::new(index, previous_hash, txs, None, 0);
+    block.header.timestamp = 1_600_000_000 + index;
+    block
+}
+
+/// Store `n` linked blocks starting at genesis and return them.
+fn linked_chain<S: ChainStore>(store: &S, n: u64) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    for i in 0..n {
+        let prev = blocks.last().map(Block::hash).unwrap_or([0u8; HASH_SIZE]);
+        let b = block(i, prev, vec![]);
+        ChainStore::put_block(store, &b).unwrap();
+        blocks.push(b);
+    }
+    blocks
+}
+
+async fn test_storage_integration<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
     // Test data
     let mut rng = rand::thread_rng();
     let num_blocks = 100;
@@ -57,8 +78,6 @@ async fn test_storage_integration() -> anyhow::Result<()> {
     let tip_hash = store.tip_hash()?.expect("Tip hash should exist");
     assert_eq!(tip_height, num_blocks as u64 - 1);
     assert_eq!(tip_hash, blocks.last().unwrap().hash());
-    // Cleanup
-    teardown_store(temp_dir, store);
     Ok(())
 }
 
@@ -101,10 +120,7 @@ async fn test_storage_persistence() -> anyhow::Result<()> {
     Ok(())
 }
 
-#[tokio::test]
-async fn test_storage_edge_cases() -> anyhow::Result<()> {
-    // create a temporary sled database
-    let (temp_dir, store) = create_temp_store();
+async fn test_storage_edge_cases<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
     // Test empty block storage
     let header =
         ledger_core::BlockHeader::new(0, [0u8; HASH_SIZE], [0u8; HASH_SIZE], [0u8; HASH_SIZE], 0);
@@ -123,6 +139,9 @@ async fn test_storage_edge_cases() -> anyhow::Result<()> {
             to: format!("addr_to_{}", i),
             amount: i as u64,
             timestamp: 1_600_000_000 + i as u64,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         })
         .collect();
     let header =
@@ -136,19 +155,11 @@ async fn test_storage_edge_cases() -> anyhow::Result<()> {
     let retrieved_large_block = store.get_block(1)?.expect("Large block should exist");
     assert_eq!(retrieved_large_block.txs.len(), large_txs.len());
     assert_eq!(retrieved_large_block.txs, large_txs);
-    // Cleanup
-    teardown_store(temp_dir, store);
     Ok(())
 }
 
-#[tokio::test]
-async fn test_storage_concurrency() -> anyhow::Result<()> {
-    use std::sync::Arc;
+async fn test_storage_concurrency<S: ChainStore + 'static>(store: Arc<S>) -> anyhow::Result<()> {
     use tokio::task;
-    // Create a temporary directory for the sled database
-    let (temp_dir, db_path) = create_temp_dir();
-    // Initialize the SledStore
-    let store = Arc::new(SledStore::open(db_path.to_str().unwrap())?);
     let num_blocks = 50;
     let mut handles = Vec::new();
     // Concurrently add blocks
@@ -188,8 +199,6 @@ async fn test_storage_concurrency() -> anyhow::Result<()> {
         let retrieved_block = store.get_block(i as u64)?.expect("Block should exist");
         assert_eq!(retrieved_block.header.index, i as u64);
     }
-    // Cleanup
-    remove_temp_dir(temp_dir);
     Ok(())
 }
 
@@ -252,23 +261,28 @@ async fn test_storage_data_integrity() -> anyhow::Result<()> {
     Ok(())
 }
 
-#[tokio::test]
-async fn test_storage_trait_compliance() -> anyhow::Result<()> {
-    // create a temporary sled database
-    let (temp_dir, store) = create_temp_store();
-    // Verify that SledStore implements the Storage trait
-    fn assert_storage_trait<T: Storage>() {}
-    assert_storage_trait::<SledStore>();
-    // Cleanup
-    // Cleanup
-    teardown_store(temp_dir, store);
+/// Code still bound on the deprecated `Storage` trait accepts any `ChainStore`.
+#[allow(deprecated)]
+async fn test_storage_trait_compliance<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
+    use ledger_storage::Storage;
+
+    fn tip_via_storage<T: Storage + ?Sized>(store: &T) -> (u64, Option<Hash>) {
+        (store.tip_height().unwrap(), store.tip_hash().unwrap())
+    }
+
+    let store = &*store;
+    let blocks = linked_chain(store, 3);
+    Storage::put_block(store, &block(3, blocks[2].hash(), vec![]))?;
+    let (height, hash) = tip_via_storage(store);
+    assert_eq!(height, 3);
+    assert_eq!(hash, ChainStore::tip_hash(store)?);
+    assert!(Storage::get_block(store, 3)?.is_some());
+    let dyn_store: &dyn ChainStore = store;
+    assert_eq!(tip_via_storage(dyn_store).0, 3);
     Ok(())
 }
 
-#[tokio::test]
-async fn test_storage_cleanup() -> anyhow::Result<()> {
-    // create a temporary sled database
-    let (temp_dir, store) = create_temp_store();
+async fn test_storage_cleanup<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
     // Add a block
     let header =
         ledger_core::BlockHeader::new(0, [0u8; HASH_SIZE], [0u8; HASH_SIZE], [0u8; HASH_SIZE], 0);
@@ -281,15 +295,10 @@ async fn test_storage_cleanup() -> anyhow::Result<()> {
     // Verify the block exists
     let retrieved_block = store.get_block(0)?.expect("Block should exist");
     assert_eq!(retrieved_block.header.index, 0);
-    // Cleanup
-    teardown_store(temp_dir, store);
     Ok(())
 }
 
-#[tokio::test]
-async fn test_storage_large_blockchain() -> anyhow::Result<()> {
-    // create a temporary sled database
-    let (temp_dir, store) = create_temp_store();
+async fn test_storage_large_blockchain<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
     // Test data
     let mut rng = rand::thread_rng();
     let num_blocks = 10000;
@@ -335,15 +344,10 @@ async fn test_storage_large_blockchain() -> anyhow::Result<()> {
     let tip_hash = store.tip_hash()?.expect("Tip hash should exist");
     assert_eq!(tip_height, num_blocks as u64 - 1);
     assert_eq!(tip_hash, blocks.last().unwrap().hash());
-    // Cleanup
-    teardown_store(temp_dir, store);
     Ok(())
 }
 
-#[tokio::test]
-async fn test_storage_empty_database() -> anyhow::Result<()> {
-    // create a temporary sled database
-    let (temp_dir, store) = create_temp_store();
+async fn test_storage_empty_database<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
     // Verify that the database is empty
     let tip_height = store.tip_height()?;
     let tip_hash = store.tip_hash()?;
@@ -357,20 +361,13 @@ async fn test_storage_empty_database() -> anyhow::Result<()> {
         block.is_none(),
         "No blocks should exist in an empty database"
     );
-    // Cleanup
-    teardown_store(temp_dir, store);
     Ok(())
 }
 
-#[tokio::test]
-async fn test_storage_non_existent_block() -> anyhow::Result<()> {
-    // create a temporary sled database
-    let (temp_dir, store) = create_temp_store();
+async fn test_storage_non_existent_block<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
     // Attempt to retrieve a non-existent block
     let block = store.get_block(9999)?;
     assert!(block.is_none(), "Block should not exist");
-    // Cleanup
-    teardown_store(temp_dir, store);
     Ok(())
 }
 
@@ -408,10 +405,7 @@ async fn test_storage_repeated_open_close() -> anyhow::Result<()> {
     Ok(())
 }
 
-#[tokio::test]
-async fn test_storage_large_transactions() -> anyhow::Result<()> {
-    // create a temporary sled database
-    let (temp_dir, store) = create_temp_store();
+async fn test_storage_large_transactions<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
     // Create a block with large transactions
     let large_txs: Vec<Transaction> = (0..1000)
         .map(|i| Transaction {
@@ -419,6 +413,9 @@ async fn test_storage_large_transactions() -> anyhow::Result<()> {
             to: "b".repeat(1000) + &i.to_string(),
             amount: i as u64,
             timestamp: 1_600_000_000 + i as u64,
+            fee: 0,
+            signature: None,
+            pubkey: None,
         })
         .collect();
     let header =
@@ -433,15 +430,10 @@ async fn test_storage_large_transactions() -> anyhow::Result<()> {
     let retrieved_block = store.get_block(0)?.expect("Block should exist");
     assert_eq!(retrieved_block.txs.len(), large_txs.len());
     assert_eq!(retrieved_block.txs, large_txs);
-    // Cleanup
-    teardown_store(temp_dir, store);
     Ok(())
 }
 
-#[tokio::test]
-async fn test_storage_multiple_tips() -> anyhow::Result<()> {
-    // create a temporary sled database
-    let (temp_dir, store) = create_temp_store();
+async fn test_storage_multiple_tips<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
     // Add multiple blocks
     let mut prev_hash = [0u8; HASH_SIZE];
     for i in 0..5 {
@@ -465,20 +457,11 @@ async fn test_storage_multiple_tips() -> anyhow::Result<()> {
     let tip_hash = store.tip_hash()?.expect("Tip hash should exist");
     assert_eq!(tip_height, 4);
     assert_eq!(tip_hash, prev_hash);
-    // Cleanup
-    teardown_store(temp_dir, store);
     Ok(())
 }
 
-#[tokio::test]
-async fn test_storage_stress() -> anyhow::Result<()> {
-    use std::sync::Arc;
+async fn test_storage_stress<S: ChainStore + 'static>(store: Arc<S>) -> anyhow::Result<()> {
     use tokio::task;
-    // Create a temporary directory for the sled database
-    let (temp_dir, db_path) = create_temp_dir();
-
-    // Initialize the SledStore
-    let store = Arc::new(SledStore::open(db_path.to_str().unwrap())?);
     let num_blocks = 1000;
     let mut handles = Vec::new();
     // Concurrently add blocks
@@ -518,8 +501,6 @@ async fn test_storage_stress() -> anyhow::Result<()> {
         let retrieved_block = store.get_block(i as u64)?.expect("Block should exist");
         assert_eq!(retrieved_block.header.index, i as u64);
     }
-    // Cleanup
-    remove_temp_dir(temp_dir);
     Ok(())
 }
 
@@ -560,3 +541,375 @@ async fn test_storage_reindex() -> anyhow::Result<()> {
     remove_temp_dir(temp_dir);
     Ok(())
 }
+
+async fn test_storage_get_block_by_hash<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
+    use ledger_core::{chain::Chain, digest::HashAlgo};
+
+    let mut chain = Chain::new(store.clone()).with_hash_algo(HashAlgo::Sha256);
+    chain.ensure_genesis()?;
+    let (mined, hash) = chain.mine_with_txs_parallel(vec![], Some("by hash".into()), 1)?;
+
+    let found = ChainStore::get_block_by_hash(&*store, &hash)?.expect("block should be indexed");
+    assert_eq!(found.header.index, mined.header.index);
+    assert_eq!(found.hash(), hash);
+    assert!(store.get_block_by_hash(&[0xAB; HASH_SIZE])?.is_none());
+    Ok(())
+}
+
+async fn test_storage_get_transaction<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
+    use ledger_core::chain::Chain;
+    use ledger_core::digest::HashAlgo;
+    use ledger_core::tx_hash;
+
+    let mut chain = Chain::new(store.clone()).with_hash_algo(HashAlgo::Sha256);
+    chain.ensure_genesis()?;
+    let tx = |to: &str, amount| Transaction {
+        from: "alice".into(),
+        to: to.into(),
+        amount,
+        timestamp: 1_600_000_000,
+        fee: 0,
+        signature: None,
+        pubkey: None,
+    };
+    chain.mine_with_txs_parallel(vec![tx("bob", 1)], None, 1)?;
+    let (mined, _) = chain.mine_with_txs_parallel(vec![tx("carol", 2), tx("dave", 3)], None, 1)?;
+
+    let wanted = &mined.txs[1];
+    let (index, found) = chain
+        .get_transaction(&tx_hash(wanted))?
+        .expect("tx should be indexed");
+    assert_eq!(index, mined.header.index);
+    assert_eq!(&found, wanted);
+    assert!(chain.get_transaction(&[0xAB; HASH_SIZE])?.is_none());
+
+    // deleting the block drops its transactions from the index
+    ChainStore::delete_block(&*store, mined.header.index)?;
+    assert!(chain.get_transaction(&tx_hash(wanted))?.is_none());
+    Ok(())
+}
+
+async fn test_storage_duplicate_index_is_noop<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
+    let store = &*store;
+    let first = block(1, [0u8; HASH_SIZE], vec![]);
+    let second = block(1, [1u8; HASH_SIZE], vec![]);
+    ChainStore::put_block(store, &first).unwrap();
+    ChainStore::put_block(store, &second).unwrap();
+    let got = ChainStore::get_block(store, 1).unwrap().unwrap();
+    assert_eq!(got.hash(), first.hash());
+    assert_eq!(ChainStore::tip_hash(store).unwrap(), Some(first.hash()));
+    Ok(())
+}
+
+async fn test_storage_lower_index_keeps_tip<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
+    let store = &*store;
+    let five = block(5, [0u8; HASH_SIZE], vec![]);
+    ChainStore::put_block(store, &five).unwrap();
+    ChainStore::put_block(store, &block(3, [0u8; HASH_SIZE], vec![])).unwrap();
+    assert_eq!(ChainStore::tip_height(store).unwrap(), 5);
+    assert_eq!(ChainStore::tip_hash(store).unwrap(), Some(five.hash()));
+    Ok(())
+}
+
+async fn test_storage_list_blocks_range<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
+    let store = &*store;
+    linked_chain(store, 10);
+    let indices = |blocks: Vec<Block>| blocks.iter().map(|b| b.header.index).collect::<Vec<_>>();
+    assert_eq!(
+        indices(ChainStore::list_blocks_range(store, 9, 3, true).unwrap()),
+        vec![9, 8, 7]
+    );
+    assert_eq!(
+        indices(ChainStore::list_blocks_range(store, 2, 3, false).unwrap()),
+        vec![2, 3, 4]
+    );
+    assert_eq!(
+        indices(ChainStore::list_blocks_range(store, 1, 5, true).unwrap()),
+        vec![1, 0]
+    );
+    assert_eq!(
+        indices(ChainStore::list_blocks_range(store, 8, 5, false).unwrap()),
+        vec![8, 9]
+    );
+    assert_eq!(
+        indices(ChainStore::list_blocks_range(store, 50, 3, true).unwrap()),
+        vec![9, 8, 7]
+    );
+    assert!(ChainStore::list_blocks_range(store, 50, 3, false)
+        .unwrap()
+        .is_empty());
+    assert!(ChainStore::list_blocks_range(store, 5, 0, true)
+        .unwrap()
+        .is_empty());
+    // a limit far past the chain is capped, not allocated up front
+    assert_eq!(
+        ChainStore::list_blocks_range(store, 0, u32::MAX, false)
+            .unwrap()
+            .len(),
+        10
+    );
+    Ok(())
+}
+
+/// Forwards everything except `list_blocks_range` and `iter_blocks`, so the
+/// trait defaults run.
+struct DefaultRange<'a, S>(&'a S);
+
+impl<S: ChainStore> ChainStore for DefaultRange<'_, S> {
+    fn put_block(&self, block: &Block) -> anyhow::Result<()> {
+        ChainStore::put_block(self.0, block)
+    }
+    fn get_block(&self, index: u64) -> anyhow::Result<Option<Block>> {
+        ChainStore::get_block(self.0, index)
+    }
+    fn tip_height(&self) -> anyhow::Result<u64> {
+        ChainStore::tip_height(self.0)
+    }
+    fn tip_hash(&self) -> anyhow::Result<Option<Hash>> {
+        ChainStore::tip_hash(self.0)
+    }
+    fn delete_block(&self, index: u64) -> anyhow::Result<()> {
+        ChainStore::delete_block(self.0, index)
+    }
+    fn close(&self) -> anyhow::Result<()> {
+        ChainStore::close(self.0)
+    }
+}
+
+async fn test_storage_default_list_blocks_range_matches<S: ChainStore>(
+    store: Arc<S>,
+) -> anyhow::Result<()> {
+    let store = &*store;
+    linked_chain(store, 10);
+    // leave a hole so both implementations have to skip it
+    ChainStore::delete_block(store, 4).unwrap();
+    let hashes = |blocks: Vec<Block>| blocks.iter().map(Block::hash).collect::<Vec<_>>();
+    let fallback = DefaultRange(store);
+    for start in [0, 3, 4, 5, 9, 50] {
+        for limit in [0, 1, 3, 20] {
+            for desc in [true, false] {
+                assert_eq!(
+                    hashes(ChainStore::list_blocks_range(store, start, limit, desc).unwrap()),
+                    hashes(fallback.list_blocks_range(start, limit, desc).unwrap()),
+                    "start={start} limit={limit} desc={desc}"
+                );
+            }
+        }
+        for desc in [true, false] {
+            let streamed = |blocks: Box<dyn Iterator<Item = anyhow::Result<Block>> + '_>| {
+                hashes(blocks.map(Result::unwrap).collect())
+            };
+            assert_eq!(
+                streamed(ChainStore::iter_blocks(store, start, desc)),
+                streamed(fallback.iter_blocks(start, desc)),
+                "iter_blocks start={start} desc={desc}"
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn test_storage_delete_block_moves_tip<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
+    let store = &*store;
+    let blocks = linked_chain(store, 4);
+    ChainStore::delete_block(store, 3).unwrap();
+    assert_eq!(ChainStore::tip_height(store).unwrap(), 2);
+    assert_eq!(ChainStore::tip_hash(store).unwrap(), Some(blocks[2].hash()));
+    assert!(ChainStore::get_block_by_hash(store, &blocks[3].hash())
+        .unwrap()
+        .is_none());
+    // a non-tip delete leaves the tip alone
+    ChainStore::delete_block(store, 1).unwrap();
+    assert_eq!(ChainStore::tip_height(store).unwrap(), 2);
+    ChainStore::delete_block(store, 99).unwrap();
+    Ok(())
+}
+
+async fn test_storage_put_blocks_batch<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
+    let store = &*store;
+    let mut blocks: Vec<Block> = Vec::new();
+    for i in 0..500 {
+        let prev = blocks.last().map(Block::hash).unwrap_or([0u8; HASH_SIZE]);
+        blocks.push(block(i, prev, vec![]));
+    }
+    ChainStore::put_blocks(store, &blocks).unwrap();
+    assert_eq!(ChainStore::tip_height(store).unwrap(), 499);
+    assert_eq!(
+        ChainStore::tip_hash(store).unwrap(),
+        Some(blocks[499].hash())
+    );
+    let middle = rand::random::<u64>() % 500;
+    let got = ChainStore::get_block(store, middle).unwrap().unwrap();
+    assert_eq!(got.hash(), blocks[middle as usize].hash());
+    assert_eq!(
+        ChainStore::get_block_by_hash(store, &blocks[middle as usize].hash())
+            .unwrap()
+            .map(|b| b.header.index),
+        Some(middle)
+    );
+    // stored indices are skipped, and an empty batch is a no-op
+    ChainStore::put_blocks(store, &[block(3, [9u8; HASH_SIZE], vec![])]).unwrap();
+    assert_eq!(
+        ChainStore::get_block(store, 3).unwrap().unwrap().hash(),
+        blocks[3].hash()
+    );
+    ChainStore::put_blocks(store, &[]).unwrap();
+    Ok(())
+}
+
+async fn test_storage_tip_work_sums_block_work<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
+    let store = &*store;
+    use ledger_core::{mine::mine_block_parallel, pow::block_work};
+    assert_eq!(ChainStore::tip_work(store).unwrap(), 0);
+    let mut prev = [0u8; HASH_SIZE];
+    let mut works = Vec::new();
+    for i in 0..3 {
+        let (b, hash) = mine_block_parallel(i, prev, vec![], None, 8).unwrap();
+        ChainStore::put_block(store, &b).unwrap();
+        works.push(block_work(&hash));
+        prev = hash;
+    }
+    assert!(works.iter().all(|w| *w >= 1 << 8));
+    assert_eq!(
+        ChainStore::tip_work(store).unwrap(),
+        works.iter().sum::<u128>()
+    );
+    ChainStore::delete_block(store, 2).unwrap();
+    assert_eq!(ChainStore::tip_work(store).unwrap(), works[0] + works[1]);
+    Ok(())
+}
+
+async fn test_storage_replace_block<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
+    use ledger_core::{pow::block_work, tx_hash};
+    let store = &*store;
+    let tx = |to: &str| Transaction {
+        from: "alice".into(),
+        to: to.into(),
+        amount: 1,
+        timestamp: 1_600_000_000,
+        fee: 0,
+        signature: None,
+        pubkey: None,
+    };
+    let mut blocks = linked_chain(store, 2);
+    let old = block(2, blocks[1].hash(), vec![tx("bob")]);
+    store.put_block(&old)?;
+    blocks.push(old.clone());
+
+    let rival = block(2, blocks[1].hash(), vec![tx("carol")]);
+    store.replace_block(&rival)?;
+    assert_eq!(store.get_block(2)?.map(|b| b.hash()), Some(rival.hash()));
+    assert_eq!(store.tip_height()?, 2);
+    assert_eq!(store.tip_hash()?, Some(rival.hash()));
+    assert!(store.get_block_by_hash(&old.hash())?.is_none());
+    assert_eq!(
+        store
+            .get_block_by_hash(&rival.hash())?
+            .map(|b| b.header.index),
+        Some(2)
+    );
+    assert_eq!(store.tx_block_index(&tx_hash(&old.txs[0]))?, None);
+    assert_eq!(store.tx_block_index(&tx_hash(&rival.txs[0]))?, Some(2));
+    blocks[2] = rival;
+    let work: u128 = blocks.iter().map(|b| block_work(&b.hash())).sum();
+    assert_eq!(store.tip_work()?, work);
+
+    // a lower index swaps the block but leaves the tip where it is
+    let low = block(1, blocks[0].hash(), vec![tx("dave")]);
+    store.replace_block(&low)?;
+    assert_eq!(store.get_block(1)?.map(|b| b.hash()), Some(low.hash()));
+    assert_eq!(store.tip_hash()?, Some(blocks[2].hash()));
+    Ok(())
+}
+
+async fn test_storage_put_block_after<S: ChainStore>(store: Arc<S>) -> anyhow::Result<()> {
+    let store = &*store;
+    let blocks = linked_chain(store, 2);
+    let next = block(2, blocks[1].hash(), vec![]);
+    // the block below has moved on, or was never there
+    assert!(!store.put_block_after(&next, Some(blocks[0].hash()))?);
+    assert!(!store.put_block_after(&next, None)?);
+    assert!(store.get_block(2)?.is_none());
+    // a taken index is refused even on the right parent
+    let rival = block(
+        1,
+        blocks[0].hash(),
+        vec![Transaction {
+            from: "alice".into(),
+            to: "bob".into(),
+            amount: 1,
+            timestamp: 1_600_000_000,
+            fee: 0,
+            signature: None,
+            pubkey: None,
+        }],
+    );
+    assert!(!store.put_block_after(&rival, Some(blocks[0].hash()))?);
+    assert_eq!(
+        store.get_block(1)?.map(|b| b.hash()),
+        Some(blocks[1].hash())
+    );
+
+    assert!(store.put_block_after(&next, Some(blocks[1].hash()))?);
+    assert_eq!(store.tip_hash()?, Some(next.hash()));
+    // genesis has no parent to check, and a block past a gap expects none
+    assert!(!store.put_block_after(&block(0, [0u8; HASH_SIZE], vec![]), None)?);
+    assert!(store.put_block_after(&block(9, [0u8; HASH_SIZE], vec![]), None)?);
+    Ok(())
+}
+
+macro_rules! backend_tests {
+    ($($check:ident),* $(,)?) => {
+        mod sled_backend {
+            use std::sync::Arc;
+            $(
+                #[tokio::test]
+                async fn $check() -> anyhow::Result<()> {
+                    let (temp_dir, store) = crate::helpers::create_temp_store();
+                    let store = Arc::new(store);
+                    super::$check(store.clone()).await?;
+                    let Ok(store) = Arc::try_unwrap(store) else {
+                        panic!("{} kept the store alive", stringify!($check));
+                    };
+                    crate::helpers::teardown_store(temp_dir, store);
+                    Ok(())
+                }
+            )*
+        }
+
+        mod mem_backend {
+            use std::sync::Arc;
+            $(
+                #[tokio::test]
+                async fn $check() -> anyhow::Result<()> {
+                    super::$check(Arc::new(ledger_storage::mem_store::MemStore::new())).await
+                }
+            )*
+        }
+    };
+}
+
+backend_tests!(
+    test_storage_integration,
+    test_storage_edge_cases,
+    test_storage_concurrency,
+    test_storage_trait_compliance,
+    test_storage_cleanup,
+    test_storage_large_blockchain,
+    test_storage_empty_database,
+    test_storage_non_existent_block,
+    test_storage_large_transactions,
+    test_storage_multiple_tips,
+    test_storage_stress,
+    test_storage_get_block_by_hash,
+    test_storage_get_transaction,
+    test_storage_duplicate_index_is_noop,
+    test_storage_lower_index_keeps_tip,
+    test_storage_list_blocks_range,
+    test_storage_default_list_blocks_range_matches,
+    test_storage_delete_block_moves_tip,
+    test_storage_put_blocks_batch,
+    test_storage_tip_work_sums_block_work,
+    test_storage_put_block_after,
+    test_storage_replace_block,
+);
@@ -1,6 +1,7 @@
 //! Terminal UI for the ledger node.
 use std::{
     io,
+    path::{Path, PathBuf},
     time::{Duration, Instant},
 };
 
@@ -27,6 +28,9 @@ struct Args {
     /// Base URL of the running ledger-node (e.g. http://127.0.0.1:3000)
     #[arg(short, long, default_value = "http://127.0.0.1:8080")]
     node: String,
+    /// Seconds between automatic dashboard refreshes; `space` pauses them
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u64).range(1..))]
+    refresh_secs: u64,
 }
 
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
@@ -39,6 +43,33 @@ enum Tab {
     HashDemo,
 }
 
+/// Which field of the mempool form receives typed characters.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+enum FormField {
+    From,
+    To,
+    #[default]
+    Amount,
+}
+
+impl FormField {
+    fn next(self) -> Self {
+        match self {
+            FormField::From => FormField::To,
+            FormField::To => FormField::Amount,
+            FormField::Amount => FormField::From,
+        }
+    }
+
+    fn previous(self) -> Self {
+        match self {
+            FormField::From => FormField::Amount,
+            FormField::To => FormField::From,
+            FormField::Amount => FormField::To,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct Head {
     height: u64,
@@ -50,6 +81,24 @@ struct Tip {
     hash: Option<String>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+struct ChainStatus {
+    height: u64,
+    last_block_age_secs: Option<u64>,
+    uptime_secs: u64,
+    mempool_size: usize,
+}
+
+/// The parts of `/chain/stats` the difficulty panel shows.
+#[derive(Debug, Clone, Deserialize)]
+struct ChainStats {
+    avg_block_time_secs: Option<f64>,
+    tip_target: Option<u32>,
+    /// Leading zero bits of the most recent block hashes, oldest first.
+    #[serde(default)]
+    recent_difficulty: Vec<u32>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct BlockRow {
     index: u64,
@@ -58,9 +107,12 @@ struct BlockRow {
     hash: String,
     previous_hash: String,
     merkle_root: String,
-    data_hash: String,
+    /// Omitted by the node when the block carries no data.
+    #[serde(default)]
+    data_hash: Option<String>,
     tx_count: usize,
-    data: String,
+    #[serde(default)]
+    data: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -78,9 +130,29 @@ struct TxRow {
     timestamp: u64,
 }
 
+/// `/chain/blocks?paged=true`: one page of rows and where the next starts.
+#[derive(Debug, Clone, Deserialize)]
+struct ChainPage {
+    blocks: Vec<BlockRow>,
+    next_start: Option<u64>,
+}
+
+/// The parts of the node's `/chain/block/{index}` response the popup needs.
+#[derive(Debug, Clone, Deserialize)]
+struct BlockDetail {
+    index: u64,
+    txs: Vec<TxRow>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct MempoolCount {
+    count: usize,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct MineResult {
     mined: bool,
+    #[serde(alias = "height")]
     index: Option<u64>,
     nonce: Option<u64>,
     hash: Option<String>,
@@ -96,14 +168,39 @@ struct App {
     // dashboard
     head: Option<Head>,
     tip: Option<Tip>,
+    status: Option<ChainStatus>,
+    stats: Option<ChainStats>,
+    /// Why the last dashboard refresh failed, e.g. the node being
+    /// unreachable; cleared once it answers again.
+    node_status: Option<String>,
+    /// `ETag` of the last `/chain/head` body, sent back so an unchanged
+    /// head comes back as an empty `304`.
+    head_etag: Option<String>,
     last_refresh: Instant,
     // chain list
     chain_rows: Vec<BlockRow>,
+    /// `start` of the next, older page of blocks; `None` once genesis is loaded.
+    chain_next_start: Option<u64>,
+    /// Blocks fetched per page: one screen's worth of rows.
+    chain_page_size: u32,
+    /// Substring typed after `/`, matched against hash, previous hash and data.
+    chain_filter: String,
+    /// Whether keys are currently going into `chain_filter`.
+    chain_filter_editing: bool,
+    /// Positions in `chain_rows` that match `chain_filter`, in display order.
+    filtered_indices: Vec<usize>,
+    /// Position within `filtered_indices`, not `chain_rows`.
     chain_cursor: usize,
     chain_state: TableState,
     chain_scroll: ScrollbarState,
     chain_status: Option<String>,
+    /// Where `y`/`Y` put the hash; the system clipboard outside tests.
+    clipboard: Box<dyn Clipboard>,
+    /// Where `y`/`Y` write the hash when there is no system clipboard (e.g. over SSH).
+    clipboard_fallback: PathBuf,
     chain_popup: bool,
+    /// Full block behind the chain popup, fetched when the popup opens or the cursor moves.
+    chain_popup_block: Option<BlockDetail>,
     tx_popup: bool,
     // mempool tx list
     tx_rows: Vec<TxRow>,
@@ -114,12 +211,17 @@ struct App {
     tx_from: String,
     tx_to: String,
     tx_amount: String,
+    tx_focus: FormField,
     tx_status: Option<String>,
     //
     // mining
     mine_target: u32,
     mine_data: String,
     mine_status: Option<String>,
+    /// Pending txs per `/mempool/count`; `None` until the first refresh.
+    mine_pending: Option<usize>,
+    /// Set with `space`; only `r` refreshes while paused.
+    refresh_paused: bool,
     // hash demo
     hash_input: String,
     hash_output: String,
@@ -129,21 +231,64 @@ struct App {
 // Each item in the chain & mempool tables is 1 row high
 const ITEM_HEIGHT: usize = 1;
 
+/// Blocks per page until the terminal size is known.
+const DEFAULT_CHAIN_PAGE_SIZE: u32 = 50;
+
+/// How long a request may take before the node counts as unreachable, so a
+/// hung node can't freeze the UI loop.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `/mine` searches before it answers; the node gives up on its own after
+/// `--max-mine-secs` (300 by default), so allow a little longer.
+const MINE_TIMEOUT: Duration = Duration::from_secs(330);
+
+/// Status text for a failed request; timeouts and refused connections read
+/// as "node unreachable".
+fn request_error(e: &reqwest::Error) -> String {
+    if is_unreachable(e) {
+        format!("node unreachable: {e}")
+    } else {
+        e.to_string()
+    }
+}
+
+/// Whether `e` means the node could not be reached at all, as opposed to
+/// an error response, so the last data shown is still the best we have.
+fn is_unreachable(e: &reqwest::Error) -> bool {
+    e.is_timeout() || e.is_connect()
+}
+
 impl App {
     fn new(args: Args) -> Self {
         Self {
             args,
-            http: Client::new(),
+            http: Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .pool_max_idle_per_host(4)
+                .build()
+                .expect("default TLS backend is available"),
             tab: Tab::Dashboard,
             head: None,
             tip: None,
+            status: None,
+            stats: None,
+            node_status: None,
+            head_etag: None,
             last_refresh: Instant::now(),
             chain_rows: Vec::new(),
+            chain_next_start: None,
+            chain_page_size: DEFAULT_CHAIN_PAGE_SIZE,
+            chain_filter: String::new(),
+            chain_filter_editing: false,
+            filtered_indices: Vec::new(),
             chain_cursor: 0,
             chain_state: TableState::default(),
             chain_scroll: ScrollbarState::default(),
             chain_status: None,
+            clipboard: Box::new(SystemClipboard),
+            clipboard_fallback: PathBuf::from("clipboard.txt"),
             chain_popup: false,
+            chain_popup_block: None,
             tx_popup: false,
             tx_rows: Vec::new(),
             tx_cursor: 0,
@@ -152,10 +297,13 @@ impl App {
             tx_from: "alice".into(),
             tx_to: "bob".into(),
             tx_amount: "42".into(),
+            tx_focus: FormField::default(),
             tx_status: None,
             mine_target: 20,
             mine_data: String::new(),
             mine_status: None,
+            mine_pending: None,
+            refresh_paused: false,
             hash_input: String::new(),
             hash_output: String::new(),
             hash_leading_zeros: 0,
@@ -164,63 +312,211 @@ impl App {
 
     async fn refresh_dashboard(&mut self) {
         let base = &self.args.node;
+        let mut req = self.http.get(format!("{base}/chain/head"));
+        if let Some(etag) = &self.head_etag {
+            req = req.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        let head = match req.send().await {
+            Ok(resp) => {
+                self.node_status = None;
+                resp.error_for_status()
+            }
+            Err(e) => {
+                // the remaining requests would only wait out the same timeout
+                self.node_status = Some(request_error(&e));
+                self.last_refresh = Instant::now();
+                return;
+            }
+        };
+        let mut head_changed = false;
+        if let Ok(resp) = head {
+            if resp.status() != reqwest::StatusCode::NOT_MODIFIED {
+                let etag = resp
+                    .headers()
+                    .get(reqwest::header::ETAG)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string);
+                if let Ok(head) = resp.json::<Head>().await {
+                    self.head = Some(head);
+                    self.head_etag = etag;
+                    head_changed = true;
+                }
+            }
+        }
+        if let Ok(resp) = self
+            .http
+            .get(format!("{base}/chain/tip"))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+        {
+            if let Ok(tip) = resp.json::<Tip>().await {
+                self.tip = Some(tip);
+            }
+        }
         if let Ok(resp) = self
             .http
-            .get(format!("{base}/chain/head"))
+            .get(format!("{base}/chain/status"))
             .send()
             .await
             .and_then(|r| r.error_for_status())
         {
-            if let Ok(head) = resp.json::<Head>().await {
-                self.head = Some(head);
+            if let Ok(status) = resp.json::<ChainStatus>().await {
+                self.status = Some(status);
             }
         }
         if let Ok(resp) = self
             .http
-            .get(format!("{base}/chain/tip"))
+            .get(format!("{base}/mempool/count"))
             .send()
             .await
             .and_then(|r| r.error_for_status())
         {
-            if let Ok(tip) = resp.json::<Tip>().await {
-                self.tip = Some(tip);
+            if let Ok(pending) = resp.json::<MempoolCount>().await {
+                self.mine_pending = Some(pending.count);
+            }
+        }
+        // the stats only move with the tip, so a `304` head keeps the last ones
+        if head_changed || self.stats.is_none() {
+            if let Ok(resp) = self
+                .http
+                .get(format!("{base}/chain/stats"))
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+            {
+                if let Ok(stats) = resp.json::<ChainStats>().await {
+                    self.stats = Some(stats);
+                }
             }
         }
         self.last_refresh = Instant::now();
     }
 
-    async fn load_chain_page(&mut self, start: Option<u64>, limit: u32, desc: bool) {
+    /// GET one page of blocks, newest first, starting at `start` (the tip
+    /// when `None`).
+    async fn fetch_chain_page(&self, start: Option<u64>) -> reqwest::Result<ChainPage> {
         let base = &self.args.node;
-        let dir = if desc { "desc" } else { "asc" };
-        let mut url = format!("{base}/chain/blocks?limit={limit}&dir={dir}");
+        let limit = self.chain_page_size;
+        let mut url = format!("{base}/chain/blocks?limit={limit}&dir=desc&paged=true");
         if let Some(s) = start {
             url.push_str(&format!("&start={s}"));
         }
-        match self
-            .http
+        self.http
             .get(url)
             .send()
             .await
-            .and_then(|r| r.error_for_status())
-        {
-            Ok(resp) => match resp.json::<Vec<BlockRow>>().await {
-                Ok(rows) => {
-                    self.chain_rows = rows;
-                    self.chain_cursor = 0;
-                }
-                Err(e) => {
-                    self.chain_rows.clear();
-                    self.chain_status = Some(format!("Failed to decode blocks: {e}"));
-                }
-            },
+            .and_then(|r| r.error_for_status())?
+            .json()
+            .await
+    }
+
+    /// Replace the chain list with the newest page of blocks.
+    async fn load_chain_page(&mut self) {
+        match self.fetch_chain_page(None).await {
+            Ok(page) => {
+                self.chain_rows = page.blocks;
+                self.chain_next_start = page.next_start;
+                self.chain_cursor = 0;
+                self.apply_chain_filter();
+            }
+            Err(e) if e.is_decode() => {
+                self.chain_rows.clear();
+                self.chain_next_start = None;
+                self.apply_chain_filter();
+                self.chain_status = Some(format!("Failed to decode blocks: {e}"));
+            }
+            Err(e) if is_unreachable(&e) => {
+                self.chain_status = Some(format!("Failed to load blocks: {}", request_error(&e)));
+            }
             Err(e) => {
                 self.chain_rows.clear();
+                self.chain_next_start = None;
                 self.chain_cursor = 0;
+                self.apply_chain_filter();
                 self.chain_status = Some(format!("Failed to load blocks: {e}"));
             }
         }
     }
 
+    /// Append older pages until a new row passes the filter or genesis is
+    /// loaded. Returns whether any new row is visible; a failed request
+    /// keeps what is loaded.
+    async fn load_more_chain(&mut self) -> bool {
+        let visible = self.filtered_indices.len();
+        while let Some(start) = self.chain_next_start {
+            match self.fetch_chain_page(Some(start)).await {
+                Ok(page) => {
+                    self.chain_rows.extend(page.blocks);
+                    self.chain_next_start = page.next_start;
+                    self.apply_chain_filter();
+                    if self.filtered_indices.len() > visible {
+                        return true;
+                    }
+                }
+                Err(e) => {
+                    self.chain_status =
+                        Some(format!("Failed to load blocks: {}", request_error(&e)));
+                    return false;
+                }
+            }
+        }
+        false
+    }
+
+    /// Recompute `filtered_indices` from `chain_filter` and keep the cursor on a visible row.
+    fn apply_chain_filter(&mut self) {
+        let needle = self.chain_filter.to_lowercase();
+        self.filtered_indices = self
+            .chain_rows
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| {
+                needle.is_empty()
+                    || b.hash.to_lowercase().contains(&needle)
+                    || b.previous_hash.to_lowercase().contains(&needle)
+                    || b.data
+                        .as_deref()
+                        .is_some_and(|d| d.to_lowercase().contains(&needle))
+            })
+            .map(|(i, _)| i)
+            .collect();
+        self.chain_cursor = self
+            .chain_cursor
+            .min(self.filtered_indices.len().saturating_sub(1));
+        self.chain_state.select(Some(self.chain_cursor));
+        self.chain_scroll = self.chain_scroll.position(self.chain_cursor * ITEM_HEIGHT);
+    }
+
+    /// The block row under the chain cursor, after filtering.
+    fn selected_block(&self) -> Option<&BlockRow> {
+        self.filtered_indices
+            .get(self.chain_cursor)
+            .and_then(|&i| self.chain_rows.get(i))
+    }
+
+    /// Fetch the block under the chain cursor, with its transactions, for the popup.
+    async fn load_popup_block(&mut self) {
+        self.chain_popup_block = None;
+        let Some(row) = self.selected_block() else {
+            return;
+        };
+        let url = format!("{}/chain/block/{}", self.args.node, row.index);
+        match self
+            .http
+            .get(url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+        {
+            Ok(resp) => match resp.json::<BlockDetail>().await {
+                Ok(block) => self.chain_popup_block = Some(block),
+                Err(e) => self.chain_status = Some(format!("Failed to decode block: {e}")),
+            },
+            Err(e) => self.chain_status = Some(format!("Failed to load block: {e}")),
+        }
+    }
+
     async fn next_row(&mut self) {
         match self.tab {
             Tab::Mempool => {
@@ -240,9 +536,14 @@ impl App {
                 self.tx_scroll = self.tx_scroll.position(i * ITEM_HEIGHT);
             }
             Tab::Chain => {
+                let visible = self.filtered_indices.len();
+                if visible == 0 {
+                    return;
+                }
                 let i = match self.chain_state.selected() {
                     Some(i) => {
-                        if i >= self.chain_rows.len() - 1 {
+                        // past the last loaded row, fetch the next page before wrapping
+                        if i >= visible - 1 && !self.load_more_chain().await {
                             self.chain_cursor = 0;
                             0
                         } else {
@@ -278,11 +579,15 @@ impl App {
                 self.tx_scroll = self.tx_scroll.position(i * ITEM_HEIGHT);
             }
             Tab::Chain => {
+                let visible = self.filtered_indices.len();
+                if visible == 0 {
+                    return;
+                }
                 let i = match self.chain_state.selected() {
                     Some(i) => {
                         if i == 0 {
-                            self.chain_cursor = self.chain_rows.len() - 1;
-                            self.chain_rows.len() - 1
+                            self.chain_cursor = visible - 1;
+                            visible - 1
                         } else {
                             self.chain_cursor -= 1;
                             i - 1
@@ -318,6 +623,12 @@ impl App {
                     self.tx_status = Some(format!("Failed to decode transactions: {e}"));
                 }
             },
+            Err(e) if is_unreachable(&e) => {
+                self.tx_status = Some(format!(
+                    "Failed to load transactions: {}",
+                    request_error(&e)
+                ));
+            }
             Err(e) => {
                 self.tx_rows.clear();
                 self.tx_cursor = 0;
@@ -341,7 +652,7 @@ impl App {
                 let body = resp.text().await.unwrap_or_default();
                 self.tx_status = Some(format!("POST /tx -> {status} {body}"));
             }
-            Err(e) => self.tx_status = Some(format!("POST /tx failed: {e}")),
+            Err(e) => self.tx_status = Some(format!("POST /tx failed: {}", request_error(&e))),
         }
     }
 
@@ -355,6 +666,7 @@ impl App {
         match self
             .http
             .get(url)
+            .timeout(MINE_TIMEOUT)
             .send()
             .await
             .and_then(|r| r.error_for_status())
@@ -377,7 +689,7 @@ impl App {
                 }
                 Err(e) => self.mine_status = Some(format!("Decode /mine JSON failed: {e}")),
             },
-            Err(e) => self.mine_status = Some(format!("GET /mine failed: {e}")),
+            Err(e) => self.mine_status = Some(format!("GET /mine failed: {}", request_error(&e))),
         }
     }
 
@@ -385,29 +697,18 @@ impl App {
         use sha2::{Digest, Sha256};
         let digest = Sha256::digest(self.hash_input.as_bytes());
         self.hash_output = hex::encode(digest);
-        self.hash_leading_zeros = count_leading_zero_bits(self.hash_output.as_bytes());
+        self.hash_leading_zeros = hex_leading_zero_bits(&self.hash_output);
     }
 }
 
-fn count_leading_zero_bits(hex_bytes: &[u8]) -> u32 {
-    // Count leading zero bits by scanning hex nybbles
-    let mut bits = 0u32;
-    for &b in hex_bytes {
-        let v = match b {
-            b'0' => 0,
-            b'1'..=b'9' => b - b'0',
-            b'a'..=b'f' => 10u8 + (b - b'a'),
-            b'A'..=b'F' => 10u8 + (b - b'A'),
-            _ => 0,
-        };
-        if v == 0 {
-            bits += 4;
-        } else {
-            bits += v.leading_zeros() - 4;
-            break;
-        }
+/// Leading zero bits of a hex-encoded 32-byte hash, counted on the decoded
+/// bytes the same way the node does. Anything that isn't such a hash is 0.
+fn hex_leading_zero_bits(hex_hash: &str) -> u32 {
+    let mut hash = [0u8; 32];
+    match hex::decode_to_slice(hex_hash, &mut hash) {
+        Ok(()) => ledger_core::pow::count_leading_zero_bits(&hash),
+        Err(_) => 0,
     }
-    bits
 }
 
 #[tokio::main]
@@ -428,7 +729,8 @@ async fn main() -> Result<()> {
 
     let mut app = App::new(args.clone());
     app.refresh_dashboard().await;
-    app.load_chain_page(None, 999, true).await;
+    app.chain_page_size = page_size(terminal.size()?.height);
+    app.load_chain_page().await;
     app.load_mempool_page().await;
     app.update_hash_demo();
 
@@ -442,9 +744,16 @@ async fn main() -> Result<()> {
     res
 }
 
+/// Blocks per page for a terminal `height` rows tall.
+fn page_size(height: u16) -> u32 {
+    u32::from(height).max(1)
+}
+
 async fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
     loop {
         terminal.draw(|f| ui(f, app))?;
+        // later pages follow the terminal if it is resized
+        app.chain_page_size = page_size(terminal.size()?.height);
 
         if crossterm::event::poll(Duration::from_millis(200))? {
             if let CEvent::Key(key) = event::read()? {
@@ -455,7 +764,9 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Resul
         }
 
         // periodic refresh (dashboard)
-        if app.last_refresh.elapsed() >= Duration::from_secs(2) {
+        if !app.refresh_paused
+            && app.last_refresh.elapsed() >= Duration::from_secs(app.args.refresh_secs)
+        {
             app.refresh_dashboard().await;
         }
     }
@@ -466,6 +777,40 @@ async fn handle_key(app: &mut App, key: KeyEvent) -> Result<bool> {
     let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
     match key.code {
         KeyCode::Char('c') if ctrl => return Ok(true),
+        // while the chain filter is open it takes every key
+        _ if app.tab == Tab::Chain && app.chain_filter_editing => {
+            match key.code {
+                KeyCode::Esc => {
+                    app.chain_filter.clear();
+                    app.chain_filter_editing = false;
+                }
+                KeyCode::Enter => app.chain_filter_editing = false,
+                KeyCode::Backspace => {
+                    app.chain_filter.pop();
+                }
+                KeyCode::Char(c) if !c.is_control() => app.chain_filter.push(c),
+                _ => return Ok(false),
+            }
+            app.apply_chain_filter();
+        }
+        KeyCode::Char('/') if app.tab == Tab::Chain => app.chain_filter_editing = true,
+        KeyCode::Esc if app.tab == Tab::Chain && !app.chain_filter.is_empty() => {
+            app.chain_filter.clear();
+            app.apply_chain_filter();
+        }
+        // text fields of the mempool form take every printable key, so
+        // typing a name can't trigger refresh or the popup
+        KeyCode::Char(c)
+            if !ctrl
+                && app.tab == Tab::Mempool
+                && matches!(app.tx_focus, FormField::From | FormField::To) =>
+        {
+            if app.tx_focus == FormField::From {
+                app.tx_from.push(c);
+            } else {
+                app.tx_to.push(c);
+            }
+        }
         KeyCode::Esc => return Ok(true),
         KeyCode::Tab => {
             app.tab = match app.tab {
@@ -485,9 +830,13 @@ async fn handle_key(app: &mut App, key: KeyEvent) -> Result<bool> {
                 Tab::HashDemo => Tab::Mine,
             };
         }
+        // Mine data and the hash demo are free text, so space is typed there
+        KeyCode::Char(' ') if !matches!(app.tab, Tab::Mine | Tab::HashDemo) => {
+            app.refresh_paused = !app.refresh_paused;
+        }
         KeyCode::Char('r') => {
             app.refresh_dashboard().await;
-            app.load_chain_page(None, 999, true).await;
+            app.load_chain_page().await;
             app.load_mempool_page().await;
         }
         // Chain view navigation
@@ -495,15 +844,37 @@ async fn handle_key(app: &mut App, key: KeyEvent) -> Result<bool> {
             if app.tab == Tab::Chain || app.tab == Tab::Mempool {
                 app.next_row().await;
             }
+            if app.tab == Tab::Chain && app.chain_popup {
+                app.load_popup_block().await;
+            }
         }
         KeyCode::Up => {
             if app.tab == Tab::Chain || app.tab == Tab::Mempool {
                 app.previous_row().await;
             }
+            if app.tab == Tab::Chain && app.chain_popup {
+                app.load_popup_block().await;
+            }
+        }
+        KeyCode::Char(c @ ('y' | 'Y')) if app.tab == Tab::Chain => {
+            if let Some((index, what, text)) = app.selected_block().map(|b| {
+                if c == 'y' {
+                    (b.index, "hash", b.hash.clone())
+                } else {
+                    (b.index, "previous hash", b.previous_hash.clone())
+                }
+            }) {
+                let status =
+                    copy_to_clipboard(app.clipboard.as_mut(), &text, &app.clipboard_fallback);
+                app.chain_status = Some(format!("{status} {what} of block {index}"));
+            }
         }
         KeyCode::Char('p') => {
             if app.tab == Tab::Chain {
                 app.chain_popup = !app.chain_popup;
+                if app.chain_popup {
+                    app.load_popup_block().await;
+                }
             } else if app.tab == Tab::Mempool {
                 app.tx_popup = !app.tx_popup;
             }
@@ -511,9 +882,17 @@ async fn handle_key(app: &mut App, key: KeyEvent) -> Result<bool> {
         _ => {
             if app.tab == Tab::Mempool {
                 match key.code {
+                    KeyCode::Char('l') if ctrl => app.tx_focus = app.tx_focus.next(),
+                    KeyCode::Char('h') if ctrl => app.tx_focus = app.tx_focus.previous(),
+                    KeyCode::Right => app.tx_focus = app.tx_focus.next(),
+                    KeyCode::Left => app.tx_focus = app.tx_focus.previous(),
                     KeyCode::Char(c) if c.is_ascii_digit() => app.tx_amount.push(c),
                     KeyCode::Backspace => {
-                        app.tx_amount.pop();
+                        match app.tx_focus {
+                            FormField::From => app.tx_from.pop(),
+                            FormField::To => app.tx_to.pop(),
+                            FormField::Amount => app.tx_amount.pop(),
+                        };
                     }
                     KeyCode::Enter => {
                         app.submit_tx().await;
@@ -522,15 +901,15 @@ async fn handle_key(app: &mut App, key: KeyEvent) -> Result<bool> {
                 }
             } else if app.tab == Tab::Mine {
                 match key.code {
-                    KeyCode::Left => {
-                        if app.mine_target > 0 {
-                            app.mine_target -= 1;
-                        }
+                    KeyCode::Left if app.mine_target > 0 => {
+                        app.mine_target -= 1;
                     }
-                    KeyCode::Right => {
-                        if app.mine_target < 32 {
-                            app.mine_target += 1;
-                        }
+                    KeyCode::Right if app.mine_target < 32 => {
+                        app.mine_target += 1;
+                    }
+                    // plain `c` is block data, so clearing needs Alt
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::ALT) => {
+                        app.mine_data.clear();
                     }
                     KeyCode::Char(c) if !c.is_control() => app.mine_data.push(c),
                     KeyCode::Backspace => {
@@ -592,8 +971,13 @@ fn ui(f: &mut Frame, app: &mut App) {
     }
 
     // Footer
-    let help = Paragraph::new(
-        "q/ESC quit • TAB prev/next tab • r refresh • Mine: ←/→ target, Enter mine • HashDemo: type to hash • Mempool: Enter to POST /tx")
+    let refresh = if app.refresh_paused {
+        "auto-refresh paused (space resumes)".to_string()
+    } else {
+        format!("auto-refresh {}s (space pauses)", app.args.refresh_secs)
+    };
+    let help = Paragraph::new(format!(
+        "q/ESC quit • TAB prev/next tab • r refresh • {refresh} • Mine: ←/→ target, Enter mine • HashDemo: type to hash • Mempool: Enter to POST /tx"))
         .style(Style::default().fg(Color::DarkGray))
         .block(Block::default().borders(Borders::ALL).title("help"));
     f.render_widget(help, chunks[2]);
@@ -606,6 +990,32 @@ fn render_dashboard(f: &mut Frame, area: Rect, app: &App) {
         .split(area);
 
     let mut lines = Vec::new();
+    if let Some(err) = &app.node_status {
+        lines.push(Line::from(Span::styled(
+            err.clone(),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )));
+    }
+    if let Some(st) = &app.status {
+        // Shown first and coloured so a stalled miner stands out.
+        let (age, style) = match st.last_block_age_secs {
+            Some(secs) => (format_age(secs), block_age_style(secs)),
+            None => ("-".to_string(), Style::default()),
+        };
+        lines.push(Line::from(vec![
+            Span::raw("Last block: "),
+            Span::styled(format!("{age} ago"), style.add_modifier(Modifier::BOLD)),
+            Span::raw(format!(" (height {})", st.height)),
+        ]));
+        lines.push(Line::from(format!(
+            "Mempool   : {} pending",
+            st.mempool_size
+        )));
+        lines.push(Line::from(format!(
+            "Uptime    : {}",
+            format_age(st.uptime_secs)
+        )));
+    }
     if let Some(h) = &app.head {
         lines.push(Line::from(format!("Head height: {}", h.height)));
     }
@@ -620,18 +1030,93 @@ fn render_dashboard(f: &mut Frame, area: Rect, app: &App) {
         Paragraph::new(lines).block(Block::default().title("Overview").borders(Borders::ALL));
     f.render_widget(dash, chunks[0]);
 
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(8), Constraint::Min(0)])
+        .split(chunks[1]);
+    render_difficulty(f, right[0], app);
+
     let about = Paragraph::new(vec![
         Line::from("ledger-tui"),
-        Line::from("• Talks to /chain/head, /chain/tip, /chain/blocks"),
+        Line::from(
+            "• Talks to /chain/head, /chain/tip, /chain/status, /chain/stats, /chain/blocks",
+        ),
         Line::from("• Submits /tx and /mine"),
         Line::from("• Live SHA-256 hash demo"),
     ])
     .block(Block::default().title("About").borders(Borders::ALL));
-    f.render_widget(about, chunks[1]);
+    f.render_widget(about, right[1]);
+}
+
+/// Tip difficulty, average block time and a sparkline of recent block
+/// difficulties from `/chain/stats`.
+fn render_difficulty(f: &mut Frame, area: Rect, app: &App) {
+    let panel = Block::default().title("Difficulty").borders(Borders::ALL);
+    let inner = panel.inner(area);
+    f.render_widget(panel, area);
+    let Some(stats) = &app.stats else {
+        f.render_widget(Paragraph::new("no stats yet"), inner);
+        return;
+    };
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(0)])
+        .split(inner);
+    let target = stats.tip_target.map_or_else(
+        || "-".to_string(),
+        |bits| format!("{bits} leading zero bits"),
+    );
+    let block_time = stats
+        .avg_block_time_secs
+        .map_or_else(|| "-".to_string(), |secs| format!("{secs:.1}s"));
+    let text = Paragraph::new(vec![
+        Line::from(format!("Tip target : {target}")),
+        Line::from(format!("Block time : {block_time} avg")),
+    ]);
+    f.render_widget(text, rows[0]);
+    let history: Vec<u64> = stats.recent_difficulty.iter().map(|&d| d as u64).collect();
+    let sparkline = Sparkline::default()
+        .data(&history)
+        .style(Style::default().fg(Color::Cyan));
+    f.render_widget(sparkline, rows[1]);
+}
+
+/// Somewhere `y`/`Y` can put text; swapped for a fake in tests.
+trait Clipboard: std::fmt::Debug {
+    /// Replace the clipboard contents with `text`.
+    fn set_text(&mut self, text: &str) -> Result<(), String>;
+}
+
+/// The desktop clipboard, opened fresh on each copy.
+#[derive(Debug)]
+struct SystemClipboard;
+
+impl Clipboard for SystemClipboard {
+    fn set_text(&mut self, text: &str) -> Result<(), String> {
+        arboard::Clipboard::new()
+            .and_then(|mut cb| cb.set_text(text))
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Put `text` on `clipboard`, falling back to writing it to `fallback`
+/// when no clipboard is reachable. Returns a short status for the chain view.
+fn copy_to_clipboard(clipboard: &mut dyn Clipboard, text: &str, fallback: &Path) -> String {
+    match clipboard.set_text(text) {
+        Ok(()) => "copied".to_string(),
+        Err(e) => {
+            tracing::debug!("clipboard unavailable: {e}");
+            match std::fs::write(fallback, text) {
+                Ok(()) => format!("no clipboard; copied to {}:", fallback.display()),
+                Err(io) => format!("copy failed ({e}; {}: {io}):", fallback.display()),
+            }
+        }
+    }
 }
 
 fn render_chain(f: &mut Frame, area: Rect, app: &mut App) {
-    let rows = app.chain_rows.iter().enumerate().map(|(i, b)| {
+    let visible = app.filtered_indices.iter().map(|&i| &app.chain_rows[i]);
+    let rows = visible.enumerate().map(|(i, b)| {
         Row::new(vec![
             Cell::from(b.index.to_string()),
             Cell::from(b.ts.to_string()),
@@ -640,8 +1125,8 @@ fn render_chain(f: &mut Frame, area: Rect, app: &mut App) {
             Cell::from(b.previous_hash.clone()),
             Cell::from(b.tx_count.to_string()),
             Cell::from(b.merkle_root.clone()),
-            Cell::from(b.data_hash.clone()),
-            Cell::from(b.data.clone()),
+            Cell::from(b.data_hash.clone().unwrap_or_default()),
+            Cell::from(b.data.clone().unwrap_or_default()),
         ])
         .style(if i == app.chain_cursor {
             Style::default().add_modifier(Modifier::REVERSED)
@@ -664,7 +1149,24 @@ fn render_chain(f: &mut Frame, area: Rect, app: &mut App) {
         Row::new(vec!["idx", "ts", "nonce", "hash", "prev", "txs"])
             .style(Style::default().add_modifier(Modifier::BOLD)),
     )
-    .block(Block::default().borders(Borders::ALL).title("Chain blocks"));
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(
+                if app.chain_filter_editing || !app.chain_filter.is_empty() {
+                    format!(
+                        "Chain blocks (filter: {}{}, {} of {})",
+                        app.chain_filter,
+                        if app.chain_filter_editing { "_" } else { "" },
+                        app.filtered_indices.len(),
+                        app.chain_rows.len()
+                    )
+                } else {
+                    "Chain blocks (/ to filter, y/Y to copy hash/prev)".to_string()
+                },
+            )
+            .title_bottom(app.chain_status.clone().unwrap_or_default()),
+    );
     f.render_stateful_widget(table, area, &mut app.chain_state);
 
     if app.chain_popup {
@@ -674,11 +1176,8 @@ fn render_chain(f: &mut Frame, area: Rect, app: &mut App) {
             .title("Block details")
             .title_style(Style::new().yellow().bold())
             .border_style(Style::new().red().bold());
-        let items = if app.chain_rows.is_empty() || app.chain_cursor >= app.chain_rows.len() {
-            vec!["No block selected".to_string()]
-        } else {
-            let b = &app.chain_rows[app.chain_cursor];
-            vec![
+        let items = if let Some(b) = app.selected_block() {
+            let mut items = vec![
                 format!(" Index     : {}", b.index),
                 format!(" Timestamp : {}", b.ts),
                 format!(" Nonce     : {}", b.nonce),
@@ -686,15 +1185,36 @@ fn render_chain(f: &mut Frame, area: Rect, app: &mut App) {
                 format!(" Prev hash : {}", b.previous_hash),
                 format!(" Tx count  : {}", b.tx_count),
                 format!(" Merkle    : {}", b.merkle_root),
-                format!(" Data hash : {}", b.data_hash),
-                format!(" Data      : {}", b.data),
-            ]
+                format!(" Data hash : {}", b.data_hash.as_deref().unwrap_or("-")),
+                format!(" Data      : {}", b.data.as_deref().unwrap_or("-")),
+            ];
+            match app
+                .chain_popup_block
+                .as_ref()
+                .filter(|d| d.index == b.index)
+            {
+                Some(detail) => {
+                    items.push(String::new());
+                    items.push(" Transactions:".to_string());
+                    items.extend(detail.txs.iter().enumerate().map(|(i, tx)| {
+                        format!(
+                            "  [{i}] {} -> {} amount={} ts={}",
+                            tx.from, tx.to, tx.amount, tx.timestamp
+                        )
+                    }));
+                }
+                None if b.tx_count > 0 => items.push(" Transactions: loading...".to_string()),
+                None => {}
+            }
+            items
+        } else {
+            vec!["No block selected".to_string()]
         };
         let list = List::new(items);
-        let popup_area = centered_area(area, 60, 25);
+        let popup_area = centered_area(area, 60, 60);
         // clears out any background in the area before rendering the popup
         f.render_widget(Clear, popup_area);
-        f.render_widget(popup, popup_area);
+        f.render_widget(&popup, popup_area);
         f.render_widget(list, popup.inner(popup_area));
     }
 }
@@ -711,11 +1231,18 @@ fn render_mempool(f: &mut Frame, area: Rect, app: &mut App) {
         .split(area);
 
     // Simple form
+    let field = |field: FormField, label: &str, value: &str| {
+        if app.tx_focus == field {
+            Line::from(format!("> {label}: {value}_")).style(Style::default().fg(Color::Yellow))
+        } else {
+            Line::from(format!("  {label}: {value}"))
+        }
+    };
     let form = Paragraph::new(vec![
-        Line::from(format!("From   : {}", app.tx_from)),
-        Line::from(format!("To     : {}", app.tx_to)),
-        Line::from(format!("Amount : {}", app.tx_amount)),
-        Line::from("Press <Enter> to POST /tx"),
+        field(FormField::From, "From  ", &app.tx_from),
+        field(FormField::To, "To    ", &app.tx_to),
+        field(FormField::Amount, "Amount", &app.tx_amount),
+        Line::from("←/→ or Ctrl-h/Ctrl-l: switch field   <Enter>: POST /tx"),
     ])
     .block(
         Block::default()
@@ -802,6 +1329,7 @@ fn render_mine(f: &mut Frame, area: Rect, app: &App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
+            Constraint::Length(3),
             Constraint::Length(3),
             Constraint::Length(6),
             Constraint::Min(0),
@@ -815,16 +1343,28 @@ fn render_mine(f: &mut Frame, area: Rect, app: &App) {
     .block(Block::default().borders(Borders::ALL).title("Target"));
     f.render_widget(top, chunks[0]);
 
+    let pending = match app.mine_pending {
+        None => Line::from("Pending txs: ? (press r to refresh)"),
+        Some(0) => Line::from(Span::styled(
+            "⚠ Pending txs: 0 — the block will carry no transfers",
+            Style::default().fg(Color::Yellow),
+        )),
+        Some(n) => Line::from(format!("Pending txs: {n}")),
+    };
+    let pending =
+        Paragraph::new(pending).block(Block::default().borders(Borders::ALL).title("Mempool"));
+    f.render_widget(pending, chunks[1]);
+
     let data = Paragraph::new(app.mine_data.clone()).block(
         Block::default()
             .borders(Borders::ALL)
-            .title("Block data (type, Backspace, Enter to mine)"),
+            .title("Block data (type, Backspace, Alt-c to clear, Enter to mine)"),
     );
-    f.render_widget(data, chunks[1]);
+    f.render_widget(data, chunks[2]);
 
     let status = Paragraph::new(app.mine_status.clone().unwrap_or_default())
         .block(Block::default().borders(Borders::ALL).title("Status"));
-    f.render_widget(status, chunks[2]);
+    f.render_widget(status, chunks[3]);
 }
 
 fn render_hashdemo(f: &mut Frame, area: Rect, app: &App) {
@@ -856,6 +1396,24 @@ fn render_hashdemo(f: &mut Frame, area: Rect, app: &App) {
     f.render_widget(help, chunks[2]);
 }
 
+/// Compact human duration, e.g. `42s`, `3m 12s`, `1h 02m`.
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3599 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60),
+    }
+}
+
+/// Green while blocks are fresh, yellow after a minute, red after five.
+fn block_age_style(secs: u64) -> Style {
+    match secs {
+        0..=59 => Style::default().fg(Color::Green),
+        60..=299 => Style::default().fg(Color::Yellow),
+        _ => Style::default().fg(Color::Red),
+    }
+}
+
 /// Create a centered rect using the given percentage of the available rect
 fn centered_area(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
     // vertically center a strip that's percent_y tall
@@ -873,12 +1431,14 @@ fn centered_area(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
 mod tests {
     use super::*;
     use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use std::sync::{Arc, Mutex};
 
     // tokio for async tests
     #[tokio::test]
     async fn test_tab_transitions_via_handle_key() {
         let args = Args {
             node: "http://localhost:8080".to_string(),
+            refresh_secs: 2,
         };
         let mut app = App::new(args);
         assert_eq!(app.tab, Tab::Dashboard);
@@ -908,6 +1468,7 @@ mod tests {
     async fn test_update_hash_demo_and_hash_consistency() {
         let args = Args {
             node: "http://localhost:8080".to_string(),
+            refresh_secs: 2,
         };
         let mut app = App::new(args);
 
@@ -915,9 +1476,354 @@ mod tests {
         app.update_hash_demo();
         assert_eq!(app.hash_output.len(), 64); // 64 hex chars
 
-        // Local deterministic check of leading zeros calculation
-        // Use the shared implementation for leading zeros calculation
-        let expected = count_leading_zero_bits(&app.hash_output);
-        assert_eq!(app.hash_leading_zeros, expected);
+        let mut raw = [0u8; 32];
+        hex::decode_to_slice(&app.hash_output, &mut raw).unwrap();
+        assert_eq!(
+            app.hash_leading_zeros,
+            ledger_core::pow::count_leading_zero_bits(&raw)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hash_demo_leading_zeros_known_digest() {
+        let mut app = App::new(Args {
+            node: "http://localhost:8080".to_string(),
+            refresh_secs: 2,
+        });
+        // sha256 = 0004928d..., i.e. 000 then 0100: 13 zero bits
+        app.hash_input = "hash-demo-16800".to_string();
+        app.update_hash_demo();
+        assert!(app.hash_output.starts_with("0004928d"));
+        assert_eq!(app.hash_leading_zeros, 13);
+        assert_eq!(hex_leading_zero_bits("not hex"), 0);
+    }
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[tokio::test]
+    async fn test_mempool_form_edits_focused_field() {
+        let mut app = App::new(Args {
+            node: "http://localhost:8080".to_string(),
+            refresh_secs: 2,
+        });
+        app.tab = Tab::Mempool;
+        assert_eq!(app.tx_focus, FormField::Amount);
+
+        // Amount -> From -> To
+        handle_key(&mut app, press(KeyCode::Right)).await.unwrap();
+        handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL),
+        )
+        .await
+        .unwrap();
+        assert_eq!(app.tx_focus, FormField::To);
+
+        for _ in 0..app.tx_to.len() {
+            handle_key(&mut app, press(KeyCode::Backspace))
+                .await
+                .unwrap();
+        }
+        for c in "carol".chars() {
+            handle_key(&mut app, press(KeyCode::Char(c))).await.unwrap();
+        }
+        assert_eq!(app.tx_to, "carol");
+        assert_eq!(app.tx_from, "alice");
+        assert_eq!(app.tx_amount, "42");
+        assert!(!app.tx_popup);
+    }
+
+    #[tokio::test]
+    async fn test_mine_data_clear() {
+        let mut app = App::new(Args {
+            node: "http://localhost:8080".to_string(),
+            refresh_secs: 2,
+        });
+        app.tab = Tab::Mine;
+
+        for c in "cc".chars() {
+            handle_key(&mut app, press(KeyCode::Char(c))).await.unwrap();
+        }
+        assert_eq!(app.mine_data, "cc");
+
+        handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('c'), KeyModifiers::ALT),
+        )
+        .await
+        .unwrap();
+        assert!(app.mine_data.is_empty());
+        assert_eq!(app.tab, Tab::Mine);
+    }
+
+    fn block_row(index: u64, hash: &str, data: Option<&str>) -> BlockRow {
+        BlockRow {
+            index,
+            ts: 1_600_000_000 + index,
+            nonce: 0,
+            hash: hash.to_string(),
+            previous_hash: "00".repeat(32),
+            merkle_root: "00".repeat(32),
+            data_hash: None,
+            tx_count: 0,
+            data: data.map(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chain_pages_load_as_the_cursor_passes_the_end() {
+        use wiremock::matchers::{method, path, query_param, query_param_is_missing};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let page = |indices: &[u64], next_start: Option<u64>| {
+            let blocks: Vec<serde_json::Value> = indices
+                .iter()
+                .map(|i| {
+                    serde_json::json!({
+                        "index": i, "ts": 0, "nonce": 0, "hash": format!("{i:064x}"),
+                        "previous_hash": "00", "merkle_root": "00", "tx_count": 0,
+                    })
+                })
+                .collect();
+            let body = serde_json::json!({ "blocks": blocks, "next_start": next_start });
+            ResponseTemplate::new(200).set_body_json(body)
+        };
+        let pages = [
+            (None, vec![4, 3], Some(2)),
+            (Some("2"), vec![2, 1], Some(0)),
+            (Some("0"), vec![0], None),
+        ];
+        for (start, indices, next_start) in pages {
+            let mock = Mock::given(method("GET"))
+                .and(path("/chain/blocks"))
+                .and(query_param("limit", "2"))
+                .and(query_param("paged", "true"));
+            let mock = match start {
+                Some(start) => mock.and(query_param("start", start)),
+                None => mock.and(query_param_is_missing("start")),
+            };
+            mock.respond_with(page(&indices, next_start))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let mut app = App::new(Args {
+            node: server.uri(),
+            refresh_secs: 2,
+        });
+        app.tab = Tab::Chain;
+        app.chain_page_size = 2;
+        app.load_chain_page().await;
+        assert_eq!(app.chain_rows.len(), 2);
+        assert_eq!(app.chain_next_start, Some(2));
+
+        let indices = |app: &App| app.chain_rows.iter().map(|b| b.index).collect::<Vec<_>>();
+        handle_key(&mut app, press(KeyCode::Down)).await.unwrap();
+        assert_eq!(indices(&app), [4, 3]);
+        // moving past block 3 fetches the page below it
+        handle_key(&mut app, press(KeyCode::Down)).await.unwrap();
+        assert_eq!(indices(&app), [4, 3, 2, 1]);
+        assert_eq!(app.selected_block().unwrap().index, 2);
+        handle_key(&mut app, press(KeyCode::Down)).await.unwrap();
+        handle_key(&mut app, press(KeyCode::Down)).await.unwrap();
+        assert_eq!(indices(&app), [4, 3, 2, 1, 0]);
+        assert_eq!(app.chain_next_start, None);
+        assert_eq!(app.selected_block().unwrap().index, 0);
+        // with genesis loaded the cursor wraps instead
+        handle_key(&mut app, press(KeyCode::Down)).await.unwrap();
+        assert_eq!(app.selected_block().unwrap().index, 4);
+        assert_eq!(app.chain_rows.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_chain_filter() {
+        let mut app = App::new(Args {
+            node: "http://localhost:8080".to_string(),
+            refresh_secs: 2,
+        });
+        app.tab = Tab::Chain;
+        app.chain_rows = vec![
+            block_row(2, "00aa11", Some("payroll")),
+            block_row(1, "00bb22", None),
+            block_row(0, "00cc33", Some("genesis")),
+        ];
+        app.apply_chain_filter();
+        app.chain_cursor = 2;
+        assert_eq!(app.filtered_indices, [0, 1, 2]);
+
+        for c in "/bb".chars() {
+            handle_key(&mut app, press(KeyCode::Char(c))).await.unwrap();
+        }
+        assert!(app.chain_filter_editing);
+        assert_eq!(app.chain_filter, "bb");
+        assert_eq!(app.filtered_indices, [1]);
+        assert_eq!(app.chain_cursor, 0);
+        assert_eq!(app.selected_block().unwrap().index, 1);
+
+        // data matches too, and 'r'/'p' are filter text rather than commands
+        handle_key(&mut app, press(KeyCode::Backspace))
+            .await
+            .unwrap();
+        handle_key(&mut app, press(KeyCode::Backspace))
+            .await
+            .unwrap();
+        for c in "payr".chars() {
+            handle_key(&mut app, press(KeyCode::Char(c))).await.unwrap();
+        }
+        assert_eq!(app.filtered_indices, [0]);
+        assert!(!app.chain_popup);
+
+        handle_key(&mut app, press(KeyCode::Enter)).await.unwrap();
+        assert!(!app.chain_filter_editing);
+        assert_eq!(app.filtered_indices, [0]);
+
+        // Esc clears the filter instead of quitting
+        let quit = handle_key(&mut app, press(KeyCode::Esc)).await.unwrap();
+        assert!(!quit);
+        assert!(app.chain_filter.is_empty());
+        assert_eq!(app.filtered_indices, [0, 1, 2]);
+    }
+
+    /// Records what was copied, or refuses every copy when `text` is `None`.
+    #[derive(Debug, Clone)]
+    struct FakeClipboard {
+        text: Option<Arc<Mutex<String>>>,
+    }
+
+    impl Clipboard for FakeClipboard {
+        fn set_text(&mut self, text: &str) -> Result<(), String> {
+            match &self.text {
+                Some(slot) => {
+                    *slot.lock().unwrap() = text.to_string();
+                    Ok(())
+                }
+                None => Err("no display".to_string()),
+            }
+        }
+    }
+
+    fn chain_app_with(clipboard: FakeClipboard) -> App {
+        let mut app = App::new(Args {
+            node: "http://localhost:8080".to_string(),
+            refresh_secs: 2,
+        });
+        app.clipboard = Box::new(clipboard);
+        app.tab = Tab::Chain;
+        app.chain_rows = vec![block_row(7, "00aa11", None)];
+        app.apply_chain_filter();
+        app
+    }
+
+    #[tokio::test]
+    async fn test_copy_selected_hash() {
+        let slot = Arc::new(Mutex::new(String::new()));
+        let mut app = chain_app_with(FakeClipboard {
+            text: Some(slot.clone()),
+        });
+
+        handle_key(&mut app, press(KeyCode::Char('y')))
+            .await
+            .unwrap();
+        assert_eq!(app.chain_status.as_deref(), Some("copied hash of block 7"));
+        assert_eq!(*slot.lock().unwrap(), "00aa11");
+
+        handle_key(&mut app, press(KeyCode::Char('Y')))
+            .await
+            .unwrap();
+        assert_eq!(
+            app.chain_status.as_deref(),
+            Some("copied previous hash of block 7")
+        );
+        assert_eq!(*slot.lock().unwrap(), "00".repeat(32));
+    }
+
+    #[tokio::test]
+    async fn test_copy_falls_back_to_a_file() {
+        let mut app = chain_app_with(FakeClipboard { text: None });
+        let fallback = std::env::temp_dir().join(format!("ledger-tui-clip-{}", std::process::id()));
+        app.clipboard_fallback = fallback.clone();
+
+        handle_key(&mut app, press(KeyCode::Char('y')))
+            .await
+            .unwrap();
+        let status = app.chain_status.clone().unwrap();
+        assert!(status.starts_with("no clipboard; copied to"), "{status}");
+        assert!(status.ends_with("hash of block 7"), "{status}");
+        assert_eq!(std::fs::read_to_string(&fallback).unwrap(), "00aa11");
+        let _ = std::fs::remove_file(fallback);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_reports_unreachable_node() {
+        // nothing listens on port 1
+        let mut app = App::new(Args {
+            node: "http://127.0.0.1:1".to_string(),
+            refresh_secs: 2,
+        });
+        tokio::time::timeout(REQUEST_TIMEOUT * 2, app.refresh_dashboard())
+            .await
+            .expect("refresh hung");
+        let status = app.node_status.clone().unwrap();
+        assert!(status.starts_with("node unreachable"), "{status}");
+        assert!(app.head.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_space_toggles_refresh_pause() {
+        let mut app = App::new(Args {
+            node: "http://localhost:8080".to_string(),
+            refresh_secs: 2,
+        });
+        let space = press(KeyCode::Char(' '));
+        assert!(!app.refresh_paused);
+        handle_key(&mut app, space).await.unwrap();
+        assert!(app.refresh_paused);
+        handle_key(&mut app, space).await.unwrap();
+        assert!(!app.refresh_paused);
+
+        // on the Mine tab a space is part of the block data
+        app.tab = Tab::Mine;
+        handle_key(&mut app, space).await.unwrap();
+        assert!(!app.refresh_paused);
+        assert_eq!(app.mine_data, " ");
+    }
+
+    #[test]
+    fn test_render_dashboard_with_and_without_stats() {
+        use ratatui::backend::TestBackend;
+
+        let mut app = App::new(Args {
+            node: "http://localhost:8080".to_string(),
+            refresh_secs: 2,
+        });
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal
+            .draw(|f| render_dashboard(f, f.area(), &app))
+            .unwrap();
+        let screen = format!("{:?}", terminal.backend().buffer());
+        assert!(screen.contains("no stats yet"));
+
+        app.stats = Some(ChainStats {
+            avg_block_time_secs: Some(12.5),
+            tip_target: Some(20),
+            recent_difficulty: vec![18, 20, 21, 20],
+        });
+        terminal
+            .draw(|f| render_dashboard(f, f.area(), &app))
+            .unwrap();
+        let screen = format!("{:?}", terminal.backend().buffer());
+        assert!(screen.contains("20 leading zero bits"));
+        assert!(screen.contains("12.5s avg"));
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(5), "5s");
+        assert_eq!(format_age(192), "3m 12s");
+        assert_eq!(format_age(3720), "1h 02m");
     }
 }